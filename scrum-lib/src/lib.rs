@@ -1,6 +1,10 @@
-use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use base64::{Engine, engine::general_purpose::STANDARD};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Tickets{
@@ -10,10 +14,133 @@ pub struct Tickets{
     pub notes: Option<Vec<Note>>,
     pub status: TicketStatus,
     pub priority: String,
+    pub assignee: Option<String>,
+    pub sprint: Option<i32>,
+    pub estimate: Option<f64>,
+    pub due: Option<NaiveDate>,
+    #[serde(default)]
+    pub kind: TicketKind,
+    pub parent: Option<i32>,
+    #[serde(default)]
+    pub links: Vec<TicketLink>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    //Values of the custom fields defined in DbSettings, by field name
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    #[serde(default)]
+    pub checklist: Vec<ChecklistItem>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub worklog: Vec<WorkLog>,
+    //Kept in the store so a running timer survives restarts
+    pub timer: Option<RunningTimer>,
+    //Template and date a recurring ticket was created for, such as "Backup@2022-06-01"
+    pub recurrence_key: Option<String>,
+    //Issue the ticket was imported from, such as "github:owner/repo#12" or "jira:OPS-42"
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub history: Vec<StatusChange>,
+    //Set on the closed tombstone left behind when the ticket is moved to another project
+    pub moved_to: Option<TicketTransfer>,
+    //Ticket this one was moved or copied from
+    pub copied_from: Option<TicketTransfer>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+//Recorded every time the status of a ticket changes
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatusChange{
+    pub status: TicketStatus,
+    pub changed_at: DateTime<Utc>,
+}
+
+//A ticket in another project, None is the database in the working directory
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TicketTransfer{
+    pub project: Option<String>,
+    pub id: i32,
+    //Key of the ticket in that project when it was transferred, such as "OPS-42"
+    pub key: String,
+    pub transferred_at: DateTime<Utc>,
+}
+
+impl TicketTransfer{
+    //Such as "ops as OPS-42"
+    pub fn describe(&self) -> String {
+        match &self.project {
+            Some(project) => format!("{} as {}", project, self.key),
+            None => format!("the working directory as {}", self.key),
+        }
+    }
+}
+
+//Typed link to another ticket, the other ticket always holds the inverse link
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TicketLink{
+    pub kind: LinkKind,
+    pub target: i32,
+}
+
+//Step of a procedure, done steps are ticked off
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChecklistItem{
+    pub text: String,
+    pub done: bool,
+}
+
+impl ChecklistItem{
+    pub fn new(text: String) -> ChecklistItem{
+        ChecklistItem{ text, done: false }
+    }
+}
+
+//File attached to a ticket, the content is stored under its hash in ATTACHMENTS_DIR
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Attachment{
+    pub name: String,
+    pub size: u64,
+    pub hash: String,
+    pub added_by: String,
+    pub added_at: DateTime<Utc>,
+}
+
+//Directory next to the database that holds the attachment contents
+pub const ATTACHMENTS_DIR: &str = "attachments";
+//Attachments are sent to and from the server in pieces of this size
+pub const ATTACHMENT_CHUNK_SIZE: usize = 64 * 1024;
+
+//Hex encoded SHA-256 of the content, used as the file name of the attachment
+pub fn attachment_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+//Human readable size such as "12.3 KB"
+pub fn format_size(size: u64) -> String {
+    match size {
+        size if size >= 1024 * 1024 => format!("{:.1} MB", size as f64 / (1024.0 * 1024.0)),
+        size if size >= 1024 => format!("{:.1} KB", size as f64 / 1024.0),
+        size => format!("{} B", size),
+    }
+}
+
+//Time spent on a ticket
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkLog{
+    pub user: String,
+    pub started_at: DateTime<Utc>,
+    pub minutes: i64,
+    pub comment: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunningTimer{
+    pub user: String,
+    pub started_at: DateTime<Utc>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Note{
     pub text: String,
@@ -30,10 +157,125 @@ impl Tickets{
             notes: None,
             status,
             priority,
+            assignee: None,
+            sprint: None,
+            estimate: None,
+            due: None,
+            kind: TicketKind::Task,
+            parent: None,
+            links: Vec::new(),
+            tags: BTreeSet::new(),
+            fields: BTreeMap::new(),
+            checklist: Vec::new(),
+            attachments: Vec::new(),
+            worklog: Vec::new(),
+            timer: None,
+            recurrence_key: None,
+            external_id: None,
+            history: Vec::new(),
+            moved_to: None,
+            copied_from: None,
             created_at,
             updated_at,
         }
     }
+    //Changes the status and keeps track of when it happened
+    pub fn set_status(&mut self, status: TicketStatus){
+        if self.status != status {
+            let now = Utc::now();
+            self.history.push(StatusChange{ status: status.clone(), changed_at: now });
+            self.status = status;
+            self.updated_at = now;
+        }
+    }
+    //The ticket as it is added to another database: ids of this database (parent, links, sprint) do not
+    //mean anything there, notes, attachments, work log and history come along
+    pub fn transferred_copy(&self, id: i32, from: TicketTransfer) -> Tickets{
+        let mut ticket = self.clone();
+        ticket.id = id;
+        ticket.parent = None;
+        ticket.links = Vec::new();
+        ticket.sprint = None;
+        ticket.timer = None;
+        ticket.recurrence_key = None;
+        ticket.moved_to = None;
+        ticket.copied_from = Some(from);
+        ticket.updated_at = Utc::now();
+        ticket
+    }
+    //What stays behind when the ticket is moved: a closed ticket with the same id and title that points
+    //to the new one, parent and links are kept so references to it still resolve. The external id is kept so
+    //importing the issue again does not bring the ticket back
+    pub fn tombstone(&self, to: TicketTransfer) -> Tickets{
+        let mut ticket = Tickets::new(self.id, self.title.clone(), String::new(), self.status.clone(), self.priority.clone(), self.created_at, self.updated_at);
+        ticket.kind = self.kind.clone();
+        ticket.parent = self.parent;
+        ticket.links = self.links.clone();
+        ticket.recurrence_key = self.recurrence_key.clone();
+        ticket.external_id = self.external_id.clone();
+        ticket.history = self.history.clone();
+        ticket.copied_from = self.copied_from.clone();
+        ticket.moved_to = Some(to);
+        ticket.set_status(TicketStatus::Closed);
+        ticket
+    }
+    //When the ticket was last closed, tickets from before the history was kept use their last update
+    pub fn closed_at(&self) -> Option<DateTime<Utc>>{
+        if self.status != TicketStatus::Closed {
+            return None;
+        }
+        self.history
+            .iter()
+            .rev()
+            .find(|change| change.status == TicketStatus::Closed)
+            .map(|change| change.changed_at)
+            .or(Some(self.updated_at))
+    }
+    //Status the ticket had at the given time, None if it did not exist yet
+    pub fn status_at(&self, time: DateTime<Utc>) -> Option<TicketStatus>{
+        if self.created_at > time {
+            return None;
+        }
+        if self.history.is_empty() {
+            return match self.closed_at() {
+                Some(closed_at) if closed_at <= time => Some(TicketStatus::Closed),
+                _ => Some(TicketStatus::Open),
+            };
+        }
+        let status = self.history
+            .iter()
+            .rev()
+            .find(|change| change.changed_at <= time)
+            .map(|change| change.status.clone())
+            .unwrap_or(TicketStatus::Open);
+        Some(status)
+    }
+    //Days until the ticket is due, negative when overdue. None for closed tickets and tickets without a due date
+    pub fn due_in(&self, today: NaiveDate) -> Option<i64>{
+        if self.status == TicketStatus::Closed {
+            return None;
+        }
+        self.due.map(|due| (due - today).num_days())
+    }
+    //Done and total number of checklist items, None if the ticket has no checklist
+    pub fn checklist_progress(&self) -> Option<(usize, usize)>{
+        if self.checklist.is_empty() {
+            return None;
+        }
+        let done = self.checklist.iter().filter(|item| item.done).count();
+        Some((done, self.checklist.len()))
+    }
+    //Total minutes logged on the ticket, the running timer is not included
+    pub fn logged_minutes(&self) -> i64{
+        self.worklog.iter().map(|entry| entry.minutes).sum()
+    }
+    //Stops the running timer and logs the time, at least one minute is logged
+    pub fn stop_timer(&mut self, comment: String, now: DateTime<Utc>){
+        if let Some(timer) = self.timer.take() {
+            let minutes = ((now - timer.started_at).num_seconds() as f64 / 60.0).round().max(1.0) as i64;
+            self.worklog.push(WorkLog{ user: timer.user, started_at: timer.started_at, minutes, comment });
+        }
+    }
     pub fn next_id(&self) -> i32{
         self.id + 1
     }
@@ -48,6 +290,24 @@ impl Tickets{
             notes: None,
             status: TicketStatus::Open,
             priority: String::from(""),
+            assignee: None,
+            sprint: None,
+            estimate: None,
+            due: None,
+            kind: TicketKind::Task,
+            parent: None,
+            links: Vec::new(),
+            tags: BTreeSet::new(),
+            fields: BTreeMap::new(),
+            checklist: Vec::new(),
+            attachments: Vec::new(),
+            worklog: Vec::new(),
+            timer: None,
+            recurrence_key: None,
+            external_id: None,
+            history: Vec::new(),
+            moved_to: None,
+            copied_from: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -60,6 +320,24 @@ impl Tickets{
             notes: None,
             status: TicketStatus::Open,
             priority: String::from("Low"),
+            assignee: None,
+            sprint: None,
+            estimate: None,
+            due: None,
+            kind: TicketKind::Task,
+            parent: None,
+            links: Vec::new(),
+            tags: BTreeSet::new(),
+            fields: BTreeMap::new(),
+            checklist: Vec::new(),
+            attachments: Vec::new(),
+            worklog: Vec::new(),
+            timer: None,
+            recurrence_key: None,
+            external_id: None,
+            history: Vec::new(),
+            moved_to: None,
+            copied_from: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -67,8 +345,155 @@ impl Tickets{
     
 }
 
+//Direct children of the given ticket
+pub fn children(id: i32, tickets: &[Tickets]) -> Vec<&Tickets> {
+    tickets.iter().filter(|ticket| ticket.parent == Some(id)).collect()
+}
+
+//Closed and total number of children, None if the ticket has no children
+pub fn progress(id: i32, tickets: &[Tickets]) -> Option<(usize, usize)> {
+    let children = children(id, tickets);
+    if children.is_empty() {
+        return None;
+    }
+    let closed = children.iter().filter(|ticket| ticket.status == TicketStatus::Closed).count();
+    Some((closed, children.len()))
+}
+
+//Whether making `parent` the parent of `id` would create a loop
+pub fn creates_cycle(id: i32, parent: i32, tickets: &[Tickets]) -> bool {
+    let mut current = Some(parent);
+    //Bounded in case the stored data already contains a loop
+    for _ in 0..=tickets.len() {
+        match current {
+            Some(ancestor) if ancestor == id => return true,
+            Some(ancestor) => current = tickets.iter().find(|ticket| ticket.id == ancestor).and_then(|ticket| ticket.parent),
+            None => return false,
+        }
+    }
+    true
+}
+
+//Adds a link from one ticket to another, or removes it if it is already there
+pub fn toggle_link(ticket: &mut Tickets, kind: LinkKind, target: i32) {
+    let link = TicketLink{ kind, target };
+    if ticket.links.contains(&link) {
+        ticket.links.retain(|existing| *existing != link);
+    } else if target != ticket.id {
+        ticket.links.push(link);
+    }
+}
+
+//Makes every other ticket hold the inverse of the given ticket's links, so links stay bidirectional.
+//The given ticket's links are taken as the truth, if it no longer exists all links to it are removed
+pub fn sync_links(id: i32, tickets: &mut [Tickets]) {
+    let links = tickets
+        .iter()
+        .find(|ticket| ticket.id == id)
+        .map(|ticket| ticket.links.clone())
+        .unwrap_or_default();
+    for ticket in tickets.iter_mut().filter(|ticket| ticket.id != id) {
+        ticket.links.retain(|link| link.target != id);
+        for link in links.iter().filter(|link| link.target == ticket.id) {
+            ticket.links.push(TicketLink{ kind: link.kind.inverse(), target: id });
+        }
+    }
+}
+
+//Minutes as "3h 20m"
+pub fn format_minutes(minutes: i64) -> String {
+    if minutes < 60 {
+        return format!("{}m", minutes);
+    }
+    format!("{}h {}m", minutes / 60, minutes % 60)
+}
+
+//Open tickets due within this many days are highlighted as due soon
+pub const DUE_SOON_DAYS: i64 = 2;
+
+//Understands "today", "tomorrow", weekdays like "fri" (the next one, today included),
+//offsets like "+3d" or "+2w" and plain dates like "2022-05-20"
+pub fn parse_due(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+    match input.as_str() {
+        "today" => return Some(today),
+        "tomorrow" | "tmr" => return Some(today + Duration::days(1)),
+        _ => {}
+    }
+    if let Some(offset) = input.strip_prefix('+') {
+        let unit = offset.chars().last()?;
+        let amount: i64 = offset[..offset.len() - unit.len_utf8()].parse().ok()?;
+        //Keeps the date arithmetic from overflowing
+        if amount.abs() > 10000 {
+            return None;
+        }
+        return match unit {
+            'd' => Some(today + Duration::days(amount)),
+            'w' => Some(today + Duration::weeks(amount)),
+            _ => None,
+        };
+    }
+    //Weekday names, full or shortened to at least three letters
+    let weekdays = [
+        ("monday", Weekday::Mon), ("tuesday", Weekday::Tue), ("wednesday", Weekday::Wed), ("thursday", Weekday::Thu),
+        ("friday", Weekday::Fri), ("saturday", Weekday::Sat), ("sunday", Weekday::Sun),
+    ];
+    let (_, weekday) = weekdays.iter().find(|(name, _)| input.len() >= 3 && name.starts_with(&input))?;
+    let days = (weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64).rem_euclid(7);
+    Some(today + Duration::days(days))
+}
+
+//Tickets at least this similar are shown as possible duplicates
+pub const DUPLICATE_THRESHOLD: f64 = 0.6;
+
+//Lowercase words without punctuation, so "Setup Zabbix!" and "setup zabbix" match
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+//Overlap of the words in both texts, from 0 (nothing shared) to 1 (same words)
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = tokens(a);
+    let b = tokens(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.iter().filter(|token| b.contains(token)).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+//Open tickets whose title or description is similar to the given ticket, most similar first
+pub fn similar_tickets<'a>(ticket: &Tickets, tickets: &'a [Tickets]) -> Vec<(&'a Tickets, f64)> {
+    let mut similar: Vec<(&Tickets, f64)> = tickets
+        .iter()
+        .filter(|other| other.id != ticket.id && other.status == TicketStatus::Open)
+        .map(|other| {
+            let score = similarity(&ticket.title, &other.title).max(similarity(&ticket.description, &other.description));
+            (other, score)
+        })
+        .filter(|(_, score)| *score >= DUPLICATE_THRESHOLD)
+        .collect();
+    similar.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    similar
+}
+
+//Sum of the estimates of the given tickets, unestimated tickets count as zero
+pub fn total_estimate(tickets: &[Tickets]) -> f64 {
+    tickets.iter().filter_map(|ticket| ticket.estimate).fold(0.0, |total, estimate| total + estimate)
+}
+
 impl Note {
-    fn new(text: String, created_at: DateTime<Utc>, updated_at: DateTime<Utc>) -> Note {
+    pub fn new(text: String, created_at: DateTime<Utc>, updated_at: DateTime<Utc>) -> Note {
         Note{
             text,
             created_at,
@@ -77,6 +502,397 @@ impl Note {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sprint{
+    pub id: i32,
+    pub name: String,
+    pub goal: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub closed: bool,
+}
+
+impl Sprint {
+    pub fn new(id: i32, name: String, goal: String, start_date: NaiveDate, end_date: NaiveDate) -> Sprint {
+        Sprint{
+            id,
+            name,
+            goal,
+            start_date,
+            end_date,
+            closed: false,
+        }
+    }
+    //The current sprint is the earliest one that has started and has not been closed yet
+    pub fn current(sprints: &[Sprint], today: NaiveDate) -> Option<&Sprint> {
+        sprints
+            .iter()
+            .filter(|sprint| !sprint.closed && sprint.start_date <= today)
+            .min_by_key(|sprint| sprint.start_date)
+    }
+    //The sprint that unfinished tickets roll into when this one is closed
+    pub fn next<'a>(&self, sprints: &'a [Sprint]) -> Option<&'a Sprint> {
+        sprints
+            .iter()
+            .filter(|sprint| !sprint.closed && sprint.id != self.id && sprint.start_date >= self.start_date)
+            .min_by_key(|sprint| sprint.start_date)
+    }
+}
+
+//Settings stored alongside each ticket database, so every project has its own
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DbSettings{
+    pub estimate_unit: EstimateUnit,
+    pub team_capacity: f64,
+    pub tags: Vec<TagDef>,
+    pub fields: Vec<FieldDef>,
+    pub backup_retention: Retention,
+    //Highest first
+    pub priorities: Vec<String>,
+    //Names shown for Open and Closed, such as "To do" and "Done". Tickets have no other states, see validate
+    pub workflow: Vec<String>,
+    //Tickets are shown as "OPS-42" with a prefix, keys with an old prefix still find the ticket
+    pub key_prefix: String,
+    pub key_aliases: Vec<String>,
+}
+
+impl Default for DbSettings {
+    fn default() -> DbSettings {
+        DbSettings{
+            estimate_unit: EstimateUnit::default(),
+            team_capacity: 0.0,
+            tags: Vec::new(),
+            fields: Vec::new(),
+            backup_retention: Retention::default(),
+            priorities: vec!["High".to_string(), "Medium".to_string(), "Low".to_string()],
+            workflow: vec!["Open".to_string(), "Closed".to_string()],
+            key_prefix: String::new(),
+            key_aliases: Vec::new(),
+        }
+    }
+}
+
+//How many snapshots of the ticket database are kept: the latest ones, and the newest of each day and of each week
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Retention{
+    pub last: usize,
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Retention {
+        Retention{ last: 5, daily: 7, weekly: 4 }
+    }
+}
+
+impl DbSettings {
+    //Adds the tag to the registry with the next color, returns false if it was already there
+    pub fn register_tag(&mut self, name: &str) -> bool {
+        if self.tags.iter().any(|tag| tag.name == name) {
+            return false;
+        }
+        let color = self.tags.len();
+        self.tags.push(TagDef{ name: name.to_string(), color });
+        true
+    }
+    pub fn tag_color(&self, name: &str) -> usize {
+        self.tags.iter().find(|tag| tag.name == name).map(|tag| tag.color).unwrap_or(0)
+    }
+    //Position in the priorities, priorities that are not configured sort last
+    pub fn priority_rank(&self, priority: &str) -> usize {
+        self.priorities.iter().position(|name| name.eq_ignore_ascii_case(priority)).unwrap_or(self.priorities.len())
+    }
+    //The configured spelling of a priority typed in any case
+    pub fn find_priority(&self, input: &str) -> Option<String> {
+        self.priorities.iter().find(|name| name.eq_ignore_ascii_case(input.trim())).cloned()
+    }
+    pub fn lowest_priority(&self) -> String {
+        self.priorities.last().cloned().unwrap_or_else(|| "Low".to_string())
+    }
+    //Settings that would be silently misread are refused when they are loaded
+    pub fn validate(&self) -> Result<(), String> {
+        match self.workflow.as_slice() {
+            [open, closed] if open.trim().is_empty() || closed.trim().is_empty() => Err("the workflow names cannot be empty".to_string()),
+            [open, closed] if open.eq_ignore_ascii_case(closed) => Err(format!("the workflow names the open and closed states both \"{}\"", open)),
+            [_, _] => Ok(()),
+            workflow => Err(format!(
+                "the workflow has {} names ({}), tickets are open or closed so it needs exactly two such as [\"To do\", \"Done\"]",
+                workflow.len(),
+                workflow.join(", ")
+            )),
+        }
+    }
+    //Status of a workflow state typed in any case, the first state is Open and the last one Closed.
+    //"Open" and "Closed" are always understood
+    pub fn find_status(&self, input: &str) -> Option<TicketStatus> {
+        let input = input.trim();
+        let is = |state: Option<&String>, status: TicketStatus| {
+            state.is_some_and(|state| state.eq_ignore_ascii_case(input)) || status.to_string().eq_ignore_ascii_case(input)
+        };
+        if is(self.workflow.first(), TicketStatus::Open) {
+            Some(TicketStatus::Open)
+        } else if is(self.workflow.last(), TicketStatus::Closed) {
+            Some(TicketStatus::Closed)
+        } else {
+            None
+        }
+    }
+    pub fn status_name(&self, status: &TicketStatus) -> String {
+        let index = TicketStatus::all().iter().position(|candidate| candidate == status).unwrap_or(0);
+        self.workflow.get(index).cloned().unwrap_or_else(|| status.to_string().to_string())
+    }
+    //"OPS-42", or just "42" when the project has no prefix
+    pub fn ticket_key(&self, id: i32) -> String {
+        if self.key_prefix.is_empty() {id.to_string()} else {format!("{}-{}", self.key_prefix, id)}
+    }
+    //For references in text: "OPS-42", or "#42" when the project has no prefix
+    pub fn ticket_ref(&self, id: i32) -> String {
+        if self.key_prefix.is_empty() {format!("#{}", id)} else {self.ticket_key(id)}
+    }
+    //Id of "42", "#42", "OPS-42" or a key with one of the old prefixes, in any case
+    pub fn find_ticket_id(&self, input: &str) -> Option<i32> {
+        match split_ticket_key(input)? {
+            (None, id) => Some(id),
+            (Some(prefix), id) => {
+                let known = std::iter::once(&self.key_prefix).chain(self.key_aliases.iter()).any(|known| !known.is_empty() && *known == prefix);
+                if known {Some(id)} else {None}
+            }
+        }
+    }
+    //The old prefix is kept as an alias, so keys already written down keep working. An empty prefix removes it
+    pub fn set_key_prefix(&mut self, prefix: &str) -> Result<(), String> {
+        let prefix = prefix.trim().to_uppercase();
+        if !prefix.is_empty() && !valid_key_prefix(&prefix) {
+            return Err(format!("\"{}\" is not a key prefix, use letters and digits starting with a letter such as OPS", prefix));
+        }
+        let old = std::mem::replace(&mut self.key_prefix, prefix.clone());
+        if !old.is_empty() && old != prefix && !self.key_aliases.contains(&old) {
+            self.key_aliases.push(old);
+        }
+        self.key_aliases.retain(|alias| *alias != prefix);
+        Ok(())
+    }
+    //Registered tags starting with the given text, used for autocomplete
+    pub fn tag_suggestions(&self, prefix: &str) -> Vec<String> {
+        let prefix = normalize_tag(prefix);
+        self.tags
+            .iter()
+            .filter(|tag| !prefix.is_empty() && tag.name.starts_with(&prefix))
+            .map(|tag| tag.name.clone())
+            .collect()
+    }
+}
+
+//Prefilled ticket for a kind of request that is filed often
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Template{
+    pub name: String,
+    //"{date}" and "{user}" are filled in when the template is used
+    pub title: String,
+    pub description: String,
+    pub priority: String,
+    pub tags: BTreeSet<String>,
+    pub checklist: Vec<String>,
+    //Templates with a recurrence are created as tickets by the scheduler
+    pub recurrence: Option<Recurrence>,
+    //No tickets are created for dates before this one
+    pub starts: Option<NaiveDate>,
+}
+
+impl Template {
+    pub fn title_for(&self, today: NaiveDate, user: &str) -> String {
+        self.title
+            .replace("{date}", &today.format("%Y-%m-%d").to_string())
+            .replace("{user}", user)
+    }
+    //Ticket for the given date, the id is given by the caller
+    pub fn to_ticket(&self, id: i32, date: NaiveDate, user: &str, settings: &DbSettings) -> Tickets {
+        let now = Utc::now();
+        //New tickets get the lowest priority unless the template names one the project has
+        let priority = settings.find_priority(&self.priority).unwrap_or_else(|| settings.lowest_priority());
+        let mut ticket = Tickets::new(id, self.title_for(date, user), self.description.clone(), TicketStatus::Open, priority, now, now);
+        ticket.tags = self.tags.iter().map(|tag| normalize_tag(tag)).collect();
+        ticket.checklist = self.checklist.iter().map(|item| ChecklistItem::new(item.clone())).collect();
+        ticket
+    }
+    //Used when no templates file has been written yet
+    pub fn defaults() -> Vec<Template> {
+        let template = |name: &str, title: &str, description: &str, priority: &str, tags: &[&str], checklist: &[&str]| Template{
+            name: name.to_string(),
+            title: title.to_string(),
+            description: description.to_string(),
+            priority: priority.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            checklist: checklist.iter().map(|item| item.to_string()).collect(),
+            recurrence: None,
+            starts: None,
+        };
+        vec![
+            template("New user onboarding", "Onboard new user ({date})", "Name:\nStart date:\nTeam:", "Medium", &["onboarding"],
+                &["Create account", "Add to groups", "Set up laptop", "Send welcome mail"]),
+            template("Server patch", "Patch servers ({date})", "Servers:\nPatch window:\nRollback plan:", "High", &["maintenance"],
+                &["Announce window", "Take snapshots", "Apply patches", "Reboot and verify services"]),
+            template("Bug report", "Bug: ", "Steps to reproduce:\n\nExpected:\n\nActual:", "Medium", &["bug"],
+                &["Reproduce", "Fix", "Verify"]),
+        ]
+    }
+}
+
+//Custom field every ticket in the database can have
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldDef{
+    pub name: String,
+    pub kind: FieldKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum FieldKind {
+    Text,
+    Number,
+    Enum(Vec<String>),
+    Date,
+    User,
+}
+
+impl FieldKind {
+    pub fn to_string(&self) -> &str {
+        match self {
+            FieldKind::Text => "Text",
+            FieldKind::Number => "Number",
+            FieldKind::Enum(_) => "Enum",
+            FieldKind::Date => "Date",
+            FieldKind::User => "User",
+        }
+    }
+}
+
+impl FieldDef {
+    //Checks a typed value and returns it the way it is stored, empty values are allowed and mean unset
+    pub fn validate(&self, value: &str, today: NaiveDate) -> Result<String, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+        match &self.kind {
+            FieldKind::Text => Ok(value.to_string()),
+            FieldKind::Number => match value.parse::<f64>() {
+                Ok(_) => Ok(value.to_string()),
+                Err(_) => Err(format!("{} must be a number", self.name)),
+            },
+            FieldKind::Enum(options) => match options.iter().find(|option| option.eq_ignore_ascii_case(value)) {
+                Some(option) => Ok(option.clone()),
+                None => Err(format!("{} must be one of {}", self.name, options.join(", "))),
+            },
+            FieldKind::Date => match parse_due(value, today) {
+                Some(date) => Ok(date.format("%Y-%m-%d").to_string()),
+                None => Err(format!("{} must be a date", self.name)),
+            },
+            FieldKind::User => {
+                if value.contains(char::is_whitespace) {
+                    Err(format!("{} must be a user name without spaces", self.name))
+                } else {
+                    Ok(value.to_string())
+                }
+            }
+        }
+    }
+}
+
+//Known tag and the index of its color in the theme palette
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TagDef{
+    pub name: String,
+    pub color: usize,
+}
+
+//Tags are lowercase without spaces, so "Customer X" becomes "customer-x"
+pub fn normalize_tag(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join("-").to_lowercase()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub enum EstimateUnit {
+    #[default]
+    Points,
+    Hours,
+}
+
+impl EstimateUnit {
+    pub fn to_string(&self) -> &str {
+        match self {
+            EstimateUnit::Points => "Story points",
+            EstimateUnit::Hours => "Hours",
+        }
+    }
+    pub fn format(&self, value: f64) -> String {
+        match self {
+            EstimateUnit::Points => format!("{} pts", value),
+            EstimateUnit::Hours => format!("{}h", value),
+        }
+    }
+    pub fn toggle(&self) -> EstimateUnit {
+        match self {
+            EstimateUnit::Points => EstimateUnit::Hours,
+            EstimateUnit::Hours => EstimateUnit::Points,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub enum TicketKind {
+    #[default]
+    Task,
+    Epic,
+}
+
+impl TicketKind {
+    pub fn to_string(&self) -> &str {
+        match self {
+            TicketKind::Task => "Task",
+            TicketKind::Epic => "Epic",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LinkKind {
+    Blocks,
+    BlockedBy,
+    RelatesTo,
+    Duplicates,
+    DuplicatedBy,
+}
+
+impl LinkKind {
+    //Kinds that can be picked when adding a link
+    pub fn all() -> Vec<LinkKind> {
+        vec![LinkKind::Blocks, LinkKind::BlockedBy, LinkKind::RelatesTo, LinkKind::Duplicates, LinkKind::DuplicatedBy]
+    }
+    //Link the other ticket holds
+    pub fn inverse(&self) -> LinkKind {
+        match self {
+            LinkKind::Blocks => LinkKind::BlockedBy,
+            LinkKind::BlockedBy => LinkKind::Blocks,
+            LinkKind::RelatesTo => LinkKind::RelatesTo,
+            LinkKind::Duplicates => LinkKind::DuplicatedBy,
+            LinkKind::DuplicatedBy => LinkKind::Duplicates,
+        }
+    }
+    pub fn to_string(&self) -> &str {
+        match self {
+            LinkKind::Blocks => "Blocks",
+            LinkKind::BlockedBy => "Blocked by",
+            LinkKind::RelatesTo => "Relates to",
+            LinkKind::Duplicates => "Duplicates",
+            LinkKind::DuplicatedBy => "Duplicated by",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum TicketStatus {
     Open,
@@ -84,6 +900,10 @@ pub enum TicketStatus {
 }
 
 impl TicketStatus {
+    //All statuses in workflow order, used for the board columns
+    pub fn all() -> Vec<TicketStatus> {
+        vec![TicketStatus::Open, TicketStatus::Closed]
+    }
     pub fn to_string(&self) -> &str {
         match self {
             TicketStatus::Open => "Open",
@@ -123,6 +943,11 @@ impl TicketPriority {
 pub struct Request{
     pub action: TicketAction,
     pub ticket: Tickets,
+    //Only used by the attachment actions
+    pub chunk: Option<AttachmentChunk>,
+    //Database on the server, None is the one in its working directory
+    #[serde(default)]
+    pub project: Option<String>,
 }
 
 //Request into <T> value
@@ -131,10 +956,40 @@ impl From<Request> for Value {
         json!({
             "action": request.action.to_string(),
             "ticket": request.ticket,
+            "chunk": request.chunk,
+            "project": request.project,
         })
     }
 }
 
+//Piece of an attachment sent over the connection, the data is base64 encoded
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AttachmentChunk{
+    pub hash: String,
+    pub index: usize,
+    pub total: usize,
+    pub data: String,
+}
+
+impl AttachmentChunk {
+    //Splits the content into chunks, empty content is still sent as one chunk
+    pub fn split(hash: &str, data: &[u8]) -> Vec<AttachmentChunk> {
+        let pieces: Vec<&[u8]> = if data.is_empty() {vec![data]} else {data.chunks(ATTACHMENT_CHUNK_SIZE).collect()};
+        let total = pieces.len();
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(index, piece)| AttachmentChunk{ hash: hash.to_string(), index, total, data: STANDARD.encode(piece) })
+            .collect()
+    }
+    pub fn bytes(&self) -> Result<Vec<u8>, String> {
+        STANDARD.decode(&self.data).map_err(|error| format!("Chunk {} of {} is not valid base64: {}", self.index, self.hash, error))
+    }
+    pub fn is_last(&self) -> bool {
+        self.index + 1 >= self.total
+    }
+}
+
 
 
 
@@ -144,6 +999,12 @@ pub enum TicketAction {
     Update,
     Delete,
     UpdateDb,
+    //The ticket holds the attachment's metadata, chunks are sent in order
+    UploadChunk,
+    //The server answers with the chunks of the attachment named by the chunk's hash
+    Download,
+    //The server answers with the settings of the project, so keys and priorities match its tickets
+    Settings,
 }
 
 impl TicketAction {
@@ -153,6 +1014,945 @@ impl TicketAction {
             TicketAction::Update => "Update",
             TicketAction::Delete => "Delete",
             TicketAction::UpdateDb => "UpdateDb",
+            TicketAction::UploadChunk => "UploadChunk",
+            TicketAction::Download => "Download",
+            TicketAction::Settings => "Settings",
         }   
     }
-}
\ No newline at end of file
+}
+
+//How often a recurring template is turned into a ticket
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    Weekly(Weekday),
+    //Day of the month, months that are too short use their last day
+    Monthly(u32),
+    //"minute hour day-of-month month day-of-week", only the date fields are used
+    Cron(String),
+}
+
+//Oldest missed occurrence the scheduler still creates a ticket for
+pub const RECURRENCE_LOOKBACK_DAYS: i64 = 366;
+
+impl Recurrence {
+    pub fn describe(&self) -> String {
+        match self {
+            Recurrence::Daily => "Daily".to_string(),
+            Recurrence::Weekly(day) => format!("Weekly on {}", day),
+            Recurrence::Monthly(day) => format!("Monthly on day {}", day),
+            Recurrence::Cron(expression) => format!("Cron {}", expression),
+        }
+    }
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Recurrence::Monthly(day) if *day < 1 || *day > 31 => Err(format!("{} is not a day of the month", day)),
+            Recurrence::Cron(expression) => Cron::parse(expression).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+    //Whether a ticket is due on the given date, invalid cron expressions never match
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        match self {
+            Recurrence::Daily => true,
+            Recurrence::Weekly(day) => date.weekday() == *day,
+            Recurrence::Monthly(day) => {
+                let last_day = last_day_of_month(date);
+                date.day() == (*day).min(last_day)
+            }
+            Recurrence::Cron(expression) => Cron::parse(expression).is_ok_and(|cron| cron.matches(date)),
+        }
+    }
+    //Latest date on or before today that matches, not before the start date
+    pub fn last_occurrence(&self, starts: Option<NaiveDate>, today: NaiveDate) -> Option<NaiveDate> {
+        let mut date = today;
+        for _ in 0..=RECURRENCE_LOOKBACK_DAYS {
+            if starts.is_some_and(|starts| date < starts) {
+                return None;
+            }
+            if self.matches(date) {
+                return Some(date);
+            }
+            date = date.pred_opt()?;
+        }
+        None
+    }
+    //First date after the given one that matches, searched up to a few years ahead
+    pub fn next_occurrence(&self, after: NaiveDate) -> Option<NaiveDate> {
+        let mut date = after;
+        for _ in 0..(RECURRENCE_LOOKBACK_DAYS * 5) {
+            date = date.succ_opt()?;
+            if self.matches(date) {
+                return Some(date);
+            }
+        }
+        None
+    }
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 {(date.year() + 1, 1)} else {(date.year(), date.month() + 1)};
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(28)
+}
+
+//Date fields of a cron expression, each is the list of allowed values or None for "*"
+struct Cron {
+    days: Option<Vec<u32>>,
+    months: Option<Vec<u32>>,
+    weekdays: Option<Vec<u32>>,
+}
+
+impl Cron {
+    fn parse(expression: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("\"{}\" needs 5 fields: minute hour day month weekday", expression));
+        }
+        //Minute and hour are checked but not used, tickets are created per day
+        cron_field(fields[0], 0, 59)?;
+        cron_field(fields[1], 0, 23)?;
+        let weekdays = cron_field(fields[4], 0, 7)?
+            .map(|days| days.into_iter().map(|day| day % 7).collect());
+        Ok(Cron{
+            days: cron_field(fields[2], 1, 31)?,
+            months: cron_field(fields[3], 1, 12)?,
+            weekdays,
+        })
+    }
+    fn matches(&self, date: NaiveDate) -> bool {
+        let allows = |values: &Option<Vec<u32>>, value: u32| values.as_ref().is_none_or(|values| values.contains(&value));
+        if !allows(&self.months, date.month()) {
+            return false;
+        }
+        let day = allows(&self.days, date.day());
+        let weekday = allows(&self.weekdays, date.weekday().num_days_from_sunday());
+        //Like cron, when both day fields are restricted either of them is enough
+        if self.days.is_some() && self.weekdays.is_some() {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+//Parses "*", "5", "1-5", "*/2", "1-10/3" and comma separated lists of them
+fn cron_field(field: &str, min: u32, max: u32) -> Result<Option<Vec<u32>>, String> {
+    if field == "*" {
+        return Ok(None);
+    }
+    let mut values = Vec::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or(format!("Bad step in \"{}\"", part))?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (cron_value(start, min, max)?, cron_value(end, min, max)?)
+        } else {
+            let value = cron_value(range, min, max)?;
+            (value, value)
+        };
+        if start > end {
+            return Err(format!("Bad range \"{}\"", range));
+        }
+        values.extend((start..=end).step_by(step as usize));
+    }
+    Ok(Some(values))
+}
+
+fn cron_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(number) if number >= min && number <= max => Ok(number),
+        _ => Err(format!("\"{}\" is not between {} and {}", value, min, max)),
+    }
+}
+
+//Key that marks the ticket created for a template on a date
+pub fn recurrence_key(template: &Template, date: NaiveDate) -> String {
+    format!("{}@{}", template.name, date.format("%Y-%m-%d"))
+}
+
+//Creates the latest due ticket of every recurring template. Tickets that were already
+//created carry the same key, so running this again does not create duplicates.
+//Returns the ids of the created tickets, which come from the database's next id so deleted ids are not used again
+pub fn create_recurring(templates: &[Template], db: &mut TicketDb, settings: &DbSettings, today: NaiveDate, user: &str) -> Vec<i32> {
+    let mut created = Vec::new();
+    for template in templates {
+        let recurrence = match &template.recurrence {
+            Some(recurrence) if recurrence.validate().is_ok() => recurrence,
+            _ => continue,
+        };
+        let date = match recurrence.last_occurrence(template.starts, today) {
+            Some(date) => date,
+            None => continue,
+        };
+        let key = recurrence_key(template, date);
+        if db.tickets.iter().any(|ticket| ticket.recurrence_key.as_deref() == Some(key.as_str())) {
+            continue;
+        }
+        let id = db.next_free_id();
+        let mut ticket = template.to_ticket(id, date, user, settings);
+        ticket.recurrence_key = Some(key);
+        db.tickets.push(ticket);
+        db.next_id = id + 1;
+        created.push(id);
+    }
+    created
+}
+
+//Version of the ticket database layout, raised together with a new entry in MIGRATIONS
+pub const SCHEMA_VERSION: u32 = 2;
+
+//What is stored in the ticket database file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TicketDb{
+    pub schema_version: u32,
+    //Ids of deleted tickets are not handed out again
+    pub next_id: i32,
+    pub created: DateTime<Utc>,
+    pub tickets: Vec<Tickets>,
+}
+
+impl TicketDb {
+    pub fn new(tickets: Vec<Tickets>) -> TicketDb {
+        let mut db = TicketDb{ schema_version: SCHEMA_VERSION, next_id: 0, created: Utc::now(), tickets: Vec::new() };
+        db.set_tickets(tickets);
+        db
+    }
+    //Id for the next new ticket
+    pub fn next_free_id(&self) -> i32 {
+        let max_id = self.tickets.iter().map(|ticket| ticket.id).max().unwrap_or(0);
+        self.next_id.max(max_id + 1)
+    }
+    pub fn set_tickets(&mut self, tickets: Vec<Tickets>) {
+        self.tickets = tickets;
+        self.next_id = self.next_free_id();
+    }
+    //Stores a new ticket under the next id, used by the command line and the server alike. Its links are
+    //mirrored on the linked tickets and its tags are registered, returns whether the settings changed
+    pub fn create_ticket(&mut self, ticket: &mut Tickets, settings: &mut DbSettings) -> bool {
+        ticket.id = self.next_free_id();
+        self.next_id = ticket.id + 1;
+        let mut registered = false;
+        for tag in ticket.tags.iter() {
+            registered |= settings.register_tag(tag);
+        }
+        self.tickets.push(ticket.clone());
+        sync_links(ticket.id, &mut self.tickets);
+        registered
+    }
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    //Written by a newer version of the program, which this one cannot read
+    Newer(u32),
+    Invalid(String),
+}
+
+//Upgrades the file from the version it is at to the next one, the first entry upgrades version 1
+const MIGRATIONS: [fn(Value) -> Result<Value, String>; 1] = [migrate_v1];
+
+//Version 1 files are the bare list of tickets, anything else says which version it is
+pub fn schema_version(value: &Value) -> u32 {
+    match value {
+        Value::Array(_) => 1,
+        value => value.get("schema_version").and_then(|version| version.as_u64()).unwrap_or(0) as u32,
+    }
+}
+
+//Reads a database file of any known version, also returns the version it was at
+pub fn parse_ticket_db(content: &str) -> Result<(TicketDb, u32), MigrationError> {
+    let mut value: Value = serde_json::from_str(content).map_err(|error| MigrationError::Invalid(error.to_string()))?;
+    let found = schema_version(&value);
+    if found > SCHEMA_VERSION {
+        return Err(MigrationError::Newer(found));
+    }
+    if found == 0 {
+        return Err(MigrationError::Invalid("the file has no schema_version".to_string()));
+    }
+    for version in found..SCHEMA_VERSION {
+        value = MIGRATIONS[version as usize - 1](value).map_err(|error| MigrationError::Invalid(format!("upgrading from version {}: {}", version, error)))?;
+    }
+    let db = serde_json::from_value(value).map_err(|error| MigrationError::Invalid(error.to_string()))?;
+    Ok((db, found))
+}
+
+//The list of tickets goes into the envelope, the database is as old as its oldest ticket
+fn migrate_v1(value: Value) -> Result<Value, String> {
+    let mut tickets = match value {
+        Value::Array(tickets) => tickets,
+        _ => return Err("expected a list of tickets".to_string()),
+    };
+    for ticket in tickets.iter_mut() {
+        let ticket = ticket.as_object_mut().ok_or("expected a ticket")?;
+        //Files from before notes were added
+        ticket.entry("notes").or_insert(Value::Null);
+    }
+    let next_id = tickets.iter().filter_map(|ticket| ticket.get("id").and_then(|id| id.as_i64())).max().unwrap_or(0) + 1;
+    let created = tickets
+        .iter()
+        .filter_map(|ticket| ticket.get("created_at").and_then(|time| time.as_str()))
+        .filter_map(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .min()
+        .unwrap_or_else(Utc::now);
+    Ok(json!({
+        "schema_version": 2,
+        "next_id": next_id,
+        "created": created,
+        "tickets": tickets,
+    }))
+}
+
+//Copy of the file taken before it is upgraded, such as "ticketdb.json.v1.bak"
+pub fn migration_backup_name(file: &str, version: u32) -> String {
+    format!("{}.v{}.bak", file, version)
+}
+
+pub fn valid_key_prefix(prefix: &str) -> bool {
+    prefix.starts_with(|c: char| c.is_ascii_alphabetic()) && prefix.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+//Splits "ops-42" into "OPS" and 42, "42" and "#42" have no prefix
+pub fn split_ticket_key(input: &str) -> Option<(Option<String>, i32)> {
+    let input = input.trim();
+    let input = input.strip_prefix('#').unwrap_or(input);
+    match input.rsplit_once('-') {
+        Some((prefix, number)) if valid_key_prefix(prefix) => Some((Some(prefix.to_uppercase()), number.parse().ok()?)),
+        Some(_) => None,
+        None => Some((None, input.parse().ok()?)),
+    }
+}
+
+//Entry of the project registry: a name and the directory holding its database, settings and templates
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Project{
+    pub name: String,
+    pub path: PathBuf,
+}
+
+//scrum-server keeps the database of each project in a directory of this name
+pub const PROJECTS_DIR: &str = "projects";
+
+//Project names become directory names on the server, so only letters, digits, '-' and '_' are allowed
+pub fn valid_project_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+//Snapshots of the ticket database are kept in this directory next to it
+pub const BACKUPS_DIR: &str = "backups";
+
+//Snapshot file, named after the local time it was taken such as "ticketdb-20220511-040701-123.json".
+//The milliseconds keep two snapshots taken in the same second apart
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot{
+    pub name: String,
+    pub taken_at: NaiveDateTime,
+}
+
+impl Snapshot {
+    pub fn new(taken_at: NaiveDateTime) -> Snapshot {
+        Snapshot{ name: format!("ticketdb-{}.json", taken_at.format("%Y%m%d-%H%M%S-%3f")), taken_at }
+    }
+    //None for files in the directory that are not snapshots. Older snapshots have no milliseconds
+    pub fn from_name(name: &str) -> Option<Snapshot> {
+        let stamp = name.strip_prefix("ticketdb-")?.strip_suffix(".json")?;
+        let taken_at = NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S-%3f")
+            .or_else(|_| NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S")).ok()?;
+        Some(Snapshot{ name: name.to_string(), taken_at })
+    }
+}
+
+//Snapshots the retention does not keep: everything but the `last` newest ones, the newest of the
+//last `daily` days and the newest of the last `weekly` weeks that have snapshots
+pub fn expired_snapshots(snapshots: &[Snapshot], retention: &Retention) -> Vec<Snapshot> {
+    let mut newest_first = snapshots.to_vec();
+    newest_first.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.taken_at));
+
+    let mut keep: Vec<String> = newest_first.iter().take(retention.last).map(|snapshot| snapshot.name.clone()).collect();
+    let mut days = Vec::new();
+    let mut weeks = Vec::new();
+    for snapshot in newest_first.iter() {
+        let day = snapshot.taken_at.date();
+        let week = day.iso_week();
+        if !days.contains(&day) && days.len() < retention.daily {
+            days.push(day);
+            keep.push(snapshot.name.clone());
+        }
+        if !weeks.contains(&week) && weeks.len() < retention.weekly {
+            weeks.push(week);
+            keep.push(snapshot.name.clone());
+        }
+    }
+    newest_first.into_iter().filter(|snapshot| !keep.contains(&snapshot.name)).collect()
+}
+
+//How a ticket differs between a snapshot and now
+#[derive(Clone, Debug, PartialEq)]
+pub enum TicketChange {
+    //Created after the snapshot
+    Added,
+    //Deleted after the snapshot
+    Removed,
+    //Names of the fields that changed
+    Changed(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub struct TicketDiff{
+    pub id: i32,
+    pub title: String,
+    pub change: TicketChange,
+}
+
+//Differences by ticket id, fields are compared the way they are stored
+pub fn diff_tickets(snapshot: &[Tickets], current: &[Tickets]) -> Vec<TicketDiff> {
+    let mut ids: Vec<i32> = snapshot.iter().chain(current.iter()).map(|ticket| ticket.id).collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut diffs = Vec::new();
+    for id in ids {
+        let before = snapshot.iter().find(|ticket| ticket.id == id);
+        let after = current.iter().find(|ticket| ticket.id == id);
+        let (title, change) = match (before, after) {
+            (None, Some(after)) => (after.title.clone(), TicketChange::Added),
+            (Some(before), None) => (before.title.clone(), TicketChange::Removed),
+            (Some(before), Some(after)) => {
+                let before_fields = serde_json::to_value(before).unwrap_or(Value::Null);
+                let after_fields = serde_json::to_value(after).unwrap_or(Value::Null);
+                let mut changed: Vec<String> = match (before_fields.as_object(), after_fields.as_object()) {
+                    (Some(before_fields), Some(after_fields)) => after_fields
+                        .iter()
+                        .filter(|(name, value)| before_fields.get(*name) != Some(*value))
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                //Every change touches updated_at, it only counts when nothing else changed
+                if changed.len() > 1 {
+                    changed.retain(|name| name != "updated_at");
+                }
+                if changed.is_empty() {
+                    continue;
+                }
+                (after.title.clone(), TicketChange::Changed(changed))
+            }
+            (None, None) => continue,
+        };
+        diffs.push(TicketDiff{ id, title, change });
+    }
+    diffs
+}
+
+impl TicketDiff {
+    //One line such as "~ 12 Zabbix Setup: status, notes"
+    pub fn describe(&self, settings: &DbSettings) -> String {
+        let key = settings.ticket_key(self.id);
+        match &self.change {
+            TicketChange::Added => format!("+ {} {} (created since)", key, self.title),
+            TicketChange::Removed => format!("- {} {} (deleted since)", key, self.title),
+            TicketChange::Changed(fields) => format!("~ {} {}: {}", key, self.title, fields.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    fn ticket(id: i32, title: &str, description: &str) -> Tickets {
+        Tickets{ id, title: title.to_string(), description: description.to_string(), ..Tickets::default() }
+    }
+
+    //Snapshots
+
+    #[test]
+    fn snapshots_in_the_same_second_have_different_names() {
+        let first = Snapshot::new(at("2022-05-11", "04:07:01.123"));
+        let second = Snapshot::new(at("2022-05-11", "04:07:01.124"));
+        assert_eq!(first.name, "ticketdb-20220511-040701-123.json");
+        assert_ne!(first.name, second.name);
+    }
+
+    #[test]
+    fn snapshot_names_are_read_back() {
+        let snapshot = Snapshot::new(at("2022-05-11", "04:07:01.123"));
+        assert_eq!(Snapshot::from_name(&snapshot.name), Some(snapshot));
+    }
+
+    #[test]
+    fn old_snapshot_names_without_milliseconds_are_read() {
+        let snapshot = Snapshot::from_name("ticketdb-20220511-040701.json").unwrap();
+        assert_eq!(snapshot.taken_at, at("2022-05-11", "04:07:01"));
+    }
+
+    #[test]
+    fn other_files_are_not_snapshots() {
+        assert_eq!(Snapshot::from_name("notes.txt"), None);
+        assert_eq!(Snapshot::from_name("ticketdb-yesterday.json"), None);
+    }
+
+    fn names(snapshots: &[Snapshot]) -> Vec<&str> {
+        snapshots.iter().map(|snapshot| snapshot.name.as_str()).collect()
+    }
+
+    fn snapshots(times: &[(&str, &str)]) -> Vec<Snapshot> {
+        times.iter().map(|(date, time)| Snapshot::new(at(date, time))).collect()
+    }
+
+    #[test]
+    fn retention_keeps_the_last_snapshots() {
+        let all = snapshots(&[("2022-05-11", "09:00:00"), ("2022-05-11", "10:00:00"), ("2022-05-11", "11:00:00"), ("2022-05-11", "12:00:00")]);
+        let expired = expired_snapshots(&all, &Retention{ last: 2, daily: 0, weekly: 0 });
+        assert_eq!(names(&expired), vec![all[1].name.as_str(), all[0].name.as_str()]);
+    }
+
+    #[test]
+    fn retention_keeps_the_newest_snapshot_of_each_day() {
+        let all = snapshots(&[
+            ("2022-05-09", "09:00:00"),
+            ("2022-05-09", "10:00:00"),
+            ("2022-05-10", "09:00:00"),
+            ("2022-05-10", "10:00:00"),
+            ("2022-05-11", "09:00:00"),
+        ]);
+        let expired = expired_snapshots(&all, &Retention{ last: 1, daily: 2, weekly: 0 });
+        assert_eq!(names(&expired), vec![all[2].name.as_str(), all[1].name.as_str(), all[0].name.as_str()]);
+    }
+
+    #[test]
+    fn retention_keeps_the_newest_snapshot_of_each_week() {
+        //Weeks 18, 18, 20 and 21, week 19 has no snapshots and is not counted
+        let all = snapshots(&[("2022-05-02", "09:00:00"), ("2022-05-04", "09:00:00"), ("2022-05-16", "09:00:00"), ("2022-05-23", "09:00:00")]);
+        let expired = expired_snapshots(&all, &Retention{ last: 0, daily: 0, weekly: 3 });
+        assert_eq!(names(&expired), vec![all[0].name.as_str()]);
+    }
+
+    #[test]
+    fn retention_edge_cases() {
+        let all = snapshots(&[("2022-05-10", "09:00:00"), ("2022-05-11", "09:00:00")]);
+        assert!(expired_snapshots(&[], &Retention::default()).is_empty());
+        //Fewer snapshots than the retention keeps
+        assert!(expired_snapshots(&all, &Retention::default()).is_empty());
+        //Keeping nothing expires everything
+        assert_eq!(expired_snapshots(&all, &Retention{ last: 0, daily: 0, weekly: 0 }).len(), 2);
+    }
+
+    #[test]
+    fn diff_finds_added_removed_and_changed_tickets() {
+        let before = vec![ticket(1, "Backup", ""), ticket(2, "Zabbix Setup", ""), ticket(3, "Standup", "")];
+        let mut after = vec![before[1].clone(), before[2].clone(), ticket(4, "Printer", "")];
+        after[0].description = "Install the agent".to_string();
+        after[0].status = TicketStatus::Closed;
+        after[0].updated_at = after[0].updated_at + Duration::minutes(5);
+
+        let diffs = diff_tickets(&before, &after);
+        let changes: Vec<(i32, &str, TicketChange)> = diffs.iter().map(|diff| (diff.id, diff.title.as_str(), diff.change.clone())).collect();
+        assert_eq!(changes, vec![
+            (1, "Backup", TicketChange::Removed),
+            (2, "Zabbix Setup", TicketChange::Changed(vec!["description".to_string(), "status".to_string()])),
+            (4, "Printer", TicketChange::Added),
+        ]);
+        assert_eq!(diffs[1].describe(&DbSettings::default()), "~ 2 Zabbix Setup: description, status");
+    }
+
+    #[test]
+    fn diff_counts_updated_at_only_when_nothing_else_changed() {
+        let before = vec![ticket(1, "Backup", "")];
+        let mut after = before.clone();
+        after[0].updated_at = after[0].updated_at + Duration::minutes(5);
+        assert_eq!(diff_tickets(&before, &after)[0].change, TicketChange::Changed(vec!["updated_at".to_string()]));
+        assert!(diff_tickets(&before, &before).is_empty());
+    }
+
+    //Recurring tickets
+
+    #[test]
+    fn recurring_tickets_do_not_reuse_deleted_ids() {
+        let template = Template{ title: "Standup {date}".to_string(), recurrence: Some(Recurrence::Daily), ..Template::default() };
+        let mut db = TicketDb::new(vec![Tickets{ id: 1, ..Tickets::default() }]);
+        //Tickets 2 to 9 were deleted
+        db.next_id = 10;
+        let today = NaiveDate::from_ymd_opt(2022, 5, 11).unwrap();
+        let settings = DbSettings::default();
+
+        assert_eq!(create_recurring(std::slice::from_ref(&template), &mut db, &settings, today, "ann"), vec![10]);
+        assert_eq!(db.next_id, 11);
+        assert_eq!(db.tickets.last().unwrap().title, "Standup 2022-05-11");
+        //Already created for today
+        assert!(create_recurring(&[template], &mut db, &settings, today, "ann").is_empty());
+    }
+
+    //Duplicates
+
+    #[test]
+    fn similarity_ignores_case_punctuation_and_repeated_words() {
+        assert_eq!(similarity("Setup Zabbix!", "setup zabbix"), 1.0);
+        assert_eq!(similarity("zabbix zabbix setup", "Setup, Zabbix"), 1.0);
+    }
+
+    #[test]
+    fn similarity_is_the_share_of_common_words() {
+        assert_eq!(similarity("fix login page", "fix signup page"), 0.5);
+        assert_eq!(similarity("fix login", "order coffee"), 0.0);
+    }
+
+    #[test]
+    fn empty_texts_are_not_similar() {
+        assert_eq!(similarity("", ""), 0.0);
+        assert_eq!(similarity("!!!", "fix login"), 0.0);
+    }
+
+    #[test]
+    fn similar_tickets_are_open_others_above_the_threshold() {
+        let new = ticket(1, "Login page is broken", "");
+        let mut closed = ticket(3, "Login page is broken", "");
+        closed.status = TicketStatus::Closed;
+        let tickets = vec![
+            new.clone(),
+            ticket(2, "login page broken", ""),
+            closed,
+            ticket(4, "Order coffee", ""),
+            ticket(5, "Broken login page is", ""),
+        ];
+        let similar: Vec<i32> = similar_tickets(&new, &tickets).iter().map(|(ticket, _)| ticket.id).collect();
+        assert_eq!(similar, vec![5, 2]);
+    }
+
+    #[test]
+    fn similar_descriptions_count_too() {
+        let new = ticket(1, "Printer", "The printer on floor two jams");
+        let tickets = vec![ticket(2, "Paper stuck", "the printer on floor two jams")];
+        assert_eq!(similar_tickets(&new, &tickets).len(), 1);
+    }
+
+    //Due dates
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn due_dates_relative_to_today() {
+        //A Wednesday
+        let today = date(2022, 5, 11);
+        assert_eq!(parse_due("today", today), Some(today));
+        assert_eq!(parse_due(" Tomorrow ", today), Some(date(2022, 5, 12)));
+        assert_eq!(parse_due("tmr", today), Some(date(2022, 5, 12)));
+        assert_eq!(parse_due("+3d", today), Some(date(2022, 5, 14)));
+        assert_eq!(parse_due("+2w", today), Some(date(2022, 5, 25)));
+        assert_eq!(parse_due("2022-06-01", today), Some(date(2022, 6, 1)));
+    }
+
+    #[test]
+    fn due_dates_across_month_and_year_ends() {
+        assert_eq!(parse_due("tomorrow", date(2022, 1, 31)), Some(date(2022, 2, 1)));
+        assert_eq!(parse_due("+1d", date(2024, 2, 28)), Some(date(2024, 2, 29)));
+        assert_eq!(parse_due("+1d", date(2023, 2, 28)), Some(date(2023, 3, 1)));
+        assert_eq!(parse_due("+1w", date(2022, 12, 28)), Some(date(2023, 1, 4)));
+        assert_eq!(parse_due("2024-02-29", date(2024, 1, 1)), Some(date(2024, 2, 29)));
+        assert_eq!(parse_due("2023-02-29", date(2023, 1, 1)), None);
+    }
+
+    #[test]
+    fn weekdays_are_the_next_one_with_today_included() {
+        let wednesday = date(2022, 5, 11);
+        assert_eq!(parse_due("wed", wednesday), Some(wednesday));
+        assert_eq!(parse_due("FRI", wednesday), Some(date(2022, 5, 13)));
+        assert_eq!(parse_due("monday", wednesday), Some(date(2022, 5, 16)));
+        //Across the end of the month
+        assert_eq!(parse_due("tue", date(2022, 5, 30)), Some(date(2022, 5, 31)));
+        assert_eq!(parse_due("thu", date(2022, 5, 30)), Some(date(2022, 6, 2)));
+    }
+
+    #[test]
+    fn unknown_due_dates_are_refused() {
+        let today = date(2022, 5, 11);
+        for input in ["", "fr", "someday", "+", "+d", "+3x", "+99999d", "2022-13-01"] {
+            assert_eq!(parse_due(input, today), None, "{}", input);
+        }
+    }
+
+    //Recurrence
+
+    #[test]
+    fn monthly_on_a_late_day_uses_the_last_day_of_short_months() {
+        let monthly = Recurrence::Monthly(31);
+        assert!(monthly.matches(date(2022, 1, 31)));
+        assert!(!monthly.matches(date(2022, 1, 30)));
+        assert!(monthly.matches(date(2022, 4, 30)));
+        assert_eq!(monthly.next_occurrence(date(2022, 1, 31)), Some(date(2022, 2, 28)));
+        assert_eq!(monthly.next_occurrence(date(2024, 1, 31)), Some(date(2024, 2, 29)));
+        assert_eq!(monthly.next_occurrence(date(2024, 2, 29)), Some(date(2024, 3, 31)));
+        assert_eq!(monthly.next_occurrence(date(2022, 12, 31)), Some(date(2023, 1, 31)));
+    }
+
+    #[test]
+    fn weekly_and_daily_next_runs() {
+        assert_eq!(Recurrence::Weekly(Weekday::Mon).next_occurrence(date(2022, 5, 16)), Some(date(2022, 5, 23)));
+        assert_eq!(Recurrence::Daily.next_occurrence(date(2022, 12, 31)), Some(date(2023, 1, 1)));
+    }
+
+    #[test]
+    fn days_are_not_skipped_or_repeated_at_daylight_saving_changes() {
+        //Clocks change in Europe on these Sundays, occurrences are per date so they still come once
+        let sundays = Recurrence::Weekly(Weekday::Sun);
+        assert_eq!(sundays.next_occurrence(date(2022, 3, 20)), Some(date(2022, 3, 27)));
+        assert_eq!(sundays.next_occurrence(date(2022, 3, 27)), Some(date(2022, 4, 3)));
+        let cron = Recurrence::Cron("30 2 * * *".to_string());
+        assert_eq!(cron.next_occurrence(date(2022, 10, 29)), Some(date(2022, 10, 30)));
+        assert_eq!(cron.next_occurrence(date(2022, 10, 30)), Some(date(2022, 10, 31)));
+    }
+
+    #[test]
+    fn last_occurrence_is_not_before_the_start() {
+        let monthly = Recurrence::Monthly(31);
+        assert_eq!(monthly.last_occurrence(None, date(2022, 3, 15)), Some(date(2022, 2, 28)));
+        assert_eq!(monthly.last_occurrence(Some(date(2022, 3, 1)), date(2022, 3, 15)), None);
+        assert_eq!(Recurrence::Daily.last_occurrence(Some(date(2022, 3, 15)), date(2022, 3, 15)), Some(date(2022, 3, 15)));
+    }
+
+    #[test]
+    fn cron_weekdays_ranges_and_steps() {
+        let workdays = Recurrence::Cron("0 9 * * 1-5".to_string());
+        //Friday to Monday
+        assert_eq!(workdays.next_occurrence(date(2022, 5, 13)), Some(date(2022, 5, 16)));
+        let twice_a_month = Recurrence::Cron("0 0 1,15 * *".to_string());
+        assert_eq!(twice_a_month.next_occurrence(date(2022, 5, 1)), Some(date(2022, 5, 15)));
+        assert_eq!(twice_a_month.next_occurrence(date(2022, 12, 15)), Some(date(2023, 1, 1)));
+        let every_other_day = Recurrence::Cron("0 0 */2 * *".to_string());
+        assert!(every_other_day.matches(date(2022, 5, 1)));
+        assert!(!every_other_day.matches(date(2022, 5, 2)));
+        //7 is Sunday too
+        assert!(Recurrence::Cron("0 0 * * 7".to_string()).matches(date(2022, 5, 15)));
+    }
+
+    #[test]
+    fn cron_with_day_and_weekday_runs_on_either() {
+        let cron = Recurrence::Cron("0 0 13 * 5".to_string());
+        //Friday the 6th and Friday the 13th, Monday the 13th
+        assert!(cron.matches(date(2022, 5, 6)));
+        assert!(cron.matches(date(2022, 5, 13)));
+        assert!(cron.matches(date(2022, 6, 13)));
+        assert!(!cron.matches(date(2022, 5, 12)));
+    }
+
+    #[test]
+    fn cron_on_february_29_waits_for_a_leap_year() {
+        let cron = Recurrence::Cron("0 0 29 2 *".to_string());
+        assert_eq!(cron.next_occurrence(date(2022, 3, 1)), Some(date(2024, 2, 29)));
+    }
+
+    #[test]
+    fn invalid_recurrences_are_refused_and_never_match() {
+        for expression in ["* * *", "0 0 32 * *", "0 0 */0 * *", "0 0 5-1 * *", "60 0 * * *", "0 0 * 13 *", "0 0 * * mon"] {
+            let cron = Recurrence::Cron(expression.to_string());
+            assert!(cron.validate().is_err(), "{}", expression);
+            assert!(!cron.matches(date(2022, 5, 11)), "{}", expression);
+        }
+        assert!(Recurrence::Monthly(0).validate().is_err());
+        assert!(Recurrence::Monthly(32).validate().is_err());
+        assert!(Recurrence::Monthly(31).validate().is_ok());
+    }
+
+
+    //Migration
+
+    //A file as the first version wrote it, only these fields existed then
+    const V1_DB: &str = r#"[{"id":3,"title":"Zabbix Setup","description":"Setup Zabbix","status":"Open","priority":"Low","created_at":"2022-05-11T04:07:01.119896Z","updated_at":"2022-05-11T04:07:01.119897100Z"},{"id":7,"title":"Backup","description":"","status":"Closed","priority":"High","created_at":"2022-04-01T10:00:00Z","updated_at":"2022-04-02T10:00:00Z"}]"#;
+
+    #[test]
+    fn version_1_list_is_wrapped_in_the_envelope() {
+        let (db, found) = parse_ticket_db(V1_DB).unwrap();
+        assert_eq!(found, 1);
+        assert_eq!(db.schema_version, SCHEMA_VERSION);
+        assert_eq!(db.next_id, 8);
+        assert_eq!(db.created, DateTime::parse_from_rfc3339("2022-04-01T10:00:00Z").unwrap());
+        assert_eq!(db.tickets.iter().map(|ticket| ticket.id).collect::<Vec<_>>(), vec![3, 7]);
+        assert_eq!((db.tickets[1].status.clone(), db.tickets[1].priority.as_str()), (TicketStatus::Closed, "High"));
+        //Fields added later start out empty
+        let ticket = &db.tickets[0];
+        assert!(ticket.notes.is_none());
+        assert!(ticket.assignee.is_none() && ticket.sprint.is_none() && ticket.estimate.is_none() && ticket.due.is_none());
+        assert!(ticket.tags.is_empty() && ticket.links.is_empty() && ticket.checklist.is_empty() && ticket.history.is_empty());
+        assert!(ticket.external_id.is_none() && ticket.moved_to.is_none());
+    }
+
+    #[test]
+    fn empty_version_1_list_starts_at_id_1() {
+        let (db, found) = parse_ticket_db("[]").unwrap();
+        assert_eq!(found, 1);
+        assert_eq!(db.next_id, 1);
+        assert!(db.tickets.is_empty());
+    }
+
+    #[test]
+    fn version_1_ticket_without_an_id_is_invalid() {
+        let content = r#"[{"title":"Zabbix Setup","description":"","status":"Open","priority":"Low","created_at":"2022-05-11T04:07:01Z","updated_at":"2022-05-11T04:07:01Z"}]"#;
+        assert!(matches!(parse_ticket_db(content), Err(MigrationError::Invalid(_))));
+    }
+
+    #[test]
+    fn current_version_is_read_unchanged() {
+        let mut db = TicketDb::new(vec![Tickets{ id: 4, title: "Backup".to_string(), ..Tickets::default() }]);
+        db.next_id = 12;
+        let (read, found) = parse_ticket_db(&serde_json::to_string(&db).unwrap()).unwrap();
+        assert_eq!(found, SCHEMA_VERSION);
+        assert_eq!(read.next_id, 12);
+        assert_eq!(read.created, db.created);
+        assert_eq!(read.tickets[0].title, "Backup");
+    }
+
+    #[test]
+    fn newer_and_unknown_files_are_refused() {
+        assert!(matches!(parse_ticket_db(r#"{"schema_version": 99, "tickets": []}"#), Err(MigrationError::Newer(99))));
+        assert!(matches!(parse_ticket_db(r#"{"tickets": []}"#), Err(MigrationError::Invalid(_))));
+        assert!(matches!(parse_ticket_db("[1, 2]"), Err(MigrationError::Invalid(_))));
+        assert!(matches!(parse_ticket_db("not json"), Err(MigrationError::Invalid(_))));
+    }
+
+    #[test]
+    fn schema_version_of_lists_and_envelopes() {
+        assert_eq!(schema_version(&json!([])), 1);
+        assert_eq!(schema_version(&json!({"schema_version": 2})), 2);
+        assert_eq!(schema_version(&json!({"schema_version": "2"})), 0);
+        assert_eq!(schema_version(&json!("tickets")), 0);
+    }
+
+
+    //Ticket keys
+
+    fn prefixed(prefix: &str) -> DbSettings {
+        let mut settings = DbSettings::default();
+        settings.set_key_prefix(prefix).unwrap();
+        settings
+    }
+
+    #[test]
+    fn split_ticket_key_reads_numbers_and_keys() {
+        assert_eq!(split_ticket_key("42"), Some((None, 42)));
+        assert_eq!(split_ticket_key(" #42 "), Some((None, 42)));
+        assert_eq!(split_ticket_key("ops-42"), Some((Some("OPS".to_string()), 42)));
+        assert_eq!(split_ticket_key("OPS2-7"), Some((Some("OPS2".to_string()), 7)));
+        assert_eq!(split_ticket_key("OPS-"), None);
+        assert_eq!(split_ticket_key("-42"), None);
+        assert_eq!(split_ticket_key("2OPS-42"), None);
+        assert_eq!(split_ticket_key("Zabbix"), None);
+    }
+
+    #[test]
+    fn ticket_keys_use_the_prefix() {
+        let settings = prefixed("ops");
+        assert_eq!(settings.ticket_key(42), "OPS-42");
+        assert_eq!(settings.ticket_ref(42), "OPS-42");
+        assert_eq!(DbSettings::default().ticket_key(42), "42");
+        assert_eq!(DbSettings::default().ticket_ref(42), "#42");
+    }
+
+    #[test]
+    fn find_ticket_id_accepts_known_prefixes_only() {
+        let settings = prefixed("OPS");
+        assert_eq!(settings.find_ticket_id("OPS-42"), Some(42));
+        assert_eq!(settings.find_ticket_id("ops-42"), Some(42));
+        assert_eq!(settings.find_ticket_id("#42"), Some(42));
+        assert_eq!(settings.find_ticket_id("42"), Some(42));
+        assert_eq!(settings.find_ticket_id("DEV-42"), None);
+        //Without a prefix every key is someone else's
+        assert_eq!(DbSettings::default().find_ticket_id("OPS-42"), None);
+    }
+
+    #[test]
+    fn old_prefixes_are_kept_as_aliases() {
+        let mut settings = prefixed("OPS");
+        settings.set_key_prefix("IT").unwrap();
+        assert_eq!(settings.key_aliases, vec!["OPS".to_string()]);
+        assert_eq!(settings.find_ticket_id("OPS-42"), Some(42));
+        assert_eq!(settings.find_ticket_id("IT-42"), Some(42));
+        assert_eq!(settings.ticket_key(42), "IT-42");
+        //Going back to an old prefix moves it out of the aliases
+        settings.set_key_prefix("ops").unwrap();
+        assert_eq!(settings.key_prefix, "OPS");
+        assert_eq!(settings.key_aliases, vec!["IT".to_string()]);
+        //Setting the same prefix again adds no alias
+        settings.set_key_prefix("OPS").unwrap();
+        assert_eq!(settings.key_aliases, vec!["IT".to_string()]);
+    }
+
+    #[test]
+    fn empty_prefix_removes_it_and_invalid_ones_are_refused() {
+        let mut settings = prefixed("OPS");
+        settings.set_key_prefix("").unwrap();
+        assert_eq!(settings.ticket_key(42), "42");
+        assert_eq!(settings.find_ticket_id("OPS-42"), Some(42));
+
+        let mut settings = prefixed("OPS");
+        assert!(settings.set_key_prefix("O-P").is_err());
+        assert!(settings.set_key_prefix("1OPS").is_err());
+        assert_eq!(settings.key_prefix, "OPS");
+        assert!(settings.key_aliases.is_empty());
+    }
+
+
+    //Creating tickets
+
+    #[test]
+    fn created_tickets_get_the_next_id_and_mirrored_links() {
+        let mut db = TicketDb::new(vec![ticket(1, "Backup", "")]);
+        db.next_id = 5;
+        let mut settings = DbSettings::default();
+        let mut new = Tickets{ title: "Restore test".to_string(), links: vec![TicketLink{ kind: LinkKind::Blocks, target: 1 }], ..Tickets::default() };
+
+        assert!(!db.create_ticket(&mut new, &mut settings));
+        assert_eq!(new.id, 5);
+        assert_eq!(db.next_id, 6);
+        assert_eq!(db.tickets[0].links, vec![TicketLink{ kind: LinkKind::BlockedBy, target: 5 }]);
+    }
+
+    #[test]
+    fn created_tickets_register_their_tags() {
+        let mut db = TicketDb::new(Vec::new());
+        let mut settings = DbSettings::default();
+        settings.register_tag("infra");
+        let mut new = Tickets{ tags: ["infra".to_string(), "db".to_string()].into_iter().collect(), ..Tickets::default() };
+
+        assert!(db.create_ticket(&mut new, &mut settings));
+        assert_eq!(settings.tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>(), vec!["infra", "db"]);
+        //Nothing new to register the second time
+        let mut again = Tickets{ tags: ["db".to_string()].into_iter().collect(), ..Tickets::default() };
+        assert!(!db.create_ticket(&mut again, &mut settings));
+    }
+
+
+    //Settings
+
+    #[test]
+    fn workflow_needs_exactly_an_open_and_a_closed_name() {
+        let with = |names: &[&str]| DbSettings{ workflow: names.iter().map(|name| name.to_string()).collect(), ..DbSettings::default() };
+        assert!(DbSettings::default().validate().is_ok());
+        assert!(with(&["To do", "Done"]).validate().is_ok());
+        assert!(with(&["To do", "In progress", "Done"]).validate().is_err());
+        assert!(with(&["Done"]).validate().is_err());
+        assert!(with(&["Done", "done"]).validate().is_err());
+        assert!(with(&["To do", " "]).validate().is_err());
+    }
+
+    #[test]
+    fn statuses_are_found_by_workflow_and_built_in_names() {
+        let settings = DbSettings{ workflow: vec!["To do".to_string(), "Done".to_string()], ..DbSettings::default() };
+        assert_eq!(settings.find_status(" to DO "), Some(TicketStatus::Open));
+        assert_eq!(settings.find_status("closed"), Some(TicketStatus::Closed));
+        assert_eq!(settings.find_status("In progress"), None);
+        assert_eq!(settings.status_name(&TicketStatus::Closed), "Done");
+    }
+}
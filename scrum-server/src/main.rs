@@ -1,4 +1,8 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use futures::prelude::*;
 use serde_json::{Value};
@@ -6,26 +10,81 @@ use serde_json::json;
 use tokio::net::{TcpListener, TcpStream};
 use tokio_stream::StreamExt;
 use tokio_serde::formats::*;
-use tokio_util::codec::{FramedRead, LengthDelimitedCodec};
+use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
 use scrum_lib::*;
-use chrono::Utc;
+use chrono::Local;
 use tokio_serde_json::{ReadJson, WriteJson};
 
 
 const DB_PATH: &str = "ticketdb.json";
+//Templates with a recurrence, same format as the templates of the TUI
+const TEMPLATES_PATH: &str = "templates.json";
+const SCHEDULER_INTERVAL_SECS: u64 = 60 * 60;
+//Retention of the snapshots is read from the settings, same as the TUI
+const SETTINGS_PATH: &str = "dbsettings.json";
+const BACKUP_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+//One lock per project directory. Requests and the periodic jobs all read, change and write the whole
+//database file, so they hold the lock of the project for that time or their changes get lost
+static PROJECT_LOCKS: std::sync::Mutex<BTreeMap<PathBuf, Arc<tokio::sync::Mutex<()>>>> = std::sync::Mutex::new(BTreeMap::new());
+
+fn project_lock(dir: &Path) -> Arc<tokio::sync::Mutex<()>>{
+    PROJECT_LOCKS.lock().unwrap().entry(dir.to_path_buf()).or_default().clone()
+}
 
 #[tokio::main]
 pub async fn main() {
+    for dir in all_project_dirs() {
+        upgrade_db(&dir);
+    }
+
     // Bind a server socket
     let listener = TcpListener::bind("127.0.0.1:17653").await.unwrap();
 
     println!("listening on {:?}", listener.local_addr());
 
+    //Create the recurring tickets that are due on start and then every hour
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(SCHEDULER_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            for dir in all_project_dirs() {
+                let lock = project_lock(&dir);
+                let _guard = lock.lock().await;
+                if let Err(error) = run_scheduler(&dir) {
+                    println!("Recurring tickets were not created in {}: {}", dir.display(), error);
+                }
+            }
+        }
+    });
+
+    //Snapshot the database on start and then every six hours
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(BACKUP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            for dir in all_project_dirs() {
+                let lock = project_lock(&dir);
+                let _guard = lock.lock().await;
+                if let Err(error) = take_snapshot(&dir) {
+                    println!("No snapshot was taken in {}: {}", dir.display(), error);
+                }
+            }
+        }
+    });
+
     loop {
-        let (socket, _) = listener.accept().await.unwrap();
+        let socket = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(error) => {
+                println!("Cannot accept connection: {}", error);
+                continue;
+            }
+        };
+        let (reader, writer) = socket.into_split();
 
         // Delimit frames using a length header
-        let length_delimited = FramedRead::new(socket, LengthDelimitedCodec::new());
+        let length_delimited = FramedRead::new(reader, LengthDelimitedCodec::new());
 
         // Deserialize frames
         let mut deserialized = tokio_serde::SymmetricallyFramed::new(
@@ -33,29 +92,148 @@ pub async fn main() {
             SymmetricalJson::<Value>::default(),
         );
 
-        // Spawn a task that prints all received messages to STDOUT
-        tokio::spawn(async move {
-            while let Some(msg) = tokio_stream::StreamExt::try_next(&mut deserialized).await.unwrap() {
-                //convert message to Request
-
-
-                //println!("GOT: {:?}", msg);
+        //Answers, such as the chunks of a downloaded attachment
+        let mut serialized = tokio_serde::SymmetricallyFramed::new(
+            FramedWrite::new(writer, LengthDelimitedCodec::new()),
+            SymmetricalJson::<Value>::default(),
+        );
 
-                let request:Request = serde_json::from_value(msg).unwrap();
+        //One task per connection. It ends when the client goes away or sends something that is not a request,
+        //answers that cannot be sent also end it
+        tokio::spawn(async move {
+            loop {
+                let msg = match tokio_stream::StreamExt::try_next(&mut deserialized).await {
+                    Ok(Some(msg)) => msg,
+                    Ok(None) => return,
+                    Err(error) => {
+                        println!("Closing connection, not a request: {}", error);
+                        let _ = serialized.send(json!({ "error": format!("not a request: {}", error) })).await;
+                        return;
+                    }
+                };
+                let request: Request = match serde_json::from_value(msg) {
+                    Ok(request) => request,
+                    Err(error) => {
+                        println!("Closing connection, not a request: {}", error);
+                        let _ = serialized.send(json!({ "error": format!("not a request: {}", error) })).await;
+                        return;
+                    }
+                };
 
-                println!("JSON: {:?}", request);
+                //Only what was asked for, chunks and ticket contents are too big to log
+                println!("{} ticket {}", request.action.to_string(), request.ticket.id);
+                //Every request works on the database of its project
+                let dir = match project_dir(&request.project) {
+                    Ok(dir) => dir,
+                    Err(error) => {
+                        if serialized.send(json!({ "error": error })).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+                //Held while the database is read and written, not while answering
+                let lock = project_lock(&dir);
                match request.action {
-                TicketAction::Create => { add_ticket_to_db(request.ticket);               
-            
+                //Each of these is answered with one message, errors as {"error": ...}
+                TicketAction::Create => {
+                    let guard = lock.lock().await;
+                    let answer = match add_ticket_to_db(&dir, request.ticket) {
+                        Ok(ticket) => serde_json::to_value(ticket).unwrap(),
+                        Err(error) => json!({ "error": error }),
+                    };
+                    drop(guard);
+                    if serialized.send(answer).await.is_err() {
+                        return;
+                    }
+                }
+                TicketAction::Update => {
+                    let guard = lock.lock().await;
+                    let answer = match update_ticket_in_db(&dir, request.ticket) {
+                        Ok(()) => json!({ "ok": true }),
+                        Err(error) => json!({ "error": error }),
+                    };
+                    drop(guard);
+                    if serialized.send(answer).await.is_err() {
+                        return;
+                    }
+                }
+                TicketAction::Delete => {
+                    let guard = lock.lock().await;
+                    let answer = match delete_ticket_from_db(&dir, request.ticket.id) {
+                        Ok(()) => json!({ "ok": true }),
+                        Err(error) => json!({ "error": error }),
+                    };
+                    drop(guard);
+                    if serialized.send(answer).await.is_err() {
+                        return;
+                    }
                 }
-                TicketAction::Update => todo!(),
-                TicketAction::Delete => todo!(),
                 TicketAction::UpdateDb => {
-                    //let db_content = fs::read_to_string(DB_PATH).unwrap();
-                    //let mut parsed: Vec<Tickets> = serde_json::from_str(&db_content).unwrap();
                     //return struct to TCP client
-
-
+                    let guard = lock.lock().await;
+                    let answer = match read_db(&dir) {
+                        Ok(db) => serde_json::to_value(db.tickets).unwrap(),
+                        Err(error) => json!({ "error": error }),
+                    };
+                    drop(guard);
+                    if serialized.send(answer).await.is_err() {
+                        return;
+                    }
+                },
+                TicketAction::Settings => {
+                    let guard = lock.lock().await;
+                    let answer = match load_settings(&dir) {
+                        Ok(settings) => serde_json::to_value(settings).unwrap(),
+                        Err(error) => json!({ "error": error }),
+                    };
+                    drop(guard);
+                    if serialized.send(answer).await.is_err() {
+                        return;
+                    }
+                },
+                //Every chunk is answered, so the client stops when one cannot be stored
+                TicketAction::UploadChunk => {
+                    let answer = match request.chunk {
+                        Some(chunk) => {
+                            let guard = lock.lock().await;
+                            let stored = receive_chunk(&dir, &request.ticket, &chunk);
+                            drop(guard);
+                            match stored {
+                                Ok(()) => json!({ "ok": true }),
+                                Err(error) => {
+                                    println!("Upload of {} failed: {}", chunk.hash, error);
+                                    json!({ "error": error })
+                                }
+                            }
+                        }
+                        None => json!({ "error": "the upload has no chunk" }),
+                    };
+                    if serialized.send(answer).await.is_err() {
+                        return;
+                    }
+                },
+                TicketAction::Download => {
+                    //Without a chunk there is no hash, which attachment_path refuses
+                    let hash = request.chunk.map(|chunk| chunk.hash).unwrap_or_default();
+                    let data = match attachment_path(&dir, &hash) {
+                        Ok(path) => fs::read(path).map_err(|error| format!("attachment {} cannot be read: {}", hash, error)),
+                        Err(error) => Err(error),
+                    };
+                    match data {
+                        Ok(data) => {
+                            for piece in AttachmentChunk::split(&hash, &data) {
+                                if serialized.send(serde_json::to_value(piece).unwrap()).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        Err(error) => {
+                            if serialized.send(json!({ "error": error })).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
                 },
                 }
             }
@@ -64,24 +242,242 @@ pub async fn main() {
     }
 }
 
-fn add_ticket_to_db(ticket: Tickets){
-    let db_content = fs::read_to_string(DB_PATH).unwrap();
-    let mut parsed: Vec<Tickets> = serde_json::from_str(&db_content).unwrap();
-    let mut max_id = 0;
-    for ticket in parsed.iter() {
-        if ticket.id > max_id {
-            max_id = ticket.id;
+//Directory with the files of a project, None is the working directory. Other projects are created on first use
+fn project_dir(project: &Option<String>) -> Result<PathBuf, String>{
+    match project {
+        None => Ok(PathBuf::from(".")),
+        Some(name) if valid_project_name(name) => {
+            let dir = PathBuf::from(PROJECTS_DIR).join(name);
+            fs::create_dir_all(&dir).map_err(|error| error.to_string())?;
+            Ok(dir)
         }
+        Some(name) => Err(format!("\"{}\" is not a project name", name)),
     }
+}
 
-    parsed.push(ticket);
-    fs::write(DB_PATH, &serde_json::to_vec(&parsed).unwrap()).unwrap();
-   // Ok(parsed)
+//The working directory and every project that has been used, for the periodic jobs
+fn all_project_dirs() -> Vec<PathBuf>{
+    let mut dirs = vec![PathBuf::from(".")];
+    if let Ok(entries) = fs::read_dir(PROJECTS_DIR) {
+        dirs.extend(entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_dir()));
+    }
+    dirs
 }
 
+//Upgrades a database file from an older version after copying it, a file from a newer version stops the server
+fn upgrade_db(dir: &Path){
+    let db_path = dir.join(DB_PATH);
+    //The file is created by the TUI, the command line or the first ticket added
+    let db_content = match fs::read_to_string(&db_path) {
+        Ok(content) => content,
+        Err(_) => return,
+    };
+    match parse_ticket_db(&db_content) {
+        Ok((_, version)) if version == SCHEMA_VERSION => {}
+        Ok((db, version)) => {
+            let backup = migration_backup_name(&db_path.to_string_lossy(), version);
+            if let Err(error) = fs::copy(&db_path, &backup).map_err(|error| error.to_string()).and_then(|_| write_db(dir, &db)) {
+                eprintln!("Cannot upgrade {}: {}", db_path.display(), error);
+                std::process::exit(1);
+            }
+            println!("Upgraded {} to schema version {}, the old file is {}", db_path.display(), SCHEMA_VERSION, backup);
+        }
+        Err(MigrationError::Newer(version)) => {
+            eprintln!("{} was written by a newer version of scrum (schema version {}, this server reads up to {}), please update scrum-server", db_path.display(), version, SCHEMA_VERSION);
+            std::process::exit(1);
+        }
+        Err(MigrationError::Invalid(error)) => {
+            eprintln!("Cannot read {}: {}", db_path.display(), error);
+            std::process::exit(1);
+        }
+    }
+}
 
+//A project without a database yet starts empty
+fn read_db(dir: &Path) -> Result<TicketDb, String>{
+    let db_content = match fs::read_to_string(dir.join(DB_PATH)) {
+        Ok(content) => content,
+        Err(_) => return Ok(TicketDb::new(Vec::new())),
+    };
+    match parse_ticket_db(&db_content) {
+        Ok((db, _)) => Ok(db),
+        Err(MigrationError::Newer(version)) => Err(format!("{} has the newer schema version {}", dir.join(DB_PATH).display(), version)),
+        Err(MigrationError::Invalid(error)) => Err(format!("cannot read {}: {}", dir.join(DB_PATH).display(), error)),
+    }
+}
 
+//The defaults when the project has no settings or they cannot be read
+fn read_settings(dir: &Path) -> DbSettings {
+    load_settings(dir).unwrap_or_default()
+}
 
+//Settings that are changed and written back, a file that cannot be read is an error so it is not replaced
+fn load_settings(dir: &Path) -> Result<DbSettings, String>{
+    let content = match fs::read_to_string(dir.join(SETTINGS_PATH)) {
+        Ok(content) => content,
+        Err(_) if !dir.join(SETTINGS_PATH).exists() => return Ok(DbSettings::default()),
+        Err(error) => return Err(format!("cannot read {}: {}", dir.join(SETTINGS_PATH).display(), error)),
+    };
+    let settings: DbSettings = serde_json::from_str(&content).map_err(|error| format!("cannot parse {}: {}", dir.join(SETTINGS_PATH).display(), error))?;
+    settings.validate().map_err(|error| format!("{}: {}", dir.join(SETTINGS_PATH).display(), error))?;
+    Ok(settings)
+}
+
+fn write_settings(dir: &Path, settings: &DbSettings) -> Result<(), String>{
+    let content = serde_json::to_vec(settings).map_err(|error| error.to_string())?;
+    fs::write(dir.join(SETTINGS_PATH), content).map_err(|error| format!("cannot write {}: {}", dir.join(SETTINGS_PATH).display(), error))
+}
+
+fn write_db(dir: &Path, db: &TicketDb) -> Result<(), String>{
+    let content = serde_json::to_vec(db).map_err(|error| error.to_string())?;
+    fs::write(dir.join(DB_PATH), content).map_err(|error| format!("cannot write {}: {}", dir.join(DB_PATH).display(), error))
+}
+
+//The server hands out the id, the stored ticket is returned. Same as creating it locally
+fn add_ticket_to_db(dir: &Path, mut ticket: Tickets) -> Result<Tickets, String>{
+    let mut db = read_db(dir)?;
+    let mut settings = load_settings(dir)?;
+    if db.create_ticket(&mut ticket, &mut settings) {
+        write_settings(dir, &settings)?;
+    }
+    write_db(dir, &db)?;
+    Ok(ticket)
+}
+
+fn update_ticket_in_db(dir: &Path, ticket: Tickets) -> Result<(), String>{
+    let mut db = read_db(dir)?;
+    let id = ticket.id;
+    match db.tickets.iter_mut().find(|stored| stored.id == id) {
+        Some(Tickets{ moved_to: Some(moved), .. }) => return Err(moved_error(id, moved)),
+        Some(stored) => *stored = ticket,
+        None => return Err(format!("ticket {} does not exist", id)),
+    }
+    sync_links(id, &mut db.tickets);
+    write_db(dir, &db)?;
+    Ok(())
+}
+
+fn delete_ticket_from_db(dir: &Path, id: i32) -> Result<(), String>{
+    let mut db = read_db(dir)?;
+    match db.tickets.iter().find(|stored| stored.id == id) {
+        Some(Tickets{ moved_to: Some(moved), .. }) => return Err(moved_error(id, moved)),
+        Some(_) => {}
+        None => return Err(format!("ticket {} does not exist", id)),
+    }
+    db.tickets.retain(|stored| stored.id != id);
+    //Removes the links other tickets had to it
+    sync_links(id, &mut db.tickets);
+    write_db(dir, &db)?;
+    Ok(())
+}
+
+
+//Tombstones of moved tickets are kept as they are, same as locally
+fn moved_error(id: i32, moved: &TicketTransfer) -> String{
+    format!("ticket {} was moved to {}, its tombstone cannot be changed", id, moved.describe())
+}
+
+//Only hashes are accepted as file names, so a request cannot point outside the attachments directory
+fn attachment_path(dir: &Path, hash: &str) -> Result<PathBuf, String>{
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("\"{}\" is not an attachment hash", hash));
+    }
+    Ok(dir.join(ATTACHMENTS_DIR).join(hash.to_lowercase()))
+}
+
+//Chunks arrive in order and are appended to a part file. After the last one the content
+//is checked against the hash and the attachment is added to the ticket
+fn receive_chunk(dir: &Path, ticket: &Tickets, chunk: &AttachmentChunk) -> Result<(), String>{
+    let path = attachment_path(dir, &chunk.hash)?;
+    //Refused before anything is stored, and checked again when the ticket is written
+    if chunk.index == 0 {
+        if let Some(moved) = read_db(dir)?.tickets.iter().find(|stored| stored.id == ticket.id).and_then(|stored| stored.moved_to.clone()) {
+            return Err(moved_error(ticket.id, &moved));
+        }
+    }
+    let part = path.with_extension("part");
+    fs::create_dir_all(dir.join(ATTACHMENTS_DIR)).map_err(|error| error.to_string())?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(chunk.index > 0)
+        .truncate(chunk.index == 0)
+        .open(&part)
+        .map_err(|error| error.to_string())?;
+    file.write_all(&chunk.bytes()?).map_err(|error| error.to_string())?;
+    if !chunk.is_last() {
+        return Ok(());
+    }
+
+    let data = fs::read(&part).map_err(|error| error.to_string())?;
+    if attachment_hash(&data) != chunk.hash.to_lowercase() {
+        let _ = fs::remove_file(&part);
+        return Err("content does not match the hash".to_string());
+    }
+    fs::rename(&part, &path).map_err(|error| error.to_string())?;
+
+    let attachment = ticket.attachments
+        .iter()
+        .find(|attachment| attachment.hash == chunk.hash)
+        .ok_or("ticket has no metadata for the attachment")?;
+    let mut db = read_db(dir)?;
+    let stored = db.tickets
+        .iter_mut()
+        .find(|stored| stored.id == ticket.id)
+        .ok_or(format!("ticket {} does not exist", ticket.id))?;
+    if let Some(moved) = &stored.moved_to {
+        return Err(moved_error(ticket.id, moved));
+    }
+    stored.attachments.retain(|existing| existing.hash != attachment.hash || existing.name != attachment.name);
+    stored.attachments.push(attachment.clone());
+    write_db(dir, &db)?;
+    println!("Stored attachment {} on ticket {}", attachment.name, ticket.id);
+    Ok(())
+}
+
+//Tickets that were already created have the same recurrence key, so running twice creates nothing new
+fn run_scheduler(dir: &Path) -> Result<(), String>{
+    let templates: Vec<Template> = match fs::read_to_string(dir.join(TEMPLATES_PATH)) {
+        Ok(content) => serde_json::from_str(&content).map_err(|error| format!("cannot parse {}: {}", dir.join(TEMPLATES_PATH).display(), error))?,
+        //No recurring tickets set up
+        Err(_) => return Ok(()),
+    };
+    let mut db = read_db(dir)?;
+    let created = create_recurring(&templates, &mut db, &read_settings(dir), Local::now().naive_local().date(), "scrum-server");
+    if !created.is_empty() {
+        write_db(dir, &db)?;
+        println!("Created recurring tickets in {}: {:?}", dir.display(), created);
+    }
+    Ok(())
+}
+
+//Same file names as the snapshots of the TUI, so they can be listed and restored with the CLI
+fn take_snapshot(dir: &Path) -> Result<(), String>{
+    //Nothing to keep in a project that has no database yet
+    if !dir.join(DB_PATH).exists() {
+        return Ok(());
+    }
+    let db = read_db(dir)?;
+    let backups = dir.join(BACKUPS_DIR);
+    let snapshot = Snapshot::new(Local::now().naive_local());
+    fs::create_dir_all(&backups).map_err(|error| error.to_string())?;
+    //Never replace a snapshot that is already there
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).open(backups.join(&snapshot.name))
+        .map_err(|error| format!("Cannot create snapshot {}: {}", snapshot.name, error))?;
+    file.write_all(&serde_json::to_vec(&db).map_err(|error| error.to_string())?).map_err(|error| error.to_string())?;
+    println!("Saved snapshot {}", backups.join(&snapshot.name).display());
+
+    let retention = read_settings(dir).backup_retention;
+    let snapshots: Vec<Snapshot> = fs::read_dir(&backups).map_err(|error| error.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str().and_then(Snapshot::from_name))
+        .collect();
+    for expired in expired_snapshots(&snapshots, &retention) {
+        fs::remove_file(backups.join(&expired.name)).map_err(|error| error.to_string())?;
+        println!("Removed snapshot {}", backups.join(&expired.name).display());
+    }
+    Ok(())
+}
 
 
 
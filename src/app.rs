@@ -1,20 +1,19 @@
 use chrono::prelude::*;
 use crossterm::{
-    event::{self, Event as CEvent, KeyCode, KeyEvent, KeyModifiers},
+    event::{self, Event as CEvent, KeyCode, KeyModifiers},
     terminal::{disable_raw_mode, enable_raw_mode, LeaveAlternateScreen, EnterAlternateScreen}, execute,
 };
-use unicode_width::UnicodeWidthStr;
-use std::{io::{self, Write, Stdout}, sync::Arc};
+use std::io;
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
 use tui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
-    text::{Span, Spans, Text},
+    text::{Span, Spans},
     widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Tabs, TableState, Wrap,
+        Block, Borders, ListState, Tabs, TableState,
     },
     Terminal
 };
@@ -23,7 +22,17 @@ use scrum_lib::*;
 use crate::{db::*, Event};
 use crate::ui::*;
 use crate::ticket::*;
+use crate::sprint::*;
+use crate::report::*;
 use crate::theme::*;
+use crate::worklog::*;
+use crate::field::*;
+use crate::checklist::*;
+use crate::attachment::*;
+use crate::csvio::*;
+use crate::document::*;
+use crate::backup::*;
+use crate::project::*;
 
 const TICKRATE: u64 = 1000;
 
@@ -36,8 +45,60 @@ pub struct AppState {
     pub edit_priority_state: ListState,
     pub edit_status_state: ListState,
     pub edit_note_state: ListState,
+    pub edit_kind_state: ListState,
     pub edit_ticket: Tickets,
     pub edit_focus: EditItem,
+    pub board_column: usize,
+    pub board_card_state: ListState,
+    pub sprints: Vec<Sprint>,
+    pub sprint_focus: SprintFocus,
+    pub sprint_list_state: ListState,
+    pub backlog_state: ListState,
+    pub sprint_ticket_state: ListState,
+    pub edit_sprint: Sprint,
+    pub sprint_edit_focus: SprintItem,
+    pub sprint_start_input: String,
+    pub sprint_end_input: String,
+    pub filter: TicketFilter,
+    pub settings: DbSettings,
+    pub estimate_input: String,
+    pub parent_input: String,
+    pub tag_input: String,
+    pub due_input: String,
+    pub due_banner: Vec<String>,
+    pub timesheet_week: i64,
+    pub field_list_state: ListState,
+    pub field_form: bool,
+    pub field_focus: FieldItem,
+    pub field_name_input: String,
+    pub field_kind: usize,
+    pub field_options_input: String,
+    pub field_inputs: Vec<String>,
+    pub checklist_input: String,
+    pub checklist_state: ListState,
+    pub attachment_list_state: ListState,
+    pub templates: Vec<Template>,
+    pub template_list_state: ListState,
+    pub import_preview: Option<ImportReport>,
+    //Typed like the --map option of scrum import, pairs separated by commas
+    pub import_mapping: String,
+    pub import_focus_mapping: bool,
+    pub snapshots: Vec<Snapshot>,
+    pub snapshot_list_state: ListState,
+    pub snapshot_diff: Vec<TicketDiff>,
+    pub snapshot_diff_state: ListState,
+    pub snapshot_focus_diff: bool,
+    pub projects: Vec<Project>,
+    pub project_list_state: ListState,
+    pub transfer_targets: Vec<Option<Project>>,
+    pub transfer_list_state: ListState,
+    pub tree_view: bool,
+    pub notice: String,
+    pub link_kind: usize,
+    pub link_list_state: ListState,
+    pub close_as_duplicate: bool,
+    pub duplicate_state: ListState,
+    pub report_sprint: usize,
     pub messages: Vec<String>,
     pub input: String,
     pub prompt: String,
@@ -48,6 +109,16 @@ pub struct AppState {
 
 impl AppState {
     pub fn default() -> AppState {
+        //A file that cannot be read is replaced by defaults for this session, the notice says which one
+        let mut problems = Vec::new();
+        let sprints = read_sprints().unwrap_or_else(|error| {
+            problems.push(format!("Cannot read the sprints, none are shown and changes to them are not saved: {}", error));
+            Vec::new()
+        });
+        let settings = read_settings().unwrap_or_else(|error| {
+            problems.push(format!("Cannot read the settings, the defaults are used and changes to them are not saved: {}", error));
+            DbSettings::default()
+        });
         AppState {
             ticket_view_mode: TicketViewMode::Open,
             active_menu_item: MenuItem::Tickets,
@@ -57,8 +128,59 @@ impl AppState {
             edit_priority_state: ListState::default(),
             edit_status_state: ListState::default(),
             edit_note_state: ListState::default(),
+            edit_kind_state: ListState::default(),
             edit_ticket: Tickets::default(),
             edit_focus: EditItem::Title,
+            board_column: 0,
+            board_card_state: ListState::default(),
+            sprints,
+            sprint_focus: SprintFocus::Sprints,
+            sprint_list_state: ListState::default(),
+            backlog_state: ListState::default(),
+            sprint_ticket_state: ListState::default(),
+            edit_sprint: Sprint::new(0, String::new(), String::new(), Local::now().naive_local().date(), Local::now().naive_local().date()),
+            sprint_edit_focus: SprintItem::Name,
+            sprint_start_input: String::new(),
+            sprint_end_input: String::new(),
+            filter: TicketFilter::default(),
+            settings,
+            estimate_input: String::new(),
+            parent_input: String::new(),
+            tag_input: String::new(),
+            due_input: String::new(),
+            due_banner: Vec::new(),
+            timesheet_week: 0,
+            field_list_state: ListState::default(),
+            field_form: false,
+            field_focus: FieldItem::Name,
+            field_name_input: String::new(),
+            field_kind: 0,
+            field_options_input: String::new(),
+            field_inputs: Vec::new(),
+            checklist_input: String::new(),
+            checklist_state: ListState::default(),
+            attachment_list_state: ListState::default(),
+            templates: Vec::new(),
+            template_list_state: ListState::default(),
+            import_preview: None,
+            import_mapping: String::new(),
+            import_focus_mapping: false,
+            snapshots: Vec::new(),
+            snapshot_list_state: ListState::default(),
+            snapshot_diff: Vec::new(),
+            snapshot_diff_state: ListState::default(),
+            snapshot_focus_diff: false,
+            projects: Vec::new(),
+            project_list_state: ListState::default(),
+            transfer_targets: Vec::new(),
+            transfer_list_state: ListState::default(),
+            tree_view: false,
+            notice: problems.join(" | "),
+            link_kind: 0,
+            link_list_state: ListState::default(),
+            close_as_duplicate: false,
+            duplicate_state: ListState::default(),
+            report_sprint: 0,
             messages: Vec::new(),
             input: String::new(),
             prompt: "Enter Title".to_string(),
@@ -69,12 +191,37 @@ impl AppState {
     }
 }
 
+//Changed settings are only used once they are saved, otherwise the notice says why not
+pub fn save_settings(app: &mut AppState, settings: DbSettings) {
+    match write_settings(&settings) {
+        Ok(()) => app.settings = settings,
+        Err(error) => add_notice(app, format!("Settings were not saved: {}", error)),
+    }
+}
+
+//Adds a message to the notice instead of replacing what is already shown
+pub fn add_notice(app: &mut AppState, message: String) {
+    if app.notice.is_empty() {
+        app.notice = message;
+    } else {
+        app.notice = format!("{} | {}", app.notice, message);
+    }
+}
+
 #[derive(PartialEq)]
 pub enum EditItem {
     Title,
+    Assignee,
+    Tags,
     Description,
     Priority,
     Status,
+    Estimate,
+    Due,
+    Kind,
+    Parent,
+    Checklist,
+    Field(usize),
     Notes,
 }
 
@@ -84,6 +231,36 @@ impl EditItem {
     }
 }
 
+#[derive(PartialEq)]
+pub enum SprintItem {
+    Name,
+    Goal,
+    Start,
+    End,
+}
+
+#[derive(PartialEq)]
+pub enum FieldItem {
+    Name,
+    Kind,
+    Options,
+}
+
+#[derive(PartialEq)]
+pub enum SprintFocus {
+    Sprints,
+    Backlog,
+    Sprint,
+}
+
+#[derive(Default)]
+pub struct TicketFilter {
+    pub current_sprint: bool,
+    pub tag: Option<String>,
+    //Custom field name and the value it has to contain
+    pub field: Option<(String, String)>,
+}
+
 #[derive(PartialEq)]
 pub enum TicketViewMode {
     Open,
@@ -97,6 +274,25 @@ pub enum MenuItem {
     NoteForm,
     ConfirmForm,
     Help,
+    Board,
+    Sprints,
+    SprintForm,
+    Reports,
+    LinkPicker,
+    DuplicateWarning,
+    WorkLogForm,
+    Fields,
+    FilterForm,
+    TemplatePicker,
+    AttachmentForm,
+    Attachments,
+    ImportForm,
+    ExportForm,
+    Backups,
+    Projects,
+    ProjectForm,
+    PrefixForm,
+    TransferPicker,
 }
 
 pub enum SortBy {
@@ -104,6 +300,8 @@ pub enum SortBy {
     Title,
     Priority,
     Updated,
+    Due,
+    Field(usize),
 }
 
 impl From<MenuItem> for usize {
@@ -114,6 +312,25 @@ impl From<MenuItem> for usize {
             MenuItem::NoteForm => 2,
             MenuItem::ConfirmForm => 3,
             MenuItem::Help => 4,
+            MenuItem::Board => 5,
+            MenuItem::Sprints => 6,
+            MenuItem::SprintForm => 7,
+            MenuItem::Reports => 8,
+            MenuItem::LinkPicker => 9,
+            MenuItem::DuplicateWarning => 10,
+            MenuItem::WorkLogForm => 11,
+            MenuItem::Fields => 12,
+            MenuItem::FilterForm => 13,
+            MenuItem::TemplatePicker => 14,
+            MenuItem::AttachmentForm => 15,
+            MenuItem::Attachments => 16,
+            MenuItem::ImportForm => 17,
+            MenuItem::ExportForm => 18,
+            MenuItem::Backups => 19,
+            MenuItem::Projects => 20,
+            MenuItem::ProjectForm => 21,
+            MenuItem::PrefixForm => 22,
+            MenuItem::TransferPicker => 23,
         }
     }
 }
@@ -142,10 +359,8 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
     
-                if last_tick.elapsed() >= tick_rate {
-                    if let Ok(_) = tx.send(Event::Tick) {
-                        last_tick = Instant::now();
-                    }
+                if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
+                    last_tick = Instant::now();
                 }
             }
         });
@@ -155,9 +370,29 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
         let note_menu_titles = vec!["Add note (Press escape to cancel)"]; //Convert to const?
         let confirm_menu_titles = vec!["Confirmation (Press escape to cancel)"]; //Convert to const?
         let help_menu_titles = vec!["Help (Press escape to return)"]; //Convert to const?
+        let board_menu_titles = vec!["Board (Press escape to return)"]; //Convert to const?
+        let sprint_menu_titles = vec!["Sprints (Press escape to return)"]; //Convert to const?
+        let sprint_form_titles = vec!["New sprint (Press escape to cancel)"]; //Convert to const?
+        let report_menu_titles = vec!["Reports (Press escape to return)"]; //Convert to const?
+        let link_menu_titles = vec!["Link ticket (Press escape to cancel)"]; //Convert to const?
+        let duplicate_menu_titles = vec!["Possible duplicates (Press escape to return to the form)"]; //Convert to const?
+        let worklog_menu_titles = vec!["Stop timer (Press escape to keep it running)"]; //Convert to const?
+        let field_menu_titles = vec!["Custom fields (Press escape to return)"]; //Convert to const?
+        let filter_menu_titles = vec!["Filter (Press escape to cancel)"]; //Convert to const?
+        let template_menu_titles = vec!["New ticket from template (Press escape to cancel)"]; //Convert to const?
+        let attachment_form_menu_titles = vec!["Attach file (Press escape to cancel)"]; //Convert to const?
+        let attachment_menu_titles = vec!["Attachments (Press escape to go back)"]; //Convert to const?
+        let import_menu_titles = vec!["Import (Press escape to cancel)"]; //Convert to const?
+        let export_menu_titles = vec!["Export CSV (Press escape to cancel)"]; //Convert to const?
+        let backup_menu_titles = vec!["Backups (Press escape to return)"]; //Convert to const?
+        let project_menu_titles = vec!["Projects (Press escape to return)"]; //Convert to const?
+        let project_form_menu_titles = vec!["New project (Press escape to cancel)"]; //Convert to const?
+        let prefix_menu_titles = vec!["Key prefix (Press escape to cancel)"]; //Convert to const?
+        let transfer_menu_titles = vec!["Move or copy ticket (Press escape to cancel)"]; //Convert to const?
         
     
         app.ticket_list_state.select(Some(0));
+        app.due_banner = due_banner(app);
     
         loop {
             terminal.draw(|rect| {
@@ -169,7 +404,7 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                         [
                             Constraint::Length(3),
                             Constraint::Min(2),
-                           // Constraint::Length(1), //To make room for the footer which doesn't exist at the moment
+                            Constraint::Length(1),
                         ]
                         .as_ref(),
                     )
@@ -179,27 +414,33 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                     TicketViewMode::Open => "View Closed",
                     TicketViewMode::Closed => "View Open",
                 };
-                let ticket_menu_titles = vec!["Tickets", "Add", "Edit", "Note (+)", openorclosed, "Help", "Quit"];
-                let mut menu_titles = &ticket_menu_titles;
-                    
-                match app.active_menu_item {
-                    MenuItem::Tickets => {
-                        menu_titles = &ticket_menu_titles;
-                    }
-                    MenuItem::EditForm => {
-                        menu_titles = &edit_menu_titles;
-                    }
-                    MenuItem::NoteForm => {
-                        menu_titles = &note_menu_titles;
-                    }
-                    MenuItem::ConfirmForm => {
-                        menu_titles = &confirm_menu_titles;
-                    },
-                    MenuItem::Help => {
-                        menu_titles = &help_menu_titles
-                    },
-                    
-                }
+                let ticket_menu_titles = vec!["Tickets", "Add", "Edit", "Note (+)", openorclosed, "Board", "Sprints", "Reports", "Help", "Quit"];
+                let menu_titles = match app.active_menu_item {
+                    MenuItem::Tickets => &ticket_menu_titles,
+                    MenuItem::EditForm => &edit_menu_titles,
+                    MenuItem::NoteForm => &note_menu_titles,
+                    MenuItem::ConfirmForm => &confirm_menu_titles,
+                    MenuItem::Help => &help_menu_titles,
+                    MenuItem::Board => &board_menu_titles,
+                    MenuItem::Sprints => &sprint_menu_titles,
+                    MenuItem::SprintForm => &sprint_form_titles,
+                    MenuItem::Reports => &report_menu_titles,
+                    MenuItem::LinkPicker => &link_menu_titles,
+                    MenuItem::DuplicateWarning => &duplicate_menu_titles,
+                    MenuItem::WorkLogForm => &worklog_menu_titles,
+                    MenuItem::Fields => &field_menu_titles,
+                    MenuItem::FilterForm => &filter_menu_titles,
+                    MenuItem::TemplatePicker => &template_menu_titles,
+                    MenuItem::AttachmentForm => &attachment_form_menu_titles,
+                    MenuItem::Attachments => &attachment_menu_titles,
+                    MenuItem::ImportForm => &import_menu_titles,
+                    MenuItem::ExportForm => &export_menu_titles,
+                    MenuItem::Backups => &backup_menu_titles,
+                    MenuItem::Projects => &project_menu_titles,
+                    MenuItem::ProjectForm => &project_form_menu_titles,
+                    MenuItem::PrefixForm => &prefix_menu_titles,
+                    MenuItem::TransferPicker => &transfer_menu_titles,
+                };
                 let menu = menu_titles
                     .iter()
                     .map(|t| {
@@ -232,9 +473,20 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                 [Constraint::Percentage(40), Constraint::Percentage(60)].as_ref(),
                             )
                             .split(chunks[1]);
-                        let (left, right) = render_tickets(&app);
+                        let (left, right) = render_tickets(app);
+                        //Banner with what is due today, shown above the list until dismissed
+                        let tickets_chunks = if app.due_banner.is_empty() {
+                            tickets_chunks
+                        } else {
+                            let banner_chunks = Layout::default().direction(Direction::Vertical)
+                                .constraints([Constraint::Length(app.due_banner.len() as u16 + 2), Constraint::Min(3)].as_ref())
+                                .split(tickets_chunks[0]);
+                            rect.render_widget(render_due_banner(app), banner_chunks[0]);
+                            vec![banner_chunks[1], tickets_chunks[1]]
+                        };
                         rect.render_stateful_widget(left, tickets_chunks[0], &mut app.ticket_list_state);
                         rect.render_widget(right, tickets_chunks[1]);
+                        rect.render_widget(render_notice(app), chunks[2]);
                     }
                     MenuItem::EditForm => {
                         let editchunk = Layout::default().direction(Direction::Vertical)
@@ -242,17 +494,33 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                             .split(chunks[1]);
                         
                         let chunk1 = Layout::default().direction(Direction::Vertical)
-                            .constraints([Constraint::Length(3), Constraint::Min(3)].as_ref(),).split(editchunk[0]);
+                            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Min(3)].as_ref(),).split(editchunk[0]);
                         let chunk2 = Layout::default().direction(Direction::Horizontal)
                             .constraints([Constraint::Percentage(33), Constraint::Percentage(34), Constraint::Percentage(33)].as_ref(),).split(editchunk[1]);
+                        let estimatechunk = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)].as_ref(),).split(chunk2[2]);
                         let chunk3 = Layout::default().direction(Direction::Horizontal)
                             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref(),).split(editchunk[2]);
-                        let (titleinput, descinput, priorityinput, statusinput, notesinput) = render_edit_form(app);
-                        rect.render_widget(titleinput, chunk1[0]);
-                        rect.render_widget(descinput, chunk1[1]);
-                        rect.render_stateful_widget(priorityinput, chunk2[0], &mut app.edit_priority_state);
-                        rect.render_stateful_widget(statusinput, chunk2[1], &mut app.edit_status_state);
-                        rect.render_stateful_widget(notesinput, chunk3[0], &mut app.edit_note_state);
+                        let chunk4 = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(4), Constraint::Length(3), Constraint::Min(4), Constraint::Length(5)].as_ref(),).split(chunk3[1]);
+                        let notechunk = Layout::default().direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref(),).split(chunk3[0]);
+                        let form = render_edit_form(app);
+                        rect.render_widget(form.title, chunk1[0]);
+                        rect.render_widget(form.assignee, chunk1[1]);
+                        rect.render_widget(form.tags, chunk1[2]);
+                        rect.render_widget(form.description, chunk1[3]);
+                        rect.render_stateful_widget(form.priority, chunk2[0], &mut app.edit_priority_state);
+                        rect.render_stateful_widget(form.status, chunk2[1], &mut app.edit_status_state);
+                        rect.render_widget(form.estimate, estimatechunk[0]);
+                        rect.render_widget(form.due, estimatechunk[1]);
+                        rect.render_stateful_widget(form.notes, notechunk[0], &mut app.edit_note_state);
+                        rect.render_widget(form.fields, notechunk[1]);
+                        rect.render_stateful_widget(form.kind, chunk4[0], &mut app.edit_kind_state);
+                        rect.render_widget(form.parent, chunk4[1]);
+                        rect.render_stateful_widget(form.checklist, chunk4[2], &mut app.checklist_state);
+                        rect.render_widget(form.links, chunk4[3]);
+                        rect.render_widget(render_notice(app), chunks[2]);
                     }
                     MenuItem::NoteForm => {
                         let chunks = Layout::default().direction(Direction::Vertical)
@@ -270,6 +538,160 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                         let text = render_help_form(app);
                         rect.render_widget(text, chunks[1]);
                     },
+                    MenuItem::Board => {
+                        let columns = render_board(app);
+                        let board_chunks = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints(vec![Constraint::Ratio(1, columns.len() as u32); columns.len()])
+                            .split(chunks[1]);
+                        for (i, column) in columns.into_iter().enumerate() {
+                            if i == app.board_column {
+                                rect.render_stateful_widget(column, board_chunks[i], &mut app.board_card_state);
+                            } else {
+                                rect.render_widget(column, board_chunks[i]);
+                            }
+                        }
+                        rect.render_widget(render_notice(app), chunks[2]);
+                    },
+                    MenuItem::Sprints => {
+                        let sprint_chunks = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(30), Constraint::Percentage(35), Constraint::Percentage(35)].as_ref())
+                            .split(chunks[1]);
+                        let (sprintlist, backlog, sprinttickets) = render_sprints(app);
+                        rect.render_stateful_widget(sprintlist, sprint_chunks[0], &mut app.sprint_list_state);
+                        rect.render_stateful_widget(backlog, sprint_chunks[1], &mut app.backlog_state);
+                        rect.render_stateful_widget(sprinttickets, sprint_chunks[2], &mut app.sprint_ticket_state);
+                        rect.render_widget(render_notice(app), chunks[2]);
+                    },
+                    MenuItem::SprintForm => {
+                        let form_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Min(1)].as_ref())
+                            .split(chunks[1]);
+                        let (nameinput, goalinput, startinput, endinput, prompt) = render_sprint_form(app);
+                        rect.render_widget(nameinput, form_chunks[0]);
+                        rect.render_widget(goalinput, form_chunks[1]);
+                        rect.render_widget(startinput, form_chunks[2]);
+                        rect.render_widget(endinput, form_chunks[3]);
+                        rect.render_widget(prompt, form_chunks[4]);
+                    },
+                    MenuItem::Reports => {
+                        let report_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+                            .split(chunks[1]);
+                        let top_chunks = Layout::default().direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                            .split(report_chunks[0]);
+                        let bottom_chunks = Layout::default().direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                            .split(report_chunks[1]);
+                        let throughput_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                            .split(bottom_chunks[0]);
+
+                        //Charts borrow their data, so it has to outlive the widgets
+                        let burndown = burndown(app);
+                        let throughput = throughput(app);
+                        let priorities = priority_breakdown(app);
+                        let timesheet = timesheet(app);
+                        let (burndownchart, openedline, closedline, prioritychart) = render_reports(app, &burndown, &throughput, &priorities);
+                        rect.render_widget(burndownchart, top_chunks[0]);
+                        rect.render_widget(render_timesheet(app, &timesheet), top_chunks[1]);
+                        rect.render_widget(openedline, throughput_chunks[0]);
+                        rect.render_widget(closedline, throughput_chunks[1]);
+                        rect.render_widget(prioritychart, bottom_chunks[1]);
+                        rect.render_widget(render_notice(app), chunks[2]);
+                    },
+                    MenuItem::LinkPicker => {
+                        let picker_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+                            .split(chunks[1]);
+                        let (kindinput, ticketlist) = render_link_picker(app);
+                        rect.render_widget(kindinput, picker_chunks[0]);
+                        rect.render_stateful_widget(ticketlist, picker_chunks[1], &mut app.link_list_state);
+                    },
+                    MenuItem::Fields => {
+                        let field_chunks = Layout::default().direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                            .split(chunks[1]);
+                        let form_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Min(1)].as_ref())
+                            .split(field_chunks[1]);
+                        let (fieldlist, nameinput, kindinput, optionsinput, prompt) = render_fields(app);
+                        rect.render_stateful_widget(fieldlist, field_chunks[0], &mut app.field_list_state);
+                        if app.field_form {
+                            rect.render_widget(nameinput, form_chunks[0]);
+                            rect.render_widget(kindinput, form_chunks[1]);
+                            rect.render_widget(optionsinput, form_chunks[2]);
+                            rect.render_widget(prompt, form_chunks[3]);
+                        } else {
+                            rect.render_widget(prompt, field_chunks[1]);
+                        }
+                    },
+                    MenuItem::TemplatePicker => {
+                        let picker_chunks = Layout::default().direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+                            .split(chunks[1]);
+                        let (templatelist, preview) = render_template_picker(app);
+                        rect.render_stateful_widget(templatelist, picker_chunks[0], &mut app.template_list_state);
+                        rect.render_widget(preview, picker_chunks[1]);
+                    },
+                    MenuItem::AttachmentForm |
+                    MenuItem::ProjectForm |
+                    MenuItem::PrefixForm |
+                    MenuItem::ImportForm => {
+                        let form_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)].as_ref(),).split(chunks[1]);
+                        let (input, mapping, messages) = render_import_form(app);
+                        rect.render_widget(input, form_chunks[0]);
+                        rect.render_widget(mapping, form_chunks[1]);
+                        rect.render_widget(messages, form_chunks[2]);
+                    },
+                    MenuItem::ExportForm => {
+                        let form_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref(),).split(chunks[1]);
+                        let (input, messages) = render_notes_form(app);
+                        rect.render_widget(input, form_chunks[0]);
+                        rect.render_widget(messages, form_chunks[1]);
+                    },
+                    MenuItem::Attachments => {
+                        let attachmentlist = render_attachments(app);
+                        rect.render_stateful_widget(attachmentlist, chunks[1], &mut app.attachment_list_state);
+                        rect.render_widget(render_notice(app), chunks[2]);
+                    },
+                    MenuItem::Backups => {
+                        let backup_chunks = Layout::default().direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+                            .split(chunks[1]);
+                        let (snapshotlist, difflist) = render_backups(app);
+                        rect.render_stateful_widget(snapshotlist, backup_chunks[0], &mut app.snapshot_list_state);
+                        rect.render_stateful_widget(difflist, backup_chunks[1], &mut app.snapshot_diff_state);
+                        rect.render_widget(render_notice(app), chunks[2]);
+                    },
+                    MenuItem::Projects => {
+                        let projectlist = render_projects(app);
+                        rect.render_stateful_widget(projectlist, chunks[1], &mut app.project_list_state);
+                        rect.render_widget(render_notice(app), chunks[2]);
+                    },
+                    MenuItem::TransferPicker => {
+                        let targetlist = render_transfer_targets(app);
+                        rect.render_stateful_widget(targetlist, chunks[1], &mut app.transfer_list_state);
+                    },
+                    MenuItem::FilterForm |
+                    MenuItem::WorkLogForm => {
+                        let form_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref(),).split(chunks[1]);
+                        let (input, _) = render_notes_form(app);
+                        rect.render_widget(input, form_chunks[0]);
+                    },
+                    MenuItem::DuplicateWarning => {
+                        let warning_chunks = Layout::default().direction(Direction::Vertical)
+                            .constraints([Constraint::Length(5), Constraint::Min(1)].as_ref())
+                            .split(chunks[1]);
+                        let (warning, candidates) = render_duplicate_warning(app);
+                        rect.render_widget(warning, warning_chunks[0]);
+                        rect.render_stateful_widget(candidates, warning_chunks[1], &mut app.duplicate_state);
+                    },
                 }
                 
             })?;
@@ -291,14 +713,13 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                             }
                             KeyCode::Char('a') => {
                                     init_add_ticket(app).expect("Cannot add ticket");
-                                    app.edit_focus = EditItem::Title;
+                                    app.edit_focus.set_focus(EditItem::Title);
                         }
                             KeyCode::Char('e') => {
                                 edit_ticket_at_index(app).expect("Cannot edit ticket");
-                                app.edit_focus = EditItem::Title;
+                                app.edit_focus.set_focus(EditItem::Title);
                             }
-                            KeyCode::Char('k') => {
-                                if event.modifiers == KeyModifiers::CONTROL {
+                            KeyCode::Char('k') if event.modifiers == KeyModifiers::CONTROL => {
                                 match app.ticket_view_mode {
                                     TicketViewMode::Open => {
                                         //delete_ticket_at_index(&mut app).expect("Cannot delete ticket");
@@ -308,14 +729,67 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                     }
                                 }
                             }
+                            KeyCode::Char('c') => {
+                                toggle_ticket_status(app).expect("Cannot change ticket status");
+                            }
+                            KeyCode::Char('x') => {
+                                init_close_as_duplicate(app).expect("Cannot close ticket as duplicate");
+                            }
+                            KeyCode::Char('w') => {
+                                toggle_timer(app).expect("Cannot start or stop timer");
+                            }
+                            KeyCode::Char('d') => {
+                                app.field_form = false;
+                                app.prompt = String::new();
+                                if app.field_list_state.selected().is_none() && !app.settings.fields.is_empty() {
+                                    app.field_list_state.select(Some(0));
+                                }
+                                app.active_menu_item = MenuItem::Fields;
+                            }
+                            KeyCode::Char('/') => {
+                                app.messages = Vec::new();
+                                app.input = app.filter.field.as_ref().map(|(name, value)| format!("{}={}", name, value)).unwrap_or_default();
+                                app.prompt = "Filter by custom field, e.g. customer=acme (empty to clear), or go to a ticket by its key".to_string();
+                                app.active_menu_item = MenuItem::FilterForm;
+                            }
+                            KeyCode::Char('s') => {
+                                app.sprint_focus = SprintFocus::Sprints;
+                                if app.sprint_list_state.selected().is_none() && !app.sprints.is_empty() {
+                                    app.sprint_list_state.select(Some(0));
+                                }
+                                let amount_backlog = backlog_tickets(app).len();
+                                let amount_sprint = sprint_tickets(app).len();
+                                clamp_list_state(&mut app.backlog_state, amount_backlog);
+                                clamp_list_state(&mut app.sprint_ticket_state, amount_sprint);
+                                app.active_menu_item = MenuItem::Sprints;
+                            }
+                            KeyCode::Char('f') => {
+                                app.filter.current_sprint = !app.filter.current_sprint;
+                                //set index to 0 to prevent crash
+                                app.ticket_list_state.select(Some(0));
+                            }
+                            KeyCode::Char('g') => {
+                                cycle_tag_filter(app);
+                            }
+                            KeyCode::Char('t') => {
+                                app.tree_view = !app.tree_view;
+                                app.ticket_list_state.select(Some(0));
+                            }
+                            KeyCode::Char('r') => {
+                                app.report_sprint = default_report_sprint(app);
+                                app.active_menu_item = MenuItem::Reports;
+                            }
+                            KeyCode::Char('b') => {
+                                app.board_column = 0;
+                                app.board_card_state.select(None);
+                                select_board_card(app, true);
+                                app.active_menu_item = MenuItem::Board;
                             }
                             KeyCode::PageDown => {
                                 app.scroll += 1;   
                             }
-                            KeyCode::PageUp => {
-                                if app.scroll > 0 {
-                                    app.scroll -= 1;
-                                } 
+                            KeyCode::PageUp if app.scroll > 0 => {
+                                app.scroll -= 1;
                             }
                             KeyCode::Char('v') => {
                                 match app.ticket_view_mode {
@@ -335,21 +809,50 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                             KeyCode::Char('n') => {
                                 init_add_note(app).unwrap();
                             }
+                            KeyCode::Char('u') => {
+                                init_add_attachment(app).expect("Cannot attach file");
+                            }
+                            KeyCode::Char('o') => {
+                                init_attachment_list(app).expect("Cannot list attachments");
+                            }
+                            KeyCode::Char('I') => {
+                                init_import(app).expect("Cannot import tickets");
+                            }
+                            KeyCode::Char('E') => {
+                                init_export(app).expect("Cannot export tickets");
+                            }
+                            KeyCode::Char('B') => {
+                                if let Err(error) = init_backups(app) {
+                                    add_notice(app, format!("Cannot read backups: {}", error));
+                                }
+                            }
+                            KeyCode::Char('p') => {
+                                init_projects(app).expect("Cannot read projects");
+                            }
+                            KeyCode::Char('m') => {
+                                if let Err(error) = init_transfer(app) {
+                                    add_notice(app, format!("Cannot read projects: {}", error));
+                                }
+                            }
+                            KeyCode::Enter => {
+                                if let Err(error) = follow_moved_ticket(app) {
+                                    add_notice(app, format!("Cannot open project: {}", error));
+                                }
+                            }
+                            //Number keys tick off the checklist items of the selected ticket
+                            KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
+                                let number = c.to_digit(10).unwrap() as usize;
+                                toggle_checklist_item(app, number).expect("Cannot update checklist");
+                            }
                             KeyCode::Down => {      
                                 if let Some(selected) = app.ticket_list_state.selected() {
                                     app.scroll = 0;
-                                    let mut amount_tickets = 0;
-                                    
-                                    if app.ticket_view_mode == TicketViewMode::Open {
-                                        amount_tickets = app.open_tickets.len().try_into().unwrap();
-                                    } else if app.ticket_view_mode == TicketViewMode::Closed {
-                                        amount_tickets = app.closed_tickets.len().try_into().unwrap();
-                                    }
-                                    
+                                    let amount_tickets = visible_tickets(app).len();
+
                                     if amount_tickets == 0 {
                                         continue;
                                     }
-                                    if selected >= (amount_tickets - 1).try_into().unwrap() {
+                                    if selected >= amount_tickets - 1 {
                                         app.ticket_list_state.select(Some(0));
                                     } else {
                                         app.ticket_list_state.select(Some(selected + 1));                            
@@ -359,20 +862,14 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                             KeyCode::Up => {
                                 if let Some(selected) = app.ticket_list_state.selected() {
                                     app.scroll = 0;
-                                    let mut amount_tickets = 0;
-            
-                                    if app.ticket_view_mode == TicketViewMode::Open {
-                                        amount_tickets = app.open_tickets.len().try_into().unwrap();
-                                    } else if app.ticket_view_mode == TicketViewMode::Closed {
-                                        amount_tickets = app.closed_tickets.len().try_into().unwrap();
-                                    }
+                                    let amount_tickets = visible_tickets(app).len();
                                     if amount_tickets == 0 {
                                         continue;
                                     }
                                     if selected > 0 {
                                         app.ticket_list_state.select(Some(selected - 1));
                                     } else {
-                                        app.ticket_list_state.select(Some((amount_tickets - 1).try_into().unwrap()));
+                                        app.ticket_list_state.select(Some(amount_tickets - 1));
                                     }
                                 }
                             }
@@ -392,6 +889,23 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                 app.sort_by = SortBy::Updated;
                                 sort(app);
                             }
+                            KeyCode::F(5) => {
+                                app.sort_by = SortBy::Due;
+                                sort(app);
+                            }
+                            KeyCode::F(6) => {
+                                //Steps through the custom fields, then back to sorting by ID
+                                app.sort_by = match app.sort_by {
+                                    SortBy::Field(i) if i + 1 < app.settings.fields.len() => SortBy::Field(i + 1),
+                                    SortBy::Field(_) => SortBy::ID,
+                                    _ if app.settings.fields.is_empty() => SortBy::ID,
+                                    _ => SortBy::Field(0),
+                                };
+                                sort(app);
+                            }
+                            KeyCode::Esc => {
+                                app.due_banner = Vec::new();
+                            }
                             _ => {}
                         },
                         Event::Tick => {}
@@ -402,28 +916,62 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                         Event::Input(event) => match event.code {
                         KeyCode::Enter => {
                             match app.edit_focus {
-                                EditItem::Title => app.edit_focus = EditItem::Description,
+                                EditItem::Title => app.edit_focus = EditItem::Assignee,
+                                EditItem::Assignee => app.edit_focus = EditItem::Tags,
+                                //Enter adds the typed tag, or moves on when nothing was typed
+                                EditItem::Tags if !app.tag_input.is_empty() => add_tag_input(app),
+                                EditItem::Tags => app.edit_focus = EditItem::Description,
                                 EditItem::Description => app.edit_focus = EditItem::Priority,
                                 EditItem::Priority => app.edit_focus = EditItem::Status,
-                                EditItem::Status => {
-                                    add_ticket(app).unwrap();
-                                    app.active_menu_item = MenuItem::Tickets;
+                                EditItem::Status => app.edit_focus = EditItem::Estimate,
+                                EditItem::Estimate => app.edit_focus = EditItem::Due,
+                                EditItem::Due => app.edit_focus = EditItem::Kind,
+                                EditItem::Kind => app.edit_focus = EditItem::Parent,
+                                EditItem::Parent => app.edit_focus = EditItem::Checklist,
+                                //Enter adds the typed item, or moves on when nothing was typed
+                                EditItem::Checklist if !app.checklist_input.trim().is_empty() => add_checklist_input(app),
+                                //Custom fields come last, the ticket is saved after the last one
+                                EditItem::Checklist if !app.settings.fields.is_empty() => app.edit_focus = EditItem::Field(0),
+                                EditItem::Field(i) if i + 1 < app.settings.fields.len() => app.edit_focus = EditItem::Field(i + 1),
+                                EditItem::Checklist | EditItem::Field(_) => {
+                                    if let Err(error) = save_ticket(app) {
+                                        add_notice(app, format!("Ticket was not saved: {}", error));
+                                    }
                                 },
                                 EditItem::Notes => todo!(),
                         }
                     }
                         KeyCode::F(5) => {
                             //Save ticket
-                            add_ticket(app).unwrap();
-                            app.active_menu_item = MenuItem::Tickets;
+                            if let Err(error) = save_ticket(app) {
+                                add_notice(app, format!("Ticket was not saved: {}", error));
+                            }
+                        }
+                        KeyCode::F(6) => {
+                            init_link_picker(app).expect("Cannot link ticket");
+                        }
+                        KeyCode::Right if app.edit_focus == EditItem::Tags => {
+                            complete_tag(app);
+                        }
+                        KeyCode::Right | KeyCode::Left if app.edit_focus == EditItem::Checklist => {
+                            toggle_edit_checklist(app);
                         }
                         KeyCode::Tab => {
                             //Set focus to next EditItem
                             app.edit_focus = match app.edit_focus {
-                                EditItem::Title => EditItem::Description,
+                                EditItem::Title => EditItem::Assignee,
+                                EditItem::Assignee => EditItem::Tags,
+                                EditItem::Tags => EditItem::Description,
                                 EditItem::Description => EditItem::Priority,
                                 EditItem::Priority => EditItem::Status,
-                                EditItem::Status => EditItem::Title,
+                                EditItem::Status => EditItem::Estimate,
+                                EditItem::Estimate => EditItem::Due,
+                                EditItem::Due => EditItem::Kind,
+                                EditItem::Kind => EditItem::Parent,
+                                EditItem::Parent => EditItem::Checklist,
+                                EditItem::Checklist if !app.settings.fields.is_empty() => EditItem::Field(0),
+                                EditItem::Field(i) if i + 1 < app.settings.fields.len() => EditItem::Field(i + 1),
+                                EditItem::Checklist | EditItem::Field(_) => EditItem::Title,
                                 EditItem::Notes => todo!(),
                             };
                        }
@@ -431,10 +979,20 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                         KeyCode::BackTab => {
                             //Set focus to previous EditItem
                             app.edit_focus = match app.edit_focus {
-                                EditItem::Title => EditItem::Status,
-                                EditItem::Description => EditItem::Title,
+                                EditItem::Title if !app.settings.fields.is_empty() => EditItem::Field(app.settings.fields.len() - 1),
+                                EditItem::Title => EditItem::Checklist,
+                                EditItem::Assignee => EditItem::Title,
+                                EditItem::Description => EditItem::Tags,
+                                EditItem::Tags => EditItem::Assignee,
                                 EditItem::Priority => EditItem::Description,
                                 EditItem::Status => EditItem::Priority,
+                                EditItem::Estimate => EditItem::Status,
+                                EditItem::Kind => EditItem::Due,
+                                EditItem::Due => EditItem::Estimate,
+                                EditItem::Parent => EditItem::Kind,
+                                EditItem::Checklist => EditItem::Parent,
+                                EditItem::Field(0) => EditItem::Checklist,
+                                EditItem::Field(i) => EditItem::Field(i - 1),
                                 EditItem::Notes => todo!(),
                             };
                         }
@@ -443,11 +1001,39 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                 EditItem::Title => {
                                     app.edit_ticket.title.push(c);
                                 }
+                                EditItem::Assignee => {
+                                    app.edit_ticket.assignee.get_or_insert_with(String::new).push(c);
+                                }
+                                EditItem::Tags => {
+                                    app.tag_input.push(c);
+                                }
                                 EditItem::Description => {
                                     app.edit_ticket.description.push(c);
                                 }
                                 EditItem::Priority => {}
                                 EditItem::Status => {}
+                                EditItem::Estimate => {
+                                    //Only numbers are valid estimates
+                                    if c.is_ascii_digit() || c == '.' {
+                                        app.estimate_input.push(c);
+                                    }
+                                }
+                                EditItem::Due => {
+                                    app.due_input.push(c);
+                                }
+                                EditItem::Kind => {}
+                                EditItem::Parent => {
+                                    //Parents are referenced by ticket key, such as OPS-42 or 42
+                                    if c.is_ascii_alphanumeric() || c == '-' || c == '#' {
+                                        app.parent_input.push(c);
+                                    }
+                                }
+                                EditItem::Checklist => {
+                                    app.checklist_input.push(c);
+                                }
+                                EditItem::Field(i) => {
+                                    app.field_inputs[i].push(c);
+                                }
                                 EditItem::Notes => {}
                             }
                         }
@@ -456,24 +1042,56 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                 EditItem::Title => {
                                     app.edit_ticket.title.pop();
                                 }
+                                EditItem::Assignee => {
+                                    if let Some(assignee) = app.edit_ticket.assignee.as_mut() {
+                                        assignee.pop();
+                                        if assignee.is_empty() {
+                                            app.edit_ticket.assignee = None;
+                                        }
+                                    }
+                                }
+                                EditItem::Tags => {
+                                    //Removes the last tag once nothing is typed
+                                    if app.tag_input.pop().is_none() {
+                                        app.edit_ticket.tags.pop_last();
+                                    }
+                                }
                                 EditItem::Description => {
                                     app.edit_ticket.description.pop();
                                 }
                                 EditItem::Priority => {}
                                 EditItem::Status => {}
+                                EditItem::Estimate => {
+                                    app.estimate_input.pop();
+                                }
+                                EditItem::Due => {
+                                    app.due_input.pop();
+                                }
+                                EditItem::Kind => {}
+                                EditItem::Parent => {
+                                    app.parent_input.pop();
+                                }
+                                EditItem::Checklist => {
+                                    //Removes the selected item once nothing is typed
+                                    if app.checklist_input.pop().is_none() {
+                                        remove_edit_checklist(app);
+                                    }
+                                }
+                                EditItem::Field(i) => {
+                                    app.field_inputs[i].pop();
+                                }
                                 EditItem::Notes => {}
                             }
                         }
                         KeyCode::Up => {
                             match app.edit_focus {
                                 EditItem::Title => {}
+                                EditItem::Assignee => {}
+                                EditItem::Tags => {}
                                 EditItem::Description => {}
                                 EditItem::Priority => {
-                                    if app.edit_priority_state.selected() == Some(0) {
-                                        app.edit_priority_state.select(Some(2));
-                                    } else {
-                                        app.edit_priority_state.select(Some(app.edit_priority_state.selected().unwrap() - 1));
-                                    }
+                                    let amount = app.settings.priorities.len();
+                                    step_list_state(&mut app.edit_priority_state, amount, false);
                                 }
                                 EditItem::Status => {
                                     if app.edit_status_state.selected() == Some(0) {
@@ -482,20 +1100,28 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                         app.edit_status_state.select(Some(app.edit_status_state.selected().unwrap() - 1));
                                     }
                                 }
+                                EditItem::Estimate => {}
+                                EditItem::Due => {}
+                                EditItem::Kind => {
+                                    //Only two kinds, so up and down both switch
+                                    let selected = if app.edit_kind_state.selected() == Some(0) {1} else {0};
+                                    app.edit_kind_state.select(Some(selected));
+                                }
+                                EditItem::Parent => {}
+                                EditItem::Checklist => step_edit_checklist(app, false),
+                                EditItem::Field(i) => cycle_field_option(app, i, false),
                                 EditItem::Notes => {}
                             }
                         }
                         KeyCode::Down => {
                             match app.edit_focus {
                                 EditItem::Title => {}
+                                EditItem::Assignee => {}
+                                EditItem::Tags => {}
                                 EditItem::Description => {}
                                 EditItem::Priority => {
-                                    if app.edit_priority_state.selected() == Some(2) {
-                                        app.edit_priority_state.select(Some(0));
-                                    } else {
-                                        app.edit_priority_state.select(Some(app.edit_priority_state.selected().unwrap() + 1));
-                                    }
-
+                                    let amount = app.settings.priorities.len();
+                                    step_list_state(&mut app.edit_priority_state, amount, true);
                                 }
                                 EditItem::Status => {
                                     if app.edit_status_state.selected() == Some(1) {
@@ -504,6 +1130,15 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                         app.edit_status_state.select(Some(app.edit_status_state.selected().unwrap() + 1));
                                     }
                                 }
+                                EditItem::Estimate => {}
+                                EditItem::Due => {}
+                                EditItem::Kind => {
+                                    let selected = if app.edit_kind_state.selected() == Some(0) {1} else {0};
+                                    app.edit_kind_state.select(Some(selected));
+                                }
+                                EditItem::Parent => {}
+                                EditItem::Checklist => step_edit_checklist(app, true),
+                                EditItem::Field(i) => cycle_field_option(app, i, true),
                                 EditItem::Notes => {}
                             }
                         }
@@ -542,17 +1177,12 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                                 add_note(app).unwrap();
                         }
                     }
-                        KeyCode::Char(c) => {
-                            //Only allow edit up to 1 line
-                            if app.messages.len() < 1 {
+                        //Only allow edit up to 1 line
+                        KeyCode::Char(c) if app.messages.is_empty() => {
                             app.input.push(c);
-                            }
                         }
-                        KeyCode::Backspace => {
-                            //Only allow edit up to 1 line
-                            if app.messages.len() < 1 {
+                        KeyCode::Backspace if app.messages.is_empty() => {
                             app.input.pop();
-                            }
                         }
                         KeyCode::Esc => {
                             //return to Ticket menu without saving
@@ -567,13 +1197,680 @@ pub fn run(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
                 },
                 MenuItem::ConfirmForm => todo!(),
                 MenuItem::Help =>  match rx.recv()? {           
-                    Event::Input(event) => match event.code {
-                    KeyCode::Esc => {
+                    Event::Input(event) => if event.code == KeyCode::Esc {
                         app.active_menu_item = MenuItem::Tickets;
+                    },
+                    Event::Tick => {}
+                },
+                MenuItem::Board => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Left if event.modifiers == KeyModifiers::SHIFT => {
+                                move_board_card(app, false).expect("Cannot move ticket");
+                            }
+                            KeyCode::Right if event.modifiers == KeyModifiers::SHIFT => {
+                                move_board_card(app, true).expect("Cannot move ticket");
+                            }
+                            KeyCode::Left => {
+                                select_board_column(app, false);
+                            }
+                            KeyCode::Right => {
+                                select_board_column(app, true);
+                            }
+                            KeyCode::Up => {
+                                select_board_card(app, false);
+                            }
+                            KeyCode::Down => {
+                                select_board_card(app, true);
+                            }
+                            KeyCode::Esc | KeyCode::Char('b') => {
+                                app.active_menu_item = MenuItem::Tickets;
+                                //set index to 0 since the lists may have changed
+                                app.ticket_list_state.select(Some(0));
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
                     }
-                    _ => {}
                 },
-                    Event::Tick => {}
+                MenuItem::Sprints => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Tab => {
+                                app.sprint_focus = match app.sprint_focus {
+                                    SprintFocus::Sprints => SprintFocus::Backlog,
+                                    SprintFocus::Backlog => SprintFocus::Sprint,
+                                    SprintFocus::Sprint => SprintFocus::Sprints,
+                                };
+                            }
+                            KeyCode::BackTab => {
+                                app.sprint_focus = match app.sprint_focus {
+                                    SprintFocus::Sprints => SprintFocus::Sprint,
+                                    SprintFocus::Backlog => SprintFocus::Sprints,
+                                    SprintFocus::Sprint => SprintFocus::Backlog,
+                                };
+                            }
+                            KeyCode::Up | KeyCode::Down => {
+                                let forward = event.code == KeyCode::Down;
+                                match app.sprint_focus {
+                                    SprintFocus::Sprints => {
+                                        step_list_state(&mut app.sprint_list_state, app.sprints.len(), forward);
+                                        //The sprint ticket list belongs to the newly selected sprint
+                                        let amount_tickets = sprint_tickets(app).len();
+                                        app.sprint_ticket_state.select(None);
+                                        clamp_list_state(&mut app.sprint_ticket_state, amount_tickets);
+                                    }
+                                    SprintFocus::Backlog => {
+                                        let amount_tickets = backlog_tickets(app).len();
+                                        step_list_state(&mut app.backlog_state, amount_tickets, forward);
+                                    }
+                                    SprintFocus::Sprint => {
+                                        let amount_tickets = sprint_tickets(app).len();
+                                        step_list_state(&mut app.sprint_ticket_state, amount_tickets, forward);
+                                    }
+                                }
+                            }
+                            KeyCode::Right if app.sprint_focus == SprintFocus::Backlog => {
+                                move_to_sprint(app).expect("Cannot move ticket to sprint");
+                                let amount_tickets = sprint_tickets(app).len();
+                                clamp_list_state(&mut app.sprint_ticket_state, amount_tickets);
+                            }
+                            KeyCode::Left if app.sprint_focus == SprintFocus::Sprint => {
+                                move_to_backlog(app).expect("Cannot move ticket to backlog");
+                                let amount_tickets = backlog_tickets(app).len();
+                                clamp_list_state(&mut app.backlog_state, amount_tickets);
+                            }
+                            KeyCode::Char('n') => {
+                                init_add_sprint(app).expect("Cannot add sprint");
+                            }
+                            KeyCode::Char('x') => {
+                                if let Err(error) = close_sprint(app) {
+                                    add_notice(app, format!("Cannot close sprint: {}", error));
+                                }
+                            }
+                            KeyCode::Char('u') => {
+                                let mut settings = app.settings.clone();
+                                settings.estimate_unit = settings.estimate_unit.toggle();
+                                save_settings(app, settings);
+                            }
+                            KeyCode::Char('+') => {
+                                let mut settings = app.settings.clone();
+                                settings.team_capacity += 1.0;
+                                save_settings(app, settings);
+                            }
+                            KeyCode::Char('-') if app.settings.team_capacity >= 1.0 => {
+                                let mut settings = app.settings.clone();
+                                settings.team_capacity -= 1.0;
+                                save_settings(app, settings);
+                            }
+                            KeyCode::Esc => {
+                                app.active_menu_item = MenuItem::Tickets;
+                                //set index to 0 since the lists may have changed
+                                app.ticket_list_state.select(Some(0));
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::Reports => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            //Pick the sprint for the burndown chart
+                            KeyCode::Left if app.report_sprint > 0 => {
+                                app.report_sprint -= 1;
+                            }
+                            KeyCode::Right if app.report_sprint + 1 < app.sprints.len() => {
+                                app.report_sprint += 1;
+                            }
+                            //Pick the week for the timesheet
+                            KeyCode::Up => {
+                                app.timesheet_week += 1;
+                            }
+                            KeyCode::Down if app.timesheet_week > 0 => {
+                                app.timesheet_week -= 1;
+                            }
+                            KeyCode::Char('w') => {
+                                if let Err(error) = write_weekly_report(app) {
+                                    add_notice(app, format!("Cannot write status report: {}", error));
+                                }
+                            }
+                            KeyCode::Esc => {
+                                app.notice = String::new();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::SprintForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                match app.sprint_edit_focus {
+                                    SprintItem::Name => app.sprint_edit_focus = SprintItem::Goal,
+                                    SprintItem::Goal => app.sprint_edit_focus = SprintItem::Start,
+                                    SprintItem::Start => app.sprint_edit_focus = SprintItem::End,
+                                    SprintItem::End => add_sprint(app).expect("Cannot save sprint"),
+                                }
+                            }
+                            KeyCode::F(5) => {
+                                add_sprint(app).expect("Cannot save sprint");
+                            }
+                            KeyCode::Tab => {
+                                app.sprint_edit_focus = match app.sprint_edit_focus {
+                                    SprintItem::Name => SprintItem::Goal,
+                                    SprintItem::Goal => SprintItem::Start,
+                                    SprintItem::Start => SprintItem::End,
+                                    SprintItem::End => SprintItem::Name,
+                                };
+                            }
+                            KeyCode::BackTab => {
+                                app.sprint_edit_focus = match app.sprint_edit_focus {
+                                    SprintItem::Name => SprintItem::End,
+                                    SprintItem::Goal => SprintItem::Name,
+                                    SprintItem::Start => SprintItem::Goal,
+                                    SprintItem::End => SprintItem::Start,
+                                };
+                            }
+                            KeyCode::Char(c) => {
+                                match app.sprint_edit_focus {
+                                    SprintItem::Name => app.edit_sprint.name.push(c),
+                                    SprintItem::Goal => app.edit_sprint.goal.push(c),
+                                    SprintItem::Start => app.sprint_start_input.push(c),
+                                    SprintItem::End => app.sprint_end_input.push(c),
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                match app.sprint_edit_focus {
+                                    SprintItem::Name => app.edit_sprint.name.pop(),
+                                    SprintItem::Goal => app.edit_sprint.goal.pop(),
+                                    SprintItem::Start => app.sprint_start_input.pop(),
+                                    SprintItem::End => app.sprint_end_input.pop(),
+                                };
+                            }
+                            KeyCode::Esc => {
+                                //return to Sprint menu without saving
+                                app.prompt = String::new();
+                                app.active_menu_item = MenuItem::Sprints;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::LinkPicker => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                pick_link(app).expect("Cannot link ticket");
+                            }
+                            //The kind is fixed when closing as a duplicate
+                            KeyCode::Left if !app.close_as_duplicate => {
+                                let amount = LinkKind::all().len();
+                                app.link_kind = (app.link_kind + amount - 1) % amount;
+                            }
+                            KeyCode::Right if !app.close_as_duplicate => {
+                                app.link_kind = (app.link_kind + 1) % LinkKind::all().len();
+                            }
+                            KeyCode::Up => {
+                                let amount = link_candidates(app).len();
+                                step_list_state(&mut app.link_list_state, amount, false);
+                            }
+                            KeyCode::Down => {
+                                let amount = link_candidates(app).len();
+                                step_list_state(&mut app.link_list_state, amount, true);
+                            }
+                            KeyCode::Esc => {
+                                if app.close_as_duplicate {
+                                    app.close_as_duplicate = false;
+                                    app.active_menu_item = MenuItem::Tickets;
+                                } else {
+                                    app.active_menu_item = MenuItem::EditForm;
+                                }
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::Fields if app.field_form => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                if let Err(error) = add_field(app) {
+                                    app.prompt = format!("Field was not saved: {}", error);
+                                }
+                            }
+                            KeyCode::Tab => {
+                                app.field_focus = match app.field_focus {
+                                    FieldItem::Name => FieldItem::Kind,
+                                    FieldItem::Kind => FieldItem::Options,
+                                    FieldItem::Options => FieldItem::Name,
+                                };
+                            }
+                            KeyCode::BackTab => {
+                                app.field_focus = match app.field_focus {
+                                    FieldItem::Name => FieldItem::Options,
+                                    FieldItem::Kind => FieldItem::Name,
+                                    FieldItem::Options => FieldItem::Kind,
+                                };
+                            }
+                            KeyCode::Left if app.field_focus == FieldItem::Kind => {
+                                app.field_kind = (app.field_kind + FIELD_KINDS.len() - 1) % FIELD_KINDS.len();
+                            }
+                            KeyCode::Right if app.field_focus == FieldItem::Kind => {
+                                app.field_kind = (app.field_kind + 1) % FIELD_KINDS.len();
+                            }
+                            KeyCode::Char(c) => {
+                                match app.field_focus {
+                                    FieldItem::Name => app.field_name_input.push(c),
+                                    FieldItem::Kind => {}
+                                    FieldItem::Options => app.field_options_input.push(c),
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                match app.field_focus {
+                                    FieldItem::Name => app.field_name_input.pop(),
+                                    FieldItem::Kind => None,
+                                    FieldItem::Options => app.field_options_input.pop(),
+                                };
+                            }
+                            KeyCode::Esc => {
+                                //close the form without saving
+                                app.prompt = String::new();
+                                app.field_form = false;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::Fields => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Char('n') => {
+                                if let Err(error) = init_add_field(app) {
+                                    add_notice(app, format!("Cannot add field: {}", error));
+                                }
+                            }
+                            KeyCode::Delete => {
+                                remove_field(app).expect("Cannot remove field");
+                            }
+                            KeyCode::Char('k') if event.modifiers == KeyModifiers::CONTROL => {
+                                remove_field(app).expect("Cannot remove field");
+                            }
+                            KeyCode::Up => {
+                                let amount = app.settings.fields.len();
+                                step_list_state(&mut app.field_list_state, amount, false);
+                            }
+                            KeyCode::Down => {
+                                let amount = app.settings.fields.len();
+                                step_list_state(&mut app.field_list_state, amount, true);
+                            }
+                            KeyCode::Esc => {
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::TemplatePicker => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                apply_template(app).expect("Cannot use template");
+                            }
+                            KeyCode::Up => {
+                                let amount = app.templates.len() + 1;
+                                step_list_state(&mut app.template_list_state, amount, false);
+                            }
+                            KeyCode::Down => {
+                                let amount = app.templates.len() + 1;
+                                step_list_state(&mut app.template_list_state, amount, true);
+                            }
+                            KeyCode::Esc => {
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::AttachmentForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                add_attachment(app).expect("Cannot attach file");
+                            }
+                            KeyCode::Char(c) => {
+                                app.input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.input.pop();
+                            }
+                            KeyCode::Esc => {
+                                app.input = String::new();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::ImportForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            //The first Enter previews, the second imports what was previewed
+                            KeyCode::Enter => {
+                                let result = if app.import_preview.is_some() {import_tickets(app)} else {preview_import(app)};
+                                if let Err(error) = result {
+                                    reset_import_preview(app);
+                                    app.messages.push(format!("Cannot import tickets: {}", error));
+                                }
+                            }
+                            KeyCode::Tab => {
+                                app.import_focus_mapping = !app.import_focus_mapping;
+                            }
+                            KeyCode::Char(c) => {
+                                if app.import_focus_mapping {
+                                    app.import_mapping.push(c);
+                                } else {
+                                    app.input.push(c);
+                                }
+                                reset_import_preview(app);
+                            }
+                            KeyCode::Backspace => {
+                                if app.import_focus_mapping {
+                                    app.import_mapping.pop();
+                                } else {
+                                    app.input.pop();
+                                }
+                                reset_import_preview(app);
+                            }
+                            KeyCode::Esc => {
+                                app.input = String::new();
+                                app.messages = Vec::new();
+                                app.import_preview = None;
+                                app.import_mapping = String::new();
+                                app.import_focus_mapping = false;
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::ExportForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                export_tickets(app).expect("Cannot export tickets");
+                            }
+                            KeyCode::Char(c) => {
+                                app.input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.input.pop();
+                            }
+                            KeyCode::Esc => {
+                                app.input = String::new();
+                                app.messages = Vec::new();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::Attachments => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                open_attachment(app).expect("Cannot open attachment");
+                            }
+                            KeyCode::Up => {
+                                let amount = app.edit_ticket.attachments.len();
+                                step_list_state(&mut app.attachment_list_state, amount, false);
+                            }
+                            KeyCode::Down => {
+                                let amount = app.edit_ticket.attachments.len();
+                                step_list_state(&mut app.attachment_list_state, amount, true);
+                            }
+                            KeyCode::Delete => {
+                                remove_attachment(app).expect("Cannot remove attachment");
+                            }
+                            KeyCode::Esc => {
+                                app.notice = String::new();
+                                app.edit_ticket = Tickets::default();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::Backups => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Tab => {
+                                app.snapshot_focus_diff = !app.snapshot_focus_diff;
+                            }
+                            KeyCode::Up | KeyCode::Down => {
+                                let forward = event.code == KeyCode::Down;
+                                if app.snapshot_focus_diff {
+                                    let amount = app.snapshot_diff.len();
+                                    step_list_state(&mut app.snapshot_diff_state, amount, forward);
+                                } else {
+                                    let amount = app.snapshots.len();
+                                    step_list_state(&mut app.snapshot_list_state, amount, forward);
+                                    app.snapshot_diff_state.select(Some(0));
+                                    if let Err(error) = load_snapshot_diff(app) {
+                                        add_notice(app, format!("Cannot read snapshot: {}", error));
+                                    }
+                                }
+                            }
+                            KeyCode::Char('n') => {
+                                if let Err(error) = take_snapshot(app) {
+                                    add_notice(app, format!("Cannot save snapshot: {}", error));
+                                }
+                            }
+                            KeyCode::Char('R') => {
+                                if let Err(error) = restore_selected_snapshot(app) {
+                                    add_notice(app, format!("Cannot restore snapshot: {}", error));
+                                }
+                            }
+                            KeyCode::Enter if app.snapshot_focus_diff => {
+                                if let Err(error) = restore_selected_ticket(app) {
+                                    add_notice(app, format!("Cannot restore ticket: {}", error));
+                                }
+                            }
+                            KeyCode::Delete if !app.snapshot_focus_diff => {
+                                if let Err(error) = remove_selected_snapshot(app) {
+                                    add_notice(app, format!("Cannot remove snapshot: {}", error));
+                                }
+                            }
+                            KeyCode::Esc => {
+                                app.notice = String::new();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::Projects => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                switch_project(app).expect("Cannot open project");
+                            }
+                            KeyCode::Up => {
+                                let amount = app.projects.len() + 1;
+                                step_list_state(&mut app.project_list_state, amount, false);
+                            }
+                            KeyCode::Down => {
+                                let amount = app.projects.len() + 1;
+                                step_list_state(&mut app.project_list_state, amount, true);
+                            }
+                            KeyCode::Char('n') => {
+                                init_project_form(app).expect("Cannot add project");
+                            }
+                            KeyCode::Char('k') => {
+                                init_prefix_form(app).expect("Cannot change key prefix");
+                            }
+                            KeyCode::Delete => {
+                                unregister_project(app).expect("Cannot remove project");
+                            }
+                            KeyCode::Esc => {
+                                app.notice = String::new();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::ProjectForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                register_project(app).expect("Cannot add project");
+                            }
+                            KeyCode::Char(c) => {
+                                app.input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.input.pop();
+                            }
+                            KeyCode::Esc => {
+                                app.input = String::new();
+                                app.active_menu_item = MenuItem::Projects;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::TransferPicker => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                if let Err(error) = transfer_selected_ticket(app, false) {
+                                    add_notice(app, format!("Cannot move ticket: {}", error));
+                                }
+                            }
+                            KeyCode::Char('c') => {
+                                if let Err(error) = transfer_selected_ticket(app, true) {
+                                    add_notice(app, format!("Cannot copy ticket: {}", error));
+                                }
+                            }
+                            KeyCode::Up => {
+                                let amount = app.transfer_targets.len();
+                                step_list_state(&mut app.transfer_list_state, amount, false);
+                            }
+                            KeyCode::Down => {
+                                let amount = app.transfer_targets.len();
+                                step_list_state(&mut app.transfer_list_state, amount, true);
+                            }
+                            KeyCode::Esc => {
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::PrefixForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                save_prefix(app).expect("Cannot save key prefix");
+                            }
+                            KeyCode::Char(c) => {
+                                app.input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.input.pop();
+                            }
+                            KeyCode::Esc => {
+                                app.input = String::new();
+                                app.active_menu_item = MenuItem::Projects;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::FilterForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                set_field_filter(app);
+                            }
+                            KeyCode::Char(c) => {
+                                app.input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.input.pop();
+                            }
+                            KeyCode::Esc => {
+                                app.input = String::new();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::WorkLogForm => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter => {
+                                stop_timer(app).expect("Cannot log time");
+                            }
+                            KeyCode::Char(c) => {
+                                app.input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.input.pop();
+                            }
+                            KeyCode::Esc => {
+                                //return to Ticket menu, the timer keeps running
+                                app.input = String::new();
+                                app.active_menu_item = MenuItem::Tickets;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
+                },
+                MenuItem::DuplicateWarning => {
+                    match rx.recv()? {
+                        Event::Input(event) => match event.code {
+                            KeyCode::Enter | KeyCode::Char('l') => {
+                                link_duplicate(app).expect("Cannot save ticket");
+                            }
+                            KeyCode::Char('p') => {
+                                add_ticket(app).expect("Cannot save ticket");
+                            }
+                            KeyCode::Up => {
+                                let amount = duplicate_candidates(app).len();
+                                step_list_state(&mut app.duplicate_state, amount, false);
+                            }
+                            KeyCode::Down => {
+                                let amount = duplicate_candidates(app).len();
+                                step_list_state(&mut app.duplicate_state, amount, true);
+                            }
+                            KeyCode::Esc => {
+                                app.active_menu_item = MenuItem::EditForm;
+                            }
+                            _ => {}
+                        },
+                        Event::Tick => {}
+                    }
                 },
             }
             
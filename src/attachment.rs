@@ -0,0 +1,143 @@
+//For files attached to tickets: adding, opening and removing them
+
+use std::{fs, io, path::PathBuf, process::{Command, Stdio}};
+use chrono::Utc;
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+use crate::worklog::*;
+
+//Asks for the path of the file to attach to the selected ticket
+pub fn init_add_attachment(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    if let Some(ticket) = selected_ticket(app).filter(|ticket| !moved_notice(app, ticket)) {
+        app.edit_ticket = ticket;
+        app.input = String::new();
+        app.messages = Vec::new();
+        app.prompt = "Path of the file to attach (Enter to attach)".to_string();
+        app.active_menu_item = MenuItem::AttachmentForm;
+    }
+    Ok(())
+}
+
+//Stores the typed file and adds it to the ticket, errors are shown below the input
+pub fn add_attachment(app: &mut AppState) -> Result<(), Error> {
+    let path = expand_path(app.input.trim().trim_matches(|c| c == '"' || c == '\''));
+    let attachment = match store_attachment(&path, &current_user()) {
+        Ok(attachment) => attachment,
+        Err(error) => {
+            app.messages.push(format!("Cannot attach {}: {}", path.display(), error));
+            return Ok(());
+        }
+    };
+    //Attaching the same file again only updates its metadata
+    app.edit_ticket.attachments.retain(|existing| existing.hash != attachment.hash || existing.name != attachment.name);
+    app.edit_ticket.attachments.push(attachment);
+    app.edit_ticket.updated_at = Utc::now();
+    replace_ticket(app, app.edit_ticket.clone());
+    update_db(app);
+    update_ticket_count(app);
+
+    app.edit_ticket = Tickets::default();
+    app.input = String::new();
+    app.active_menu_item = MenuItem::Tickets;
+    Ok(())
+}
+
+//Paths starting with ~ are relative to the home directory
+pub fn expand_path(input: &str) -> PathBuf {
+    match (input.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(input),
+    }
+}
+
+//Lists the attachments of the selected ticket
+pub fn init_attachment_list(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    if let Some(ticket) = selected_ticket(app) {
+        if ticket.attachments.is_empty() {
+            app.notice = format!("Ticket {} has no attachments, press u to add one", app.settings.ticket_key(ticket.id));
+            return Ok(());
+        }
+        app.edit_ticket = ticket;
+        app.attachment_list_state.select(Some(0));
+        app.active_menu_item = MenuItem::Attachments;
+    }
+    Ok(())
+}
+
+fn selected_attachment(app: &AppState) -> Option<Attachment> {
+    app.attachment_list_state.selected().and_then(|selected| app.edit_ticket.attachments.get(selected)).cloned()
+}
+
+//Copies the attachment under its own name to the temp directory, so the opener knows the file type
+pub fn open_attachment(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    if let Some(attachment) = selected_attachment(app) {
+        let source = attachment_path(&attachment.hash);
+        if !source.exists() {
+            app.notice = format!("{} is missing from {}", attachment.name, source.display());
+            return Ok(());
+        }
+        let dir = std::env::temp_dir().join("scrum-attachments").join(&attachment.hash);
+        let target = dir.join(&attachment.name);
+        fs::create_dir_all(&dir)?;
+        fs::copy(&source, &target)?;
+        match system_open(&target) {
+            Ok(()) => app.notice = format!("Opened {}", attachment.name),
+            Err(error) => app.notice = format!("Cannot open {}: {}", attachment.name, error),
+        }
+    }
+    Ok(())
+}
+
+//Output of the opener is discarded so it does not draw over the TUI
+fn system_open(path: &PathBuf) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+//Removes the attachment from the ticket, the stored file is deleted once no ticket uses it
+pub fn remove_attachment(app: &mut AppState) -> Result<(), Error> {
+    if let Some(selected) = app.attachment_list_state.selected() {
+        if selected >= app.edit_ticket.attachments.len() {
+            return Ok(());
+        }
+        let attachment = app.edit_ticket.attachments.remove(selected);
+        app.edit_ticket.updated_at = Utc::now();
+        replace_ticket(app, app.edit_ticket.clone());
+        update_db(app);
+        update_ticket_count(app);
+
+        let in_use = all_tickets(app)
+            .iter()
+            .any(|ticket| ticket.attachments.iter().any(|other| other.hash == attachment.hash));
+        if !in_use {
+            let _ = fs::remove_file(attachment_path(&attachment.hash));
+        }
+        app.notice = format!("Removed {}", attachment.name);
+
+        if app.edit_ticket.attachments.is_empty() {
+            app.active_menu_item = MenuItem::Tickets;
+        } else {
+            clamp_list_state(&mut app.attachment_list_state, app.edit_ticket.attachments.len());
+        }
+    }
+    Ok(())
+}
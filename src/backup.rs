@@ -0,0 +1,79 @@
+//For undoing mistakes: snapshots of the ticket database, what changed since one and restoring it
+
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+
+//Lists the snapshots with the changes since the newest one
+pub fn init_backups(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    app.snapshot_focus_diff = false;
+    app.snapshot_list_state.select(Some(0));
+    load_snapshots(app)?;
+    app.active_menu_item = MenuItem::Backups;
+    Ok(())
+}
+
+fn load_snapshots(app: &mut AppState) -> Result<(), Error> {
+    app.snapshots = list_snapshots()?;
+    clamp_list_state(&mut app.snapshot_list_state, app.snapshots.len());
+    load_snapshot_diff(app)
+}
+
+//Compares the selected snapshot with the tickets as they are now
+pub fn load_snapshot_diff(app: &mut AppState) -> Result<(), Error> {
+    app.snapshot_diff = match selected_snapshot(app) {
+        Some(snapshot) => diff_tickets(&read_snapshot(&snapshot)?.tickets, &all_tickets(app)),
+        None => Vec::new(),
+    };
+    clamp_list_state(&mut app.snapshot_diff_state, app.snapshot_diff.len());
+    Ok(())
+}
+
+pub fn selected_snapshot(app: &AppState) -> Option<Snapshot> {
+    app.snapshot_list_state.selected().and_then(|selected| app.snapshots.get(selected)).cloned()
+}
+
+pub fn take_snapshot(app: &mut AppState) -> Result<(), Error> {
+    let snapshot = create_snapshot()?;
+    app.snapshot_list_state.select(Some(0));
+    load_snapshots(app)?;
+    app.notice = format!("Saved {}", snapshot.name);
+    Ok(())
+}
+
+//Puts the whole database back, the state before is kept as a new snapshot
+pub fn restore_selected_snapshot(app: &mut AppState) -> Result<(), Error> {
+    if let Some(snapshot) = selected_snapshot(app) {
+        let current = restore_snapshot(&snapshot)?;
+        update_ticket_count(app);
+        app.snapshot_list_state.select(Some(0));
+        load_snapshots(app)?;
+        app.notice = format!("Restored {}, the previous state is in {}", snapshot.name, current.name);
+    }
+    Ok(())
+}
+
+//Puts the ticket selected in the changes back the way it was in the snapshot
+pub fn restore_selected_ticket(app: &mut AppState) -> Result<(), Error> {
+    let diff = app.snapshot_diff_state.selected().and_then(|selected| app.snapshot_diff.get(selected)).cloned();
+    if let (Some(snapshot), Some(diff)) = (selected_snapshot(app), diff) {
+        app.notice = match restore_ticket(&snapshot, diff.id)? {
+            Some(_) => format!("Restored ticket {} from {}", app.settings.ticket_key(diff.id), snapshot.name),
+            None => format!("Removed ticket {}, it did not exist in {}", app.settings.ticket_key(diff.id), snapshot.name),
+        };
+        update_ticket_count(app);
+        load_snapshot_diff(app)?;
+    }
+    Ok(())
+}
+
+pub fn remove_selected_snapshot(app: &mut AppState) -> Result<(), Error> {
+    if let Some(snapshot) = selected_snapshot(app) {
+        std::fs::remove_file(backups_dir().join(&snapshot.name))?;
+        load_snapshots(app)?;
+        app.notice = format!("Removed {}", snapshot.name);
+    }
+    Ok(())
+}
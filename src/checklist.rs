@@ -0,0 +1,60 @@
+//For procedures: checklist items on tickets
+
+use chrono::Utc;
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+
+//Ticks off or unticks a checklist item of the selected ticket, items are numbered from 1
+pub fn toggle_checklist_item(app: &mut AppState, number: usize) -> Result<(), Error> {
+    app.notice = String::new();
+    if let Some(mut ticket) = selected_ticket(app).filter(|ticket| !moved_notice(app, ticket)) {
+        match number.checked_sub(1).and_then(|index| ticket.checklist.get_mut(index)) {
+            Some(item) => item.done = !item.done,
+            None => {
+                app.notice = format!("Ticket {} has no checklist item {}", app.settings.ticket_key(ticket.id), number);
+                return Ok(());
+            }
+        }
+        ticket.updated_at = Utc::now();
+        replace_ticket(app, ticket);
+        update_db(app);
+        update_ticket_count(app);
+    }
+    Ok(())
+}
+
+//Adds the typed item to the checklist of the ticket being edited
+pub fn add_checklist_input(app: &mut AppState) {
+    let text = app.checklist_input.trim().to_string();
+    if !text.is_empty() {
+        app.edit_ticket.checklist.push(ChecklistItem::new(text));
+        app.checklist_state.select(Some(app.edit_ticket.checklist.len() - 1));
+    }
+    app.checklist_input = String::new();
+}
+
+//Ticks off or unticks the selected item in the edit form
+pub fn toggle_edit_checklist(app: &mut AppState) {
+    if let Some(item) = app.checklist_state.selected().and_then(|index| app.edit_ticket.checklist.get_mut(index)) {
+        item.done = !item.done;
+    }
+}
+
+//Removes the selected item in the edit form
+pub fn remove_edit_checklist(app: &mut AppState) {
+    if let Some(index) = app.checklist_state.selected() {
+        if index < app.edit_ticket.checklist.len() {
+            app.edit_ticket.checklist.remove(index);
+        }
+        let len = app.edit_ticket.checklist.len();
+        app.checklist_state.select(if len == 0 {None} else {Some(index.min(len - 1))});
+    }
+}
+
+//Moves the selection in the edit form's checklist
+pub fn step_edit_checklist(app: &mut AppState, forward: bool) {
+    let len = app.edit_ticket.checklist.len();
+    step_list_state(&mut app.checklist_state, len, forward);
+}
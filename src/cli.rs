@@ -0,0 +1,939 @@
+//Subcommands for scripting, the TUI starts when none is given
+
+use chrono::{Local, Utc};
+use std::{io::{self, Write}, path::{Path, PathBuf}};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::{Map, Value};
+use scrum_lib::*;
+use thiserror::Error;
+use crate::db::*;
+use crate::client::*;
+use crate::storage::*;
+use crate::csvio::*;
+use crate::document::*;
+use crate::importer::*;
+
+#[derive(Parser)]
+#[command(
+    name = "scrum",
+    about = "Scrum ticket tracker, the TUI starts when no command is given",
+    after_help = "Exit codes: 0 success, 2 invalid input, 3 ticket not found, 4 database, file or server error",
+)]
+pub struct Cli {
+    /// Use scrum-server instead of the local database
+    #[arg(long, global = true)]
+    pub remote: bool,
+    /// Address of scrum-server in remote mode
+    #[arg(long, global = true, value_name = "ADDRESS", default_value = SERVER_ADDRESS)]
+    pub server: String,
+    /// Use a registered project instead of the database in the working directory, in remote mode the server's project of that name
+    #[arg(long, global = true, value_name = "NAME")]
+    pub project: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Add a ticket and print its key
+    Add {
+        #[arg(long)]
+        title: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// One of the priorities of the project (high, medium or low unless configured), the lowest by default
+        #[arg(long)]
+        priority: Option<String>,
+        #[arg(long)]
+        assignee: Option<String>,
+        /// Can be given more than once
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Same formats as the TUI, such as 2022-05-20, tomorrow, fri or +3d
+        #[arg(long)]
+        due: Option<String>,
+    },
+    /// List tickets
+    List {
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Show a ticket with its notes
+    Show {
+        /// Key such as OPS-42, or the number
+        id: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Close a ticket
+    Close {
+        id: String,
+    },
+    /// Add a note to a ticket
+    Note {
+        id: String,
+        text: String,
+    },
+    /// Delete a closed ticket
+    Delete {
+        id: String,
+    },
+    /// Attach a file to a ticket, in remote mode it is uploaded to the server
+    Attach {
+        id: String,
+        file: PathBuf,
+    },
+    /// Save an attachment of a ticket to a file, in remote mode it is downloaded from the server
+    Attachment {
+        id: String,
+        /// Name of the attachment as shown by `scrum show`
+        name: String,
+        /// The attachment's name in the working directory when not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Move a ticket with its notes, attachments and history to another project, where it gets a new id
+    ///
+    /// A closed tombstone with the old key stays behind and leads to the new ticket.
+    Move {
+        id: String,
+        /// Registered project, or "." for the database in the working directory
+        #[arg(long)]
+        to: String,
+    },
+    /// Copy a ticket with its notes, attachments and history to another project
+    Copy {
+        id: String,
+        /// Registered project, or "." for the database in the working directory
+        #[arg(long)]
+        to: String,
+    },
+    /// Import tickets from CSV or from a GitHub, GitLab or Jira export, entries that cannot be read are reported and skipped
+    ///
+    /// Issues imported before are updated instead of added again, so are rows whose id column names a ticket of the project.
+    Import {
+        file: PathBuf,
+        /// Guessed from the file when not given
+        #[arg(long, value_enum)]
+        from: Option<ImportSource>,
+        /// Column for a field when the header is not recognised, such as title=Summary (csv only)
+        #[arg(long = "map", value_name = "FIELD=HEADER")]
+        map: Vec<String>,
+        /// Only show what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Export tickets to CSV, which can be imported again, or to a Markdown or HTML document
+    Export {
+        /// Only these tickets, by key or number, the filter is ignored when they are given
+        ids: Vec<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Taken from the extension of the output file when not given, otherwise csv
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+        /// Written to standard output when not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Snapshots of the local ticket database
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    /// Registry of projects, each with its own database, settings and templates
+    Project {
+        #[command(subcommand)]
+        action: ProjectAction,
+    },
+    /// Weekly status report by status and assignee
+    Report {
+        /// 0 is this week, 1 last week
+        #[arg(long, default_value_t = 0)]
+        weeks_ago: i64,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+        /// Written to standard output when not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+//Snapshots are named by any unique part of the name, such as 20220511-0407, or "latest"
+#[derive(Subcommand)]
+pub enum BackupAction {
+    /// Take a snapshot, older ones are pruned by the retention in the settings
+    Create,
+    /// List the snapshots, newest first
+    List,
+    /// Show which tickets changed since a snapshot
+    Diff {
+        snapshot: String,
+    },
+    /// Restore the whole database, the current state is snapshotted first
+    Restore {
+        snapshot: String,
+        /// Only restore this ticket, by key or number
+        #[arg(long)]
+        ticket: Option<String>,
+    },
+    /// Remove the snapshots the retention does not keep
+    Prune {
+        /// Keep this many of the newest snapshots instead of the setting
+        #[arg(long)]
+        last: Option<usize>,
+        /// Keep the newest snapshot of this many days instead of the setting
+        #[arg(long)]
+        daily: Option<usize>,
+        /// Keep the newest snapshot of this many weeks instead of the setting
+        #[arg(long)]
+        weekly: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProjectAction {
+    /// List the registered projects
+    List,
+    /// Register a directory as a project, it is created if it does not exist
+    Add {
+        name: String,
+        path: PathBuf,
+    },
+    /// Remove a project from the registry, its files are kept
+    Remove {
+        name: String,
+    },
+    /// Set the key prefix of the project in use, such as OPS for OPS-42, keys with the old prefix keep working
+    Prefix {
+        /// Empty to show plain numbers again
+        prefix: String,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+    Html,
+}
+
+#[derive(Args)]
+pub struct FilterArgs {
+    #[arg(long, value_enum, default_value_t = StatusFilter::Open)]
+    status: StatusFilter,
+    #[arg(long)]
+    tag: Option<String>,
+    #[arg(long)]
+    assignee: Option<String>,
+    #[arg(long)]
+    priority: Option<String>,
+}
+
+impl FilterArgs {
+    fn apply(&self, tickets: Vec<Tickets>, settings: &DbSettings) -> Result<Vec<Tickets>, CliError> {
+        let priority = self.priority.as_deref().map(|priority| parse_priority(priority, settings)).transpose()?;
+        let tag = self.tag.as_deref().map(normalize_tag);
+        Ok(tickets
+            .into_iter()
+            .filter(|ticket| match self.status {
+                StatusFilter::Open => ticket.status == TicketStatus::Open,
+                StatusFilter::Closed => ticket.status == TicketStatus::Closed,
+                StatusFilter::All => true,
+            })
+            .filter(|ticket| tag.as_ref().is_none_or(|tag| ticket.tags.contains(tag)))
+            .filter(|ticket| self.assignee.is_none() || ticket.assignee == self.assignee)
+            .filter(|ticket| priority.as_ref().is_none_or(|priority| ticket.priority == *priority))
+            .collect())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum StatusFilter {
+    Open,
+    Closed,
+    All,
+}
+
+#[derive(Args)]
+pub struct OutputArgs {
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// Comma separated, such as id,title,due
+    #[arg(long, value_enum, value_delimiter = ',')]
+    columns: Vec<Column>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    Table,
+    //Same representation as the database file
+    Json,
+    Csv,
+    //One id per line, for piping into other commands
+    Ids,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Column {
+    Id,
+    //Id with the project's prefix, such as OPS-42
+    Key,
+    Title,
+    Status,
+    Priority,
+    Assignee,
+    Due,
+    Tags,
+    Estimate,
+    Sprint,
+    Created,
+    Updated,
+    //Issue in another tracker the ticket was imported from
+    External,
+}
+
+//Columns used when none are chosen
+const DEFAULT_COLUMNS: [Column; 4] = [Column::Key, Column::Status, Column::Priority, Column::Title];
+
+impl Column {
+    //Also the key of the field in the JSON of a ticket
+    fn name(&self) -> &str {
+        match self {
+            Column::Id => "id",
+            Column::Key => "key",
+            Column::Title => "title",
+            Column::Status => "status",
+            Column::Priority => "priority",
+            Column::Assignee => "assignee",
+            Column::Due => "due",
+            Column::Tags => "tags",
+            Column::Estimate => "estimate",
+            Column::Sprint => "sprint",
+            Column::Created => "created_at",
+            Column::Updated => "updated_at",
+            Column::External => "external_id",
+        }
+    }
+    fn value(&self, ticket: &Tickets, settings: &DbSettings) -> String {
+        match self {
+            Column::Id => ticket.id.to_string(),
+            Column::Key => settings.ticket_key(ticket.id),
+            Column::Title => ticket.title.clone(),
+            Column::Status => ticket.status.to_string().to_string(),
+            Column::Priority => ticket.priority.clone(),
+            Column::Assignee => ticket.assignee.clone().unwrap_or_default(),
+            Column::Due => ticket.due.map(|due| due.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            Column::Tags => ticket.tags.iter().cloned().collect::<Vec<String>>().join(" "),
+            Column::Estimate => ticket.estimate.map(|estimate| estimate.to_string()).unwrap_or_default(),
+            Column::Sprint => ticket.sprint.map(|sprint| sprint.to_string()).unwrap_or_default(),
+            Column::Created => ticket.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+            Column::Updated => ticket.updated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+            Column::External => ticket.external_id.clone().unwrap_or_default(),
+        }
+    }
+}
+
+//Exit codes, so scripts can tell what went wrong
+pub const EXIT_INVALID: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_STORAGE: i32 = 4;
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    //The input was understood but cannot be used, such as an unknown priority
+    #[error("{0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Db(#[from] Error),
+    #[error("cannot write the output: {0}")]
+    Output(#[from] io::Error),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Invalid(_) |
+            CliError::Db(Error::UnknownProject(_)) |
+            CliError::Db(Error::InvalidProject(_)) |
+            CliError::Db(Error::InvalidInput(_)) |
+            CliError::Db(Error::Moved(_, _)) => EXIT_INVALID,
+            CliError::Db(Error::NotFound(_)) => EXIT_NOT_FOUND,
+            CliError::Db(_) | CliError::Output(_) => EXIT_STORAGE,
+        }
+    }
+}
+
+impl From<csv::Error> for CliError {
+    fn from(error: csv::Error) -> Self {
+        CliError::from(io::Error::from(error))
+    }
+}
+
+//Runs the command and returns the exit code
+pub fn run_command(command: Command, remote: Option<String>, project: Option<String>) -> i32 {
+    //The server takes its own snapshots
+    if remote.is_some() && matches!(command, Command::Backup { .. }) {
+        eprintln!("scrum: backup commands work on the local database only");
+        return EXIT_INVALID;
+    }
+    if remote.is_some() && matches!(command, Command::Project { .. } | Command::Move { .. } | Command::Copy { .. }) {
+        eprintln!("scrum: project commands work on the local registry only");
+        return EXIT_INVALID;
+    }
+    let remote_keys = remote.is_some();
+    let storage = open_storage(remote, project);
+    match execute(command, storage.as_ref(), remote_keys) {
+        Ok(()) => 0,
+        //The reader stopped early, such as `scrum list | head`
+        Err(CliError::Output(error)) if error.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(error) => {
+            eprintln!("scrum: {}", error);
+            error.exit_code()
+        }
+    }
+}
+
+//Tickets are given by key. Keys, priorities and status names come from the settings of the storage,
+//in remote mode those of the server's project
+fn execute(command: Command, storage: &dyn Storage, remote: bool) -> Result<(), CliError> {
+    let settings = match command {
+        Command::Backup { .. } | Command::Project { .. } => read_settings()?,
+        _ => storage.settings()?,
+    };
+    let settings = &settings;
+    let id = |input: &str| ticket_id(input, settings);
+    match command {
+        Command::Add { title, description, priority, assignee, tags, due } => add(storage, settings, title, description, priority, assignee, tags, due),
+        Command::List { filter, output } => list(storage, settings, &filter, &output),
+        Command::Show { id: key, output } => show(storage, settings, id(&key)?, &output, remote),
+        Command::Close { id: key } => close(storage, settings, id(&key)?),
+        Command::Note { id: key, text } => note(storage, settings, id(&key)?, text),
+        Command::Delete { id: key } => delete(storage, settings, id(&key)?),
+        Command::Attach { id: key, file } => attach(storage, settings, id(&key)?, &file),
+        Command::Attachment { id: key, name, output } => save_attachment(storage, settings, id(&key)?, &name, output),
+        Command::Move { id: key, to } => transfer(settings, id(&key)?, &to, false),
+        Command::Copy { id: key, to } => transfer(settings, id(&key)?, &to, true),
+        Command::Import { file, from, map, dry_run } => import(storage, settings, &file, from, &map, dry_run),
+        Command::Export { ids, filter, format, output } => {
+            let ids = ids.iter().map(|key| id(key)).collect::<Result<Vec<i32>, CliError>>()?;
+            export(storage, settings, &ids, &filter, format, output)
+        }
+        Command::Report { weeks_ago, format, output } => report(storage, settings, weeks_ago, format, output),
+        Command::Backup { action } => backup(action),
+        Command::Project { action } => project(action),
+    }
+}
+
+fn ticket_id(input: &str, settings: &DbSettings) -> Result<i32, CliError> {
+    settings.find_ticket_id(input).ok_or_else(|| CliError::Invalid(format!("\"{}\" is not a ticket key such as OPS-42 or 42", input)))
+}
+
+//"high" becomes "High", the way the project's settings spell the priorities
+pub fn parse_priority(input: &str, settings: &DbSettings) -> Result<String, CliError> {
+    settings.find_priority(input).ok_or_else(|| CliError::Invalid(format!("\"{}\" is not a priority, use one of {}", input, settings.priorities.join(", "))))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn add(storage: &dyn Storage, settings: &DbSettings, title: String, description: String, priority: Option<String>, assignee: Option<String>, tags: Vec<String>, due: Option<String>) -> Result<(), CliError> {
+    if title.trim().is_empty() {
+        return Err(CliError::Invalid("the title cannot be empty".to_string()));
+    }
+    let now = Utc::now();
+    let mut ticket = Tickets::new(0, title.trim().to_string(), description, TicketStatus::Open, String::new(), now, now);
+    ticket.priority = match priority {
+        Some(priority) => parse_priority(&priority, settings)?,
+        None => settings.lowest_priority(),
+    };
+    ticket.assignee = assignee.filter(|assignee| !assignee.trim().is_empty());
+    ticket.tags = tags.iter().map(|tag| normalize_tag(tag)).filter(|tag| !tag.is_empty()).collect();
+    if let Some(due) = due {
+        let today = Local::now().naive_local().date();
+        ticket.due = Some(parse_due(&due, today).ok_or_else(|| CliError::Invalid(format!("\"{}\" is not a due date", due)))?);
+    }
+    let ticket = storage.create(ticket)?;
+    println!("{}", settings.ticket_key(ticket.id));
+    Ok(())
+}
+
+fn list(storage: &dyn Storage, settings: &DbSettings, filter: &FilterArgs, output: &OutputArgs) -> Result<(), CliError> {
+    let tickets = filter.apply(storage.tickets()?, settings)?;
+    match output.format {
+        Format::Json => print_json(serde_json::to_value(&tickets).map_err(Error::from)?, &output.columns, settings),
+        _ => print_tickets(&tickets, output, settings),
+    }
+}
+
+//Tickets as rows of the chosen columns
+fn print_tickets(tickets: &[Tickets], output: &OutputArgs, settings: &DbSettings) -> Result<(), CliError> {
+    let columns: &[Column] = if output.columns.is_empty() {&DEFAULT_COLUMNS} else {&output.columns};
+    let header: Vec<String> = columns.iter().map(|column| column.name().to_string()).collect();
+    let rows: Vec<Vec<String>> = tickets
+        .iter()
+        .map(|ticket| columns.iter().map(|column| column.value(ticket, settings)).collect())
+        .collect();
+    let mut stdout = io::stdout().lock();
+    match output.format {
+        Format::Ids => {
+            for ticket in tickets {
+                writeln!(stdout, "{}", ticket.id)?;
+            }
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(stdout);
+            writer.write_record(&header)?;
+            for row in rows {
+                writer.write_record(&row)?;
+            }
+            writer.flush()?;
+        }
+        _ => {
+            //Every column is as wide as its longest value, the last one is not padded
+            let widths: Vec<usize> = (0..columns.len())
+                .map(|i| rows.iter().map(|row| row[i].chars().count()).chain([header[i].len()]).max().unwrap_or(0))
+                .collect();
+            for row in [header].iter().chain(rows.iter()) {
+                let cells: Vec<String> = row
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| if i + 1 == row.len() {cell.clone()} else {format!("{:<width$}", cell, width = widths[i])})
+                    .collect();
+                writeln!(stdout, "{}", cells.join("  "))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+//The serde representation of the tickets with their key, only the chosen fields when columns are given
+fn print_json(value: Value, columns: &[Column], settings: &DbSettings) -> Result<(), CliError> {
+    let pick = |ticket: &Value| -> Value {
+        let mut ticket = ticket.clone();
+        let key = ticket.get("id").and_then(|id| id.as_i64()).map(|id| settings.ticket_key(id as i32));
+        if let (Value::Object(fields), Some(key)) = (&mut ticket, key) {
+            fields.insert("key".to_string(), Value::String(key));
+        }
+        if columns.is_empty() {
+            return ticket;
+        }
+        let fields: Map<String, Value> = columns
+            .iter()
+            .map(|column| (column.name().to_string(), ticket.get(column.name()).cloned().unwrap_or(Value::Null)))
+            .collect();
+        Value::Object(fields)
+    };
+    let value = match &value {
+        Value::Array(tickets) => Value::Array(tickets.iter().map(pick).collect()),
+        ticket => pick(ticket),
+    };
+    writeln!(io::stdout().lock(), "{}", serde_json::to_string_pretty(&value).map_err(Error::from)?)?;
+    Ok(())
+}
+
+fn show(storage: &dyn Storage, settings: &DbSettings, id: i32, output: &OutputArgs, remote: bool) -> Result<(), CliError> {
+    let mut ticket = storage.find(id)?;
+    let mut settings = settings.clone();
+    //The old key of a moved ticket leads to where it is now, the server does not know the other projects
+    while let (Some(moved), false) = (ticket.moved_to.clone(), remote) {
+        eprintln!("Ticket {} was moved to {}", settings.ticket_key(ticket.id), moved.describe());
+        set_project(transfer_project(&moved.project)?)?;
+        settings = read_settings()?;
+        ticket = LocalStorage.find(moved.id)?;
+    }
+    match output.format {
+        Format::Json => return print_json(serde_json::to_value(&ticket).map_err(Error::from)?, &output.columns, &settings),
+        Format::Csv | Format::Ids => return print_tickets(&[ticket], output, &settings),
+        Format::Table if !output.columns.is_empty() => return print_tickets(&[ticket], output, &settings),
+        Format::Table => {}
+    }
+    println!("ID: {}", settings.ticket_key(ticket.id));
+    println!("Title: {}", ticket.title);
+    println!("Status: {}", ticket.status.to_string());
+    println!("Priority: {}", ticket.priority);
+    println!("Assignee: {}", ticket.assignee.clone().unwrap_or_else(|| "Unassigned".to_string()));
+    if let Some(due) = ticket.due {
+        println!("Due: {}", due.format("%Y-%m-%d"));
+    }
+    if !ticket.tags.is_empty() {
+        let tags: Vec<String> = ticket.tags.iter().cloned().collect();
+        println!("Tags: {}", tags.join(", "));
+    }
+    println!("Created: {}", ticket.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"));
+    println!("Updated: {}", ticket.updated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"));
+    if let Some(from) = &ticket.copied_from {
+        println!("From: {}", from.describe());
+    }
+    if let Some(moved) = &ticket.moved_to {
+        println!("Moved to: {}", moved.describe());
+    }
+    if !ticket.description.is_empty() {
+        println!();
+        println!("{}", ticket.description);
+    }
+    if let Some((done, total)) = ticket.checklist_progress() {
+        println!();
+        println!("Checklist {}/{}", done, total);
+        for item in ticket.checklist.iter() {
+            println!("[{}] {}", if item.done {"x"} else {" "}, item.text);
+        }
+    }
+    if !ticket.attachments.is_empty() {
+        println!();
+        for attachment in ticket.attachments.iter() {
+            println!("Attachment: {} ({})", attachment.name, format_size(attachment.size));
+        }
+    }
+    if let Some(notes) = &ticket.notes {
+        println!();
+        for note in notes {
+            println!("{} {}", note.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"), note.text);
+        }
+    }
+    Ok(())
+}
+
+fn close(storage: &dyn Storage, settings: &DbSettings, id: i32) -> Result<(), CliError> {
+    let tickets = storage.tickets()?;
+    let mut ticket = tickets.iter().find(|ticket| ticket.id == id).cloned().ok_or(Error::NotFound(id))?;
+    if let Some(moved) = &ticket.moved_to {
+        return Err(CliError::Invalid(format!("ticket {} was moved to {}", settings.ticket_key(id), moved.describe())));
+    }
+    //Same rule as the TUI, parents are closed after their children
+    let open: Vec<String> = children(id, &tickets)
+        .iter()
+        .filter(|child| child.status == TicketStatus::Open)
+        .map(|child| settings.ticket_ref(child.id))
+        .collect();
+    if !open.is_empty() {
+        return Err(CliError::Invalid(format!("ticket {} has open children: {}", settings.ticket_key(id), open.join(", "))));
+    }
+    ticket.set_status(TicketStatus::Closed);
+    storage.update(ticket)?;
+    println!("Closed ticket {}", settings.ticket_key(id));
+    Ok(())
+}
+
+fn note(storage: &dyn Storage, settings: &DbSettings, id: i32, text: String) -> Result<(), CliError> {
+    if text.trim().is_empty() {
+        return Err(CliError::Invalid("the note cannot be empty".to_string()));
+    }
+    let mut ticket = storage.find(id)?;
+    if let Some(moved) = &ticket.moved_to {
+        return Err(CliError::Invalid(format!("ticket {} was moved to {}", settings.ticket_key(id), moved.describe())));
+    }
+    let now = Utc::now();
+    ticket.notes.get_or_insert_with(Vec::new).push(Note::new(text.trim().to_string(), now, now));
+    storage.update(ticket)?;
+    println!("Added note to ticket {}", settings.ticket_key(id));
+    Ok(())
+}
+
+fn delete(storage: &dyn Storage, settings: &DbSettings, id: i32) -> Result<(), CliError> {
+    let ticket = storage.find(id)?;
+    if let Some(moved) = &ticket.moved_to {
+        return Err(CliError::Invalid(format!("ticket {} was moved to {}, its tombstone is kept", settings.ticket_key(id), moved.describe())));
+    }
+    //Same rule as the TUI, only closed tickets can be deleted
+    if ticket.status != TicketStatus::Closed {
+        return Err(CliError::Invalid(format!("ticket {} must be closed before it is deleted", settings.ticket_key(id))));
+    }
+    storage.delete(id)?;
+    println!("Deleted ticket {}", settings.ticket_key(id));
+    Ok(())
+}
+
+fn attach(storage: &dyn Storage, settings: &DbSettings, id: i32, file: &Path) -> Result<(), CliError> {
+    let ticket = storage.find(id)?;
+    if let Some(moved) = &ticket.moved_to {
+        return Err(CliError::Invalid(format!("ticket {} was moved to {}", settings.ticket_key(id), moved.describe())));
+    }
+    if !file.is_file() {
+        return Err(CliError::Invalid(format!("{} is not a file", file.display())));
+    }
+    let attachment = storage.attach(id, file)?;
+    println!("Attached {} to {}", attachment.name, settings.ticket_key(id));
+    Ok(())
+}
+
+//The newest attachment of that name when a file was attached more than once
+fn save_attachment(storage: &dyn Storage, settings: &DbSettings, id: i32, name: &str, output: Option<PathBuf>) -> Result<(), CliError> {
+    let ticket = storage.find(id)?;
+    let attachment = ticket.attachments
+        .iter()
+        .rev()
+        .find(|attachment| attachment.name == name)
+        .ok_or_else(|| CliError::Invalid(format!("ticket {} has no attachment named {}", settings.ticket_key(id), name)))?;
+    let data = storage.attachment(&attachment.hash)?;
+    let path = output.unwrap_or_else(|| PathBuf::from(&attachment.name));
+    std::fs::write(&path, data)?;
+    println!("Saved {} to {}", attachment.name, path.display());
+    Ok(())
+}
+
+//Moves or copies the ticket to the project, the id it gets there is printed as a key
+fn transfer(settings: &DbSettings, id: i32, to: &str, keep: bool) -> Result<(), CliError> {
+    let target = if to == "." {None} else {Some(find_project(to)?)};
+    let key = settings.ticket_key(id);
+    let moved = transfer_ticket(id, target, keep)?;
+    println!("{} {} to {}", if keep {"Copied"} else {"Moved"}, key, moved.describe());
+    Ok(())
+}
+
+fn import(storage: &dyn Storage, settings: &DbSettings, file: &Path, from: Option<ImportSource>, map: &[String], dry_run: bool) -> Result<(), CliError> {
+    let source = from.unwrap_or_else(|| detect_source(file));
+    if source != ImportSource::Csv && !map.is_empty() {
+        return Err(CliError::Invalid("--map only applies to csv files".to_string()));
+    }
+    let mapping = parse_mapping(map).map_err(CliError::Invalid)?;
+    let report = read_import(file, source, &mapping, settings)?;
+    for (place, error) in report.errors.iter() {
+        eprintln!("{}: {}", place, error);
+    }
+
+    if dry_run {
+        let mut columns = vec![Column::Status, Column::Priority, Column::Due, Column::Created, Column::Title];
+        if source != ImportSource::Csv {
+            columns.insert(0, Column::External);
+        }
+        print_tickets(&report.tickets, &OutputArgs{ format: Format::Table, columns }, settings)?;
+        let targets = import_targets(&storage.tickets()?, &report.tickets);
+        for (ticket, target) in report.tickets.iter().zip(targets.iter()) {
+            match (target, &ticket.external_id) {
+                (ImportTarget::Update(id), Some(external_id)) => println!("{} updates ticket {}", external_id, settings.ticket_key(*id)),
+                (ImportTarget::Skip(id), Some(external_id)) => println!("{} skips ticket {}, it was moved to another project", external_id, settings.ticket_key(*id)),
+                _ => {}
+            }
+        }
+        println!("Would {}, {} entries have errors", describe_targets(&targets), report.errors.len());
+    } else {
+        let result = store_imported(storage, report.tickets)?;
+        let list = |ids: &[i32]| ids.iter().map(|id| settings.ticket_key(*id)).collect::<Vec<String>>().join(", ");
+        if !result.created.is_empty() {
+            println!("Added {} tickets: {}", result.created.len(), list(&result.created));
+        }
+        if !result.updated.is_empty() {
+            println!("Updated {} tickets: {}", result.updated.len(), list(&result.updated));
+        }
+        if !result.skipped.is_empty() {
+            println!("Skipped {} tickets that were moved to other projects: {}", result.skipped.len(), list(&result.skipped));
+        }
+        if result.created.is_empty() && result.updated.is_empty() && result.skipped.is_empty() {
+            println!("No tickets imported");
+        }
+    }
+    if !report.errors.is_empty() {
+        return Err(CliError::Invalid(format!("entries not imported: {}", report.errors.len())));
+    }
+    Ok(())
+}
+
+fn export(storage: &dyn Storage, settings: &DbSettings, ids: &[i32], filter: &FilterArgs, format: Option<ExportFormat>, output: Option<PathBuf>) -> Result<(), CliError> {
+    let tickets = if ids.is_empty() {
+        filter.apply(storage.tickets()?, settings)?
+    } else {
+        let all = storage.tickets()?;
+        let mut tickets = Vec::new();
+        for id in ids {
+            tickets.push(all.iter().find(|ticket| ticket.id == *id).cloned().ok_or(Error::NotFound(*id))?);
+        }
+        tickets
+    };
+    let format = format
+        .or_else(|| match output.as_deref().and_then(DocumentFormat::from_path) {
+            Some(DocumentFormat::Markdown) => Some(ExportFormat::Markdown),
+            Some(DocumentFormat::Html) => Some(ExportFormat::Html),
+            None => None,
+        })
+        .unwrap_or(ExportFormat::Csv);
+    let title = match tickets.as_slice() {
+        [ticket] => format!("Ticket {}", settings.ticket_ref(ticket.id)),
+        _ => format!("Tickets ({})", tickets.len()),
+    };
+    match (format, output.as_deref()) {
+        (ExportFormat::Csv, Some(path)) => export_csv(path, &tickets)?,
+        (ExportFormat::Csv, None) => write_csv(io::stdout().lock(), &tickets)?,
+        (ExportFormat::Markdown, output) => write_document(output, &tickets_document(&title, &tickets, DocumentFormat::Markdown, settings))?,
+        (ExportFormat::Html, output) => write_document(output, &tickets_document(&title, &tickets, DocumentFormat::Html, settings))?,
+    }
+    if let Some(path) = output {
+        println!("Exported {} tickets to {}", tickets.len(), path.display());
+    }
+    Ok(())
+}
+
+fn backup(action: BackupAction) -> Result<(), CliError> {
+    match action {
+        BackupAction::Create => {
+            let snapshot = create_snapshot()?;
+            println!("{}", backups_dir().join(&snapshot.name).display());
+        }
+        BackupAction::List => {
+            let snapshots = list_snapshots()?;
+            let mut out = io::stdout().lock();
+            for snapshot in snapshots.iter() {
+                let tickets = read_snapshot(snapshot).map(|db| db.tickets.len().to_string()).unwrap_or("?".to_string());
+                writeln!(out, "{}  {}  {} tickets", snapshot.name, snapshot.taken_at.format("%Y-%m-%d %H:%M"), tickets)?;
+            }
+        }
+        BackupAction::Diff { snapshot } => {
+            let snapshot = find_snapshot(&snapshot)?;
+            let settings = read_settings()?;
+            let diffs = diff_tickets(&read_snapshot(&snapshot)?.tickets, &read_db()?);
+            let mut out = io::stdout().lock();
+            for diff in diffs.iter() {
+                writeln!(out, "{}", diff.describe(&settings))?;
+            }
+            if diffs.is_empty() {
+                writeln!(out, "No changes since {}", snapshot.name)?;
+            }
+        }
+        BackupAction::Restore { snapshot, ticket } => {
+            let ticket = ticket.map(|key| ticket_id(&key, &read_settings()?)).transpose()?;
+            restore(&snapshot, ticket)?;
+        }
+        BackupAction::Prune { last, daily, weekly } => {
+            let mut retention = read_settings()?.backup_retention;
+            retention.last = last.unwrap_or(retention.last);
+            retention.daily = daily.unwrap_or(retention.daily);
+            retention.weekly = weekly.unwrap_or(retention.weekly);
+            for snapshot in prune_snapshots(&retention)? {
+                println!("Removed {}", snapshot.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn restore(snapshot: &str, ticket: Option<i32>) -> Result<(), CliError> {
+    let snapshot = find_snapshot(snapshot)?;
+    match ticket {
+        None => {
+            let current = restore_snapshot(&snapshot)?;
+            println!("Restored {}, the previous state is in {}", snapshot.name, current.name);
+        }
+        Some(id) => {
+            let key = read_settings()?.ticket_key(id);
+            match restore_ticket(&snapshot, id)? {
+                Some(_) => println!("Restored ticket {} from {}", key, snapshot.name),
+                None => println!("Removed ticket {}, it did not exist in {}", key, snapshot.name),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn project(action: ProjectAction) -> Result<(), CliError> {
+    match action {
+        ProjectAction::List => {
+            let current = current_project().map(|project| project.name);
+            let mut out = io::stdout().lock();
+            for project in read_projects()? {
+                let marker = if current.as_ref() == Some(&project.name) {"*"} else {" "};
+                writeln!(out, "{} {}  {}", marker, project.name, project.path.display())?;
+            }
+        }
+        ProjectAction::Add { name, path } => {
+            let project = add_project(&name, &path)?;
+            println!("Added {} at {}", project.name, project.path.display());
+        }
+        ProjectAction::Remove { name } => {
+            let project = remove_project(&name)?;
+            println!("Removed {}, the files in {} were kept", project.name, project.path.display());
+        }
+        ProjectAction::Prefix { prefix } => {
+            let mut settings = read_settings()?;
+            settings.set_key_prefix(&prefix).map_err(CliError::Invalid)?;
+            write_settings(&settings)?;
+            let shown = if settings.key_prefix.is_empty() {"by number".to_string()} else {format!("as {}", settings.ticket_key(1))};
+            match settings.key_aliases.is_empty() {
+                true => println!("Tickets are shown {}", shown),
+                false => println!("Tickets are shown {}, keys starting with {} still work", shown, settings.key_aliases.join(", ")),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_snapshot(query: &str) -> Result<Snapshot, CliError> {
+    let snapshots = list_snapshots()?;
+    if query == "latest" {
+        return snapshots.into_iter().next().ok_or(CliError::Invalid("there are no snapshots yet".to_string()));
+    }
+    let matches: Vec<Snapshot> = snapshots.into_iter().filter(|snapshot| snapshot.name.contains(query)).collect();
+    match matches.as_slice() {
+        [snapshot] => Ok(snapshot.clone()),
+        [] => Err(CliError::Invalid(format!("no snapshot matches \"{}\"", query))),
+        _ => Err(CliError::Invalid(format!("\"{}\" matches {} snapshots", query, matches.len()))),
+    }
+}
+
+fn report(storage: &dyn Storage, settings: &DbSettings, weeks_ago: i64, format: ExportFormat, output: Option<PathBuf>) -> Result<(), CliError> {
+    let format = match format {
+        ExportFormat::Markdown => DocumentFormat::Markdown,
+        ExportFormat::Html => DocumentFormat::Html,
+        ExportFormat::Csv => return Err(CliError::Invalid("the report can be markdown or html".to_string())),
+    };
+    let report = weekly_report(&storage.tickets()?, week_start(weeks_ago), settings);
+    write_document(output.as_deref(), &report_document(&report, format, settings))
+}
+
+//To the file when one is given, otherwise to standard output
+fn write_document(output: Option<&Path>, document: &str) -> Result<(), CliError> {
+    match output {
+        Some(path) => std::fs::write(path, document)?,
+        None => io::stdout().lock().write_all(document.as_bytes())?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Runs the command line as the shell would give it, without a server
+    fn scrum(args: &[&str]) -> i32 {
+        let cli = Cli::try_parse_from(std::iter::once("scrum").chain(args.iter().copied())).unwrap();
+        let remote = if cli.remote {Some(cli.server)} else {None};
+        run_command(cli.command.unwrap(), remote, cli.project)
+    }
+
+    #[test]
+    fn tickets_are_added_closed_and_deleted_by_key() {
+        let _guard = scratch_project("cli-keys");
+        assert_eq!(scrum(&["project", "prefix", "ops"]), 0);
+        assert_eq!(scrum(&["add", "--title", "Backup", "--priority", "high", "--tag", "Ops"]), 0);
+        let ticket = LocalStorage.tickets().unwrap().into_iter().find(|ticket| ticket.title == "Backup").unwrap();
+        assert_eq!(ticket.priority, "High");
+        assert!(ticket.tags.contains("ops"));
+        let key = format!("ops-{}", ticket.id);
+
+        assert_eq!(scrum(&["delete", &key]), EXIT_INVALID);
+        assert_eq!(scrum(&["close", &key]), 0);
+        assert_eq!(scrum(&["delete", &format!("DEV-{}", ticket.id)]), EXIT_INVALID);
+        assert_eq!(scrum(&["delete", &key]), 0);
+        assert!(LocalStorage.find(ticket.id).is_err());
+    }
+
+    #[test]
+    fn invalid_input_and_unknown_tickets_have_their_own_exit_codes() {
+        let _guard = scratch_project("cli-exit-codes");
+        assert_eq!(scrum(&["add", "--title", " "]), EXIT_INVALID);
+        assert_eq!(scrum(&["add", "--title", "Backup", "--priority", "someday"]), EXIT_INVALID);
+        assert_eq!(scrum(&["add", "--title", "Backup", "--due", "never"]), EXIT_INVALID);
+        assert_eq!(scrum(&["show", "99"]), EXIT_NOT_FOUND);
+        assert_eq!(scrum(&["note", "99", "Called support"]), EXIT_NOT_FOUND);
+        assert_eq!(scrum(&["list", "--priority", "someday"]), EXIT_INVALID);
+    }
+
+    #[test]
+    fn local_only_commands_are_refused_in_remote_mode() {
+        let _guard = scratch_project("cli-remote");
+        assert_eq!(scrum(&["--remote", "backup", "create"]), EXIT_INVALID);
+        assert_eq!(scrum(&["--remote", "project", "list"]), EXIT_INVALID);
+        assert_eq!(scrum(&["--remote", "move", "1", "--to", "."]), EXIT_INVALID);
+        //Nothing listens there
+        assert_eq!(scrum(&["--remote", "--server", "127.0.0.1:1", "list"]), EXIT_STORAGE);
+    }
+}
@@ -3,23 +3,96 @@ use scrum_lib::*;
 use futures::prelude::*;
 use tokio::net::TcpStream;
 use tokio_serde::formats::*;
-use tokio_util::codec::{FramedWrite, LengthDelimitedCodec};
+use serde_json::Value;
+use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
 
+//Where scrum-server listens by default
+pub const SERVER_ADDRESS: &str = "127.0.0.1:17653";
+
+//Sends a request and waits for the server's answer, used by the remote storage
 #[tokio::main]
-pub async fn send_request(request: Request) {
-    // Bind a server socket
-    let socket = TcpStream::connect("127.0.0.1:17653").await.unwrap();
-
-    // Delimit frames using a length header
-    let length_delimited = FramedWrite::new(socket, LengthDelimitedCodec::new());
-
-    // Serialize frames with JSON
-    let mut serialized =
-        tokio_serde::SymmetricallyFramed::new(length_delimited, SymmetricalJson::default());
-
-    // Send the value
-    serialized
-        .send(request)
-        .await
-        .unwrap()
-}
\ No newline at end of file
+pub async fn exchange(address: &str, request: Request) -> Result<Value, String> {
+    let socket = TcpStream::connect(address).await.map_err(|error| format!("cannot connect to {}: {}", address, error))?;
+    let (reader, writer) = socket.into_split();
+    let mut serialized = tokio_serde::SymmetricallyFramed::new(
+        FramedWrite::new(writer, LengthDelimitedCodec::new()),
+        SymmetricalJson::default(),
+    );
+    let mut deserialized = tokio_serde::SymmetricallyFramed::new(
+        FramedRead::new(reader, LengthDelimitedCodec::new()),
+        SymmetricalJson::<Value>::default(),
+    );
+
+    serialized.send(request).await.map_err(|error| error.to_string())?;
+    match deserialized.try_next().await.map_err(|error| error.to_string())? {
+        Some(answer) => match answer.get("error") {
+            Some(error) => Err(error.as_str().unwrap_or_default().to_string()),
+            None => Ok(answer),
+        },
+        None => Err("the server closed the connection without answering".to_string()),
+    }
+}
+
+//Sends the attachment to the server in chunks, the ticket must already hold the attachment's metadata.
+//Every chunk is answered, so a failed write or a hash mismatch stops the upload
+#[tokio::main]
+pub async fn upload_attachment(address: &str, project: Option<String>, ticket: Tickets, data: Vec<u8>) -> Result<(), String> {
+    let hash = attachment_hash(&data);
+    let socket = TcpStream::connect(address).await.map_err(|error| format!("cannot connect to {}: {}", address, error))?;
+    let (reader, writer) = socket.into_split();
+    let mut serialized = tokio_serde::SymmetricallyFramed::new(
+        FramedWrite::new(writer, LengthDelimitedCodec::new()),
+        SymmetricalJson::default(),
+    );
+    let mut deserialized = tokio_serde::SymmetricallyFramed::new(
+        FramedRead::new(reader, LengthDelimitedCodec::new()),
+        SymmetricalJson::<Value>::default(),
+    );
+
+    for chunk in AttachmentChunk::split(&hash, &data) {
+        let request = Request{ action: TicketAction::UploadChunk, ticket: ticket.clone(), chunk: Some(chunk), project: project.clone() };
+        serialized.send(request).await.map_err(|error| error.to_string())?;
+        match deserialized.try_next().await.map_err(|error| error.to_string())? {
+            Some(answer) => if let Some(error) = answer.get("error") {
+                return Err(error.as_str().unwrap_or_default().to_string());
+            },
+            None => return Err("the server closed the connection during the upload".to_string()),
+        }
+    }
+    Ok(())
+}
+
+//Asks the server for an attachment and puts the chunks back together
+#[tokio::main]
+pub async fn download_attachment(address: &str, project: Option<String>, hash: String) -> Result<Vec<u8>, String> {
+    let socket = TcpStream::connect(address).await.map_err(|error| format!("cannot connect to {}: {}", address, error))?;
+    let (reader, writer) = socket.into_split();
+    let mut serialized = tokio_serde::SymmetricallyFramed::new(
+        FramedWrite::new(writer, LengthDelimitedCodec::new()),
+        SymmetricalJson::default(),
+    );
+    let mut deserialized = tokio_serde::SymmetricallyFramed::new(
+        FramedRead::new(reader, LengthDelimitedCodec::new()),
+        SymmetricalJson::<Value>::default(),
+    );
+
+    let chunk = AttachmentChunk{ hash: hash.clone(), index: 0, total: 0, data: String::new() };
+    let request = Request{ action: TicketAction::Download, ticket: Tickets::default(), chunk: Some(chunk), project };
+    serialized.send(request).await.map_err(|error| error.to_string())?;
+
+    let mut data = Vec::new();
+    while let Some(message) = deserialized.try_next().await.map_err(|error| error.to_string())? {
+        if let Some(error) = message.get("error") {
+            return Err(error.as_str().unwrap_or_default().to_string());
+        }
+        let chunk: AttachmentChunk = serde_json::from_value(message).map_err(|error| error.to_string())?;
+        data.extend(chunk.bytes()?);
+        if chunk.is_last() {
+            break;
+        }
+    }
+    if attachment_hash(&data) != hash {
+        return Err("downloaded content does not match the hash".to_string());
+    }
+    Ok(data)
+}
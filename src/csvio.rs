@@ -0,0 +1,391 @@
+//For spreadsheets: importing tickets from CSV and exporting them to CSV or a document
+
+use std::{collections::BTreeMap, fs::{self, File}, io::Write, path::{Path, PathBuf}};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+use crate::storage::*;
+use crate::document::*;
+use crate::importer::*;
+use crate::attachment::expand_path;
+
+//Ticket fields a CSV column can be mapped to, with the headers that are recognised without a mapping
+pub const IMPORT_FIELDS: [(&str, &[&str]); 11] = [
+    ("id", &["id"]),
+    ("title", &["title", "summary", "subject", "name"]),
+    ("description", &["description", "details", "body"]),
+    ("priority", &["priority"]),
+    ("status", &["status", "state"]),
+    ("assignee", &["assignee", "owner"]),
+    ("tags", &["tags", "labels"]),
+    ("due", &["due", "due date", "deadline"]),
+    ("created", &["created", "created_at", "creation date"]),
+    ("updated", &["updated", "updated_at", "last updated"]),
+    ("notes", &["notes", "comments"]),
+];
+
+//Columns of an export, named so the file can be imported again
+pub const EXPORT_COLUMNS: [&str; 11] = ["id", "title", "description", "priority", "status", "assignee", "tags", "due", "created", "updated", "notes"];
+
+//Tickets read from a file, entries that could not be read are listed with where they are, such as "line 4"
+pub struct ImportReport {
+    pub tickets: Vec<Tickets>,
+    pub errors: Vec<(String, String)>,
+}
+
+//Parses "title=Summary" pairs, the header overrides the recognised names of the field
+pub fn parse_mapping(pairs: &[String]) -> Result<BTreeMap<String, String>, String> {
+    let mut mapping = BTreeMap::new();
+    for pair in pairs {
+        let (field, header) = pair.split_once('=').ok_or(format!("\"{}\" is not field=header", pair))?;
+        let field = field.trim().to_lowercase();
+        if !IMPORT_FIELDS.iter().any(|(name, _)| *name == field) {
+            let names: Vec<&str> = IMPORT_FIELDS.iter().map(|(name, _)| *name).collect();
+            return Err(format!("\"{}\" is not a field, use one of {}", field, names.join(", ")));
+        }
+        mapping.insert(field, header.trim().to_string());
+    }
+    Ok(mapping)
+}
+
+pub fn read_csv(path: &Path, mapping: &BTreeMap<String, String>, settings: &DbSettings) -> Result<ImportReport, Error> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path).map_err(csv_error)?;
+    let headers: Vec<String> = reader.headers().map_err(csv_error)?.iter().map(|header| header.trim().to_lowercase()).collect();
+
+    //Column index of every field found in the header
+    let mut columns = BTreeMap::new();
+    for (field, names) in IMPORT_FIELDS.iter() {
+        let found = match mapping.get(*field) {
+            Some(header) => headers.iter().position(|name| *name == header.to_lowercase()),
+            None => headers.iter().position(|name| names.contains(&name.as_str())),
+        };
+        if let Some(index) = found {
+            columns.insert(field.to_string(), index);
+        }
+    }
+    let mut report = ImportReport{ tickets: Vec::new(), errors: Vec::new() };
+    if let Some((field, header)) = mapping.iter().find(|(field, _)| !columns.contains_key(*field)) {
+        report.errors.push(("line 1".to_string(), format!("no column named \"{}\" for {}", header, field)));
+        return Ok(report);
+    }
+    if !columns.contains_key("title") {
+        report.errors.push(("line 1".to_string(), "no title column, map one with title=<header>".to_string()));
+        return Ok(report);
+    }
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                let line = error.position().map(|position| position.line()).unwrap_or(0);
+                report.errors.push((format!("line {}", line), error.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map(|position| position.line()).unwrap_or(0);
+        let cell = |field: &str| columns.get(field).and_then(|index| record.get(*index)).unwrap_or("").trim().to_string();
+        match row_to_ticket(&cell, settings) {
+            Ok(ticket) => report.tickets.push(ticket),
+            Err(error) => report.errors.push((format!("line {}", line), error)),
+        }
+    }
+    Ok(report)
+}
+
+//A file that cannot be read or written is a file error, a file that is not valid CSV is invalid input
+pub fn csv_error(error: csv::Error) -> Error {
+    if error.is_io_error() {Error::ReadDBError(error.into())} else {Error::InvalidInput(error.to_string())}
+}
+
+//Priorities and statuses are the project's, in any case. Empty cells are the lowest priority and the first state.
+//The id of an exported ticket is kept, so importing the file again updates that ticket
+fn row_to_ticket(cell: &dyn Fn(&str) -> String, settings: &DbSettings) -> Result<Tickets, String> {
+    let title = cell("title");
+    if title.is_empty() {
+        return Err("the title is empty".to_string());
+    }
+    let id = match cell("id").as_str() {
+        "" => 0,
+        other => settings.find_ticket_id(other).ok_or(format!("\"{}\" is not a ticket key such as OPS-42 or 42", other))?,
+    };
+    let priority = match cell("priority").as_str() {
+        "" => settings.lowest_priority(),
+        other => settings.find_priority(other).ok_or(format!("\"{}\" is not a priority, use one of {}", other, settings.priorities.join(", ")))?,
+    };
+    let status = match cell("status").as_str() {
+        "" => TicketStatus::Open,
+        other => settings.find_status(other).ok_or(format!("\"{}\" is not a status, use one of {}", other, settings.workflow.join(", ")))?,
+    };
+    let now = Utc::now();
+    let created_at = parse_time(&cell("created"))?.unwrap_or(now);
+    let updated_at = parse_time(&cell("updated"))?.unwrap_or(created_at);
+
+    let mut ticket = Tickets::new(id, title, cell("description"), status, priority, created_at, updated_at);
+    let assignee = cell("assignee");
+    ticket.assignee = if assignee.is_empty() {None} else {Some(assignee)};
+    ticket.tags = cell("tags")
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(normalize_tag)
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    let due = cell("due");
+    if !due.is_empty() {
+        let today = Local::now().naive_local().date();
+        ticket.due = Some(parse_due(&due, today).ok_or(format!("\"{}\" is not a due date", due))?);
+    }
+    //Every line of the notes cell is a note
+    let notes: Vec<Note> = cell("notes")
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| Note::new(unescape_note(line), updated_at, updated_at))
+        .collect();
+    if !notes.is_empty() {
+        ticket.notes = Some(notes);
+    }
+    Ok(ticket)
+}
+
+//RFC 3339, or a local "2022-05-11 04:07" or "2022-05-11"
+pub fn parse_time(input: &str) -> Result<Option<DateTime<Utc>>, String> {
+    if input.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(Some(time.with_timezone(&Utc)));
+    }
+    let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(input, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)));
+    match local.and_then(|time| Local.from_local_datetime(&time).earliest()) {
+        Some(time) => Ok(Some(time.with_timezone(&Utc))),
+        None => Err(format!("\"{}\" is not a date", input)),
+    }
+}
+
+//Notes are written one per line, so line breaks in a note are written as \n and backslashes as \\
+fn escape_note(text: &str) -> String {
+    text.replace('\\', "\\\\").replace("\r\n", "\n").replace('\n', "\\n")
+}
+
+fn unescape_note(line: &str) -> String {
+    let mut text = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                text.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                text.push('\\');
+                chars.next();
+            }
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+pub fn write_csv<W: Write>(writer: W, tickets: &[Tickets]) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(EXPORT_COLUMNS)?;
+    for ticket in tickets {
+        let notes: Vec<String> = ticket.notes.iter().flatten().map(|note| escape_note(note.text.trim())).collect();
+        writer.write_record([
+            ticket.id.to_string(),
+            ticket.title.clone(),
+            ticket.description.clone(),
+            ticket.priority.clone(),
+            ticket.status.to_string().to_string(),
+            ticket.assignee.clone().unwrap_or_default(),
+            ticket.tags.iter().cloned().collect::<Vec<String>>().join(" "),
+            ticket.due.map(|due| due.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            ticket.created_at.to_rfc3339(),
+            ticket.updated_at.to_rfc3339(),
+            notes.join("\n"),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn export_csv(path: &Path, tickets: &[Tickets]) -> Result<(), Error> {
+    let file = File::create(path)?;
+    write_csv(file, tickets).map_err(csv_error)
+}
+
+//Asks for the file to import, the first Enter shows a preview and the second one imports
+pub fn init_import(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    app.input = String::new();
+    app.messages = Vec::new();
+    app.import_mapping = String::new();
+    app.import_focus_mapping = false;
+    reset_import_preview(app);
+    app.active_menu_item = MenuItem::ImportForm;
+    Ok(())
+}
+
+//A changed path or mapping has to be previewed again before importing
+pub fn reset_import_preview(app: &mut AppState) {
+    app.import_preview = None;
+    app.prompt = "CSV, GitHub, GitLab or Jira file to import (Enter to preview, Tab to map CSV columns)".to_string();
+}
+
+//Reads the typed file and lists what would be imported, nothing is written yet
+pub fn preview_import(app: &mut AppState) -> Result<(), Error> {
+    app.messages = Vec::new();
+    //The whole input is the path, so it can have spaces
+    let input = app.input.trim().trim_matches(|c| c == '"' || c == '\'');
+    if input.is_empty() {
+        return Ok(());
+    }
+    let path = expand_path(input);
+    let source = detect_source(&path);
+    let pairs: Vec<String> = app.import_mapping.split(',').map(|pair| pair.trim().to_string()).filter(|pair| !pair.is_empty()).collect();
+    if source != ImportSource::Csv && !pairs.is_empty() {
+        app.messages.push("Columns can only be mapped for CSV files".to_string());
+        return Ok(());
+    }
+    let mapping = match parse_mapping(&pairs) {
+        Ok(mapping) => mapping,
+        Err(error) => {
+            app.messages.push(error);
+            return Ok(());
+        }
+    };
+    let report = match read_import(&path, source, &mapping, &app.settings) {
+        Ok(report) => report,
+        Err(error) => {
+            app.messages.push(format!("Cannot read {}: {}", path.display(), error));
+            return Ok(());
+        }
+    };
+    let targets = import_targets(&all_tickets(app), &report.tickets);
+    for (ticket, target) in report.tickets.iter().zip(targets.iter()) {
+        let action = match target {
+            ImportTarget::New => "new".to_string(),
+            ImportTarget::Update(id) => format!("update {}", app.settings.ticket_key(*id)),
+            ImportTarget::Skip(id) => format!("skip {} (moved)", app.settings.ticket_key(*id)),
+        };
+        app.messages.push(format!("{}: {} [{}] {}", action, ticket.status.to_string(), ticket.priority, ticket.title));
+    }
+    for (place, error) in report.errors.iter() {
+        app.messages.push(format!("{}: {}", place, error));
+    }
+    app.prompt = format!("Would {}, {} entries have errors (Enter to import)", describe_targets(&targets), report.errors.len());
+    app.import_preview = Some(report);
+    Ok(())
+}
+
+//Stores the previewed tickets, issues imported before are updated
+pub fn import_tickets(app: &mut AppState) -> Result<(), Error> {
+    if let Some(report) = app.import_preview.take() {
+        let result = store_imported(&LocalStorage, report.tickets)?;
+        update_ticket_count(app);
+        app.settings = read_settings()?;
+        app.notice = format!("Added {} and updated {} tickets", result.created.len(), result.updated.len());
+        if !result.skipped.is_empty() {
+            add_notice(app, format!("{} tickets were moved to other projects and not updated", result.skipped.len()));
+        }
+        app.input = String::new();
+        app.messages = Vec::new();
+        app.active_menu_item = MenuItem::Tickets;
+    }
+    Ok(())
+}
+
+//Asks where to write the tickets currently shown in the list
+pub fn init_export(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    app.input = "tickets.csv".to_string();
+    app.messages = Vec::new();
+    app.prompt = format!("Export the {} tickets shown to a .csv, .md or .html file (Enter to export)", visible_tickets(app).len());
+    app.active_menu_item = MenuItem::ExportForm;
+    Ok(())
+}
+
+pub fn export_tickets(app: &mut AppState) -> Result<(), Error> {
+    let path = PathBuf::from(app.input.trim());
+    let tickets = visible_tickets(app);
+    //Markdown and HTML are for reading, CSV for everything else
+    let result = match DocumentFormat::from_path(&path) {
+        Some(format) => fs::write(&path, tickets_document(&format!("Tickets ({})", tickets.len()), &tickets, format, &app.settings)).map_err(Error::from),
+        None => export_csv(&path, &tickets),
+    };
+    match result {
+        Ok(()) => {
+            app.notice = format!("Exported {} tickets to {}", tickets.len(), path.display());
+            app.input = String::new();
+            app.active_menu_item = MenuItem::Tickets;
+        }
+        Err(error) => app.messages.push(format!("Cannot write {}: {}", path.display(), error)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exported(name: &str, tickets: &[Tickets]) -> PathBuf {
+        let dir = scratch_dir(name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tickets.csv");
+        export_csv(&path, tickets).unwrap();
+        path
+    }
+
+    #[test]
+    fn export_reads_back_with_multi_line_notes() {
+        let now = Utc::now();
+        let mut ticket = Tickets{ id: 4, title: "Printer".to_string(), description: "Paper jam\non floor 2".to_string(), priority: "High".to_string(), ..Tickets::default() };
+        ticket.assignee = Some("ann".to_string());
+        ticket.tags = ["hardware".to_string(), "office".to_string()].into_iter().collect();
+        ticket.notes = Some(vec![Note::new("Called support\nthey call back".to_string(), now, now), Note::new("Path is C:\\new".to_string(), now, now)]);
+        let path = exported("csv-round-trip", std::slice::from_ref(&ticket));
+
+        let report = read_csv(&path, &BTreeMap::new(), &DbSettings::default()).unwrap();
+        assert!(report.errors.is_empty());
+        let read = &report.tickets[0];
+        assert_eq!((read.id, &read.title, &read.description, &read.priority), (4, &ticket.title, &ticket.description, &ticket.priority));
+        assert_eq!((&read.assignee, &read.tags), (&ticket.assignee, &ticket.tags));
+        let notes: Vec<&str> = read.notes.iter().flatten().map(|note| note.text.as_str()).collect();
+        assert_eq!(notes, vec!["Called support\nthey call back", "Path is C:\\new"]);
+    }
+
+    #[test]
+    fn importing_an_export_again_updates_the_tickets() {
+        let _guard = scratch_project("csv-reimport");
+        let now = Utc::now();
+        let mut ticket = Tickets{ id: 1, title: "Printer".to_string(), priority: "Low".to_string(), ..Tickets::default() };
+        ticket.notes = Some(vec![Note::new("Called support".to_string(), now, now)]);
+        write_changes(std::slice::from_ref(&ticket)).unwrap();
+        let path = exported("csv-reimport-file", &read_db().unwrap());
+
+        let report = read_csv(&path, &BTreeMap::new(), &DbSettings::default()).unwrap();
+        assert_eq!(import_targets(&read_db().unwrap(), &report.tickets), vec![ImportTarget::Update(1)]);
+        let result = store_imported(&LocalStorage, report.tickets).unwrap();
+        assert_eq!((result.created, result.updated), (vec![], vec![1]));
+        let tickets = read_db().unwrap();
+        assert_eq!(tickets.len(), 1);
+        assert_eq!(tickets[0].notes.as_ref().map(|notes| notes.len()), Some(1));
+    }
+
+    #[test]
+    fn unknown_ids_are_added_as_new_tickets() {
+        let settings = DbSettings{ key_prefix: "OPS".to_string(), ..DbSettings::default() };
+        let ids = |cell: &str| {
+            let row = |field: &str| match field {"id" => cell.to_string(), "title" => "Printer".to_string(), _ => String::new()};
+            row_to_ticket(&row, &settings).map(|ticket| ticket.id)
+        };
+        assert_eq!(ids("OPS-7"), Ok(7));
+        assert_eq!(ids(""), Ok(0));
+        assert!(ids("DEV-7").is_err());
+        assert_eq!(import_targets(&[], &[Tickets{ id: 7, ..Tickets::default() }]), vec![ImportTarget::New]);
+    }
+}
@@ -1,38 +1,439 @@
-use std::{fs::{self, File}, path::Path};
+use std::{fs, path::{Path, PathBuf}, sync::RwLock};
 use scrum_lib::*;
 use thiserror::Error;
-use std::io;
+use std::io::{self, Write};
+use chrono::{Local, NaiveDate, Utc};
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("error reading the DB file: {0}")]
     ReadDBError(#[from] io::Error),
     #[error("error parsing the DB file: {0}")]
     ParseDBError(#[from] serde_json::Error),
+    #[error("ticket {0} does not exist")]
+    NotFound(i32),
+    #[error("error talking to the server: {0}")]
+    RemoteError(String),
+    #[error("{DB_PATH} was written by a newer version of scrum (schema version {0}, this version reads up to {SCHEMA_VERSION}), please update scrum")]
+    NewerSchemaError(u32),
+    #[error("cannot upgrade {DB_PATH}: {0}")]
+    MigrationError(String),
+    #[error("there is no project named {0}, see `scrum project list`")]
+    UnknownProject(String),
+    #[error("{0}")]
+    InvalidProject(String),
+    //A file given by the user that cannot be understood, such as an import that is not valid CSV
+    #[error("{0}")]
+    InvalidInput(String),
+    //Tombstones of moved tickets are kept as they are
+    #[error("ticket {0} was moved to {}, its tombstone cannot be changed", .1.describe())]
+    Moved(i32, TicketTransfer),
+    //A file that exists but cannot be read is never written over, so the user can still fix it
+    #[error("{0} cannot be read and is not overwritten, fix or remove it first: {1}")]
+    UnreadableFile(String, String),
+}
+
+impl From<MigrationError> for Error {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::Newer(version) => Error::NewerSchemaError(version),
+            MigrationError::Invalid(error) => Error::MigrationError(error),
+        }
+    }
 }
 
 const DB_PATH: &str = "ticketdb.json";
+const SPRINT_DB_PATH: &str = "sprintdb.json";
+const SETTINGS_PATH: &str = "dbsettings.json";
+const TEMPLATES_PATH: &str = "templates.json";
+const PROJECTS_PATH: &str = "projects.json";
+
+//Project in use, None is the database in the working directory
+static PROJECT: RwLock<Option<Project>> = RwLock::new(None);
+
+//Makes every following read and write use the files of the project, the directory is created if needed
+pub fn set_project(project: Option<Project>) -> Result<(), Error> {
+    if let Some(project) = &project {
+        fs::create_dir_all(&project.path)?;
+    }
+    *PROJECT.write().unwrap() = project;
+    Ok(())
+}
 
-pub fn write_changes(tickets: &Vec<Tickets>) -> Result<(), Error> {
-    fs::write(DB_PATH, &serde_json::to_vec(&tickets)?)?;
+pub fn current_project() -> Option<Project> {
+    PROJECT.read().unwrap().clone()
+}
+
+//The file with this name in the directory of the project in use
+fn project_file(name: &str) -> PathBuf {
+    match &*PROJECT.read().unwrap() {
+        Some(project) => project.path.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+fn db_path() -> PathBuf {
+    project_file(DB_PATH)
+}
+
+
+pub fn write_changes(tickets: &[Tickets]) -> Result<(), Error> {
+    //Keeps the metadata, and never overwrites a file from a newer version
+    let mut db = if db_path().exists() {read_ticket_db()?} else {TicketDb::new(Vec::new())};
+    db.set_tickets(tickets.to_vec());
+    fs::write(db_path(), &serde_json::to_vec(&db)?)?;
     Ok(())
 }
 
 pub fn read_db() -> Result<Vec<Tickets>, Error> {
+    Ok(read_ticket_db()?.tickets)
+}
 
-    if !Path::new(DB_PATH).exists() {
-        //create the file
-        File::create(DB_PATH)?;
+pub fn read_ticket_db() -> Result<TicketDb, Error> {
+    Ok(load_db()?.0)
+}
+
+//Upgrades the database file if it is from an older version, returns where the old file was copied to
+pub fn upgrade_db() -> Result<Option<PathBuf>, Error> {
+    Ok(load_db()?.1)
+}
+
+//Id the next new ticket gets
+pub fn next_ticket_id() -> Result<i32, Error> {
+    Ok(read_ticket_db()?.next_free_id())
+}
+
+fn load_db() -> Result<(TicketDb, Option<PathBuf>), Error> {
+
+    if !db_path().exists() {
         //write the default ticket
         let default_ticket = Tickets::default();
-        write_changes(&vec![default_ticket])?;
+        write_changes(&[default_ticket])?;
+    }
+
+    let db_content = fs::read_to_string(db_path())?;
+    let (db, version) = parse_ticket_db(&db_content)?;
+    if version == SCHEMA_VERSION {
+        return Ok((db, None));
+    }
+    //Older files are copied before they are upgraded in place
+    let backup = PathBuf::from(migration_backup_name(&db_path().to_string_lossy(), version));
+    fs::copy(db_path(), &backup)?;
+    fs::write(db_path(), &serde_json::to_vec(&db)?)?;
+    Ok((db, Some(backup)))
+}
+
+pub fn write_sprints(sprints: &Vec<Sprint>) -> Result<(), Error> {
+    if let Err(error) = read_sprints() {
+        return Err(Error::UnreadableFile(SPRINT_DB_PATH.to_string(), error.to_string()));
+    }
+    fs::write(project_file(SPRINT_DB_PATH), &serde_json::to_vec(&sprints)?)?;
+    Ok(())
+}
+
+pub fn read_sprints() -> Result<Vec<Sprint>, Error> {
+
+    //No sprints have been planned yet
+    if !project_file(SPRINT_DB_PATH).exists() {
+        return Ok(Vec::new());
+    }
+
+    let db_content = fs::read_to_string(project_file(SPRINT_DB_PATH))?;
+    let parsed: Vec<Sprint> = serde_json::from_str(&db_content)?;
+    Ok(parsed)
+}
+
+pub fn write_settings(settings: &DbSettings) -> Result<(), Error> {
+    if let Err(error) = read_settings() {
+        return Err(Error::UnreadableFile(SETTINGS_PATH.to_string(), error.to_string()));
+    }
+    fs::write(project_file(SETTINGS_PATH), &serde_json::to_vec(&settings)?)?;
+    Ok(())
+}
+
+pub fn read_settings() -> Result<DbSettings, Error> {
+
+    //Use defaults until the settings are changed for this database
+    if !project_file(SETTINGS_PATH).exists() {
+        return Ok(DbSettings::default());
+    }
+
+    let db_content = fs::read_to_string(project_file(SETTINGS_PATH))?;
+    let parsed: DbSettings = serde_json::from_str(&db_content)?;
+    parsed.validate().map_err(|error| Error::InvalidInput(format!("{}: {}", SETTINGS_PATH, error)))?;
+    Ok(parsed)
+}
+
+//A project can have its own templates next to its database, otherwise the ones in the config directory are used
+pub fn templates_path() -> Option<PathBuf> {
+    let project_templates = project_file(TEMPLATES_PATH);
+    if project_templates.exists() {
+        return Some(project_templates);
+    }
+    dirs::config_dir().map(|dir| dir.join("scrum").join(TEMPLATES_PATH))
+}
+
+//The registry of projects is shared by all of them, so it lives in the config directory
+pub fn projects_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("scrum").join(PROJECTS_PATH))
+}
+
+pub fn read_projects() -> Result<Vec<Project>, Error> {
+
+    //No projects registered yet, only the working directory is used
+    let path = match projects_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Vec::new()),
+    };
+
+    let content = fs::read_to_string(path)?;
+    let parsed: Vec<Project> = serde_json::from_str(&content)?;
+    Ok(parsed)
+}
+
+pub fn write_projects(projects: &[Project]) -> Result<(), Error> {
+    if let Some(path) = projects_path() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, &serde_json::to_vec_pretty(&projects)?)?;
+    }
+    Ok(())
+}
+
+//Registers the directory under the name, a relative path is taken from the working directory
+pub fn add_project(name: &str, path: &Path) -> Result<Project, Error> {
+    if !valid_project_name(name) {
+        return Err(Error::InvalidProject(format!("\"{}\" is not a project name, use letters, digits, '-' and '_'", name)));
+    }
+    let mut projects = read_projects()?;
+    if projects.iter().any(|project| project.name == name) {
+        return Err(Error::InvalidProject(format!("there already is a project named {}", name)));
+    }
+    let path: PathBuf = std::env::current_dir()?.join(path).components().collect();
+    let project = Project{ name: name.to_string(), path };
+    fs::create_dir_all(&project.path)?;
+    projects.push(project.clone());
+    write_projects(&projects)?;
+    Ok(project)
+}
+
+//Only the registry entry is removed, the files stay where they are
+pub fn remove_project(name: &str) -> Result<Project, Error> {
+    let project = find_project(name)?;
+    let projects: Vec<Project> = read_projects()?.into_iter().filter(|project| project.name != name).collect();
+    write_projects(&projects)?;
+    Ok(project)
+}
+
+pub fn find_project(name: &str) -> Result<Project, Error> {
+    read_projects()?
+        .into_iter()
+        .find(|project| project.name == name)
+        .ok_or(Error::UnknownProject(name.to_string()))
+}
+
+//Project of a transferred ticket, None is the working directory
+pub fn transfer_project(name: &Option<String>) -> Result<Option<Project>, Error> {
+    name.as_ref().map(|name| find_project(name)).transpose()
+}
+
+//Adds the ticket with its notes, attachments and history to the database of another project, where it gets a
+//new id, and returns where it went. A moved ticket is replaced by a closed tombstone pointing to the new one
+pub fn transfer_ticket(id: i32, target: Option<Project>, keep: bool) -> Result<TicketTransfer, Error> {
+    let source = current_project();
+    if source == target {
+        return Err(Error::InvalidProject("the ticket is already in that project".to_string()));
+    }
+    let mut tickets = read_db()?;
+    let index = tickets.iter().position(|ticket| ticket.id == id).ok_or(Error::NotFound(id))?;
+    let ticket = tickets[index].clone();
+    let key = read_settings()?.ticket_key(id);
+    if let Some(moved) = &ticket.moved_to {
+        return Err(Error::InvalidProject(format!("ticket {} was moved to {}", key, moved.describe())));
+    }
+    if !keep && !children(id, &tickets).is_empty() {
+        return Err(Error::InvalidProject(format!("ticket {} has child tickets, move them first", key)));
+    }
+    let from = TicketTransfer{ project: source.as_ref().map(|project| project.name.clone()), id, key, transferred_at: Utc::now() };
+    let files = ticket.attachments
+        .iter()
+        .map(|attachment| fs::read(attachment_path(&attachment.hash)).map(|data| (attachment.hash.clone(), data)))
+        .collect::<io::Result<Vec<(String, Vec<u8>)>>>()?;
+
+    let added = in_project(target.clone(), || add_transferred(&ticket, from, &files))?;
+    let to = TicketTransfer{ project: target.as_ref().map(|project| project.name.clone()), ..added };
+
+    if !keep {
+        tickets[index] = ticket.tombstone(to.clone());
+        if let Err(error) = write_changes(&tickets) {
+            //Without the tombstone the ticket would be in both projects, so the copy is taken back
+            in_project(target, || remove_transferred(to.id))?;
+            return Err(error);
+        }
+    }
+    Ok(to)
+}
+
+//Runs `work` on the files of the project, the project in use before is restored even when `work` fails
+fn in_project<T>(project: Option<Project>, work: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    let previous = current_project();
+    set_project(project)?;
+    let result = work();
+    set_project(previous)?;
+    result
+}
+
+//The copied attachment files stay, other tickets of the project may use them too
+fn remove_transferred(id: i32) -> Result<(), Error> {
+    let mut tickets = read_db()?;
+    tickets.retain(|ticket| ticket.id != id);
+    write_changes(&tickets)
+}
+
+fn add_transferred(ticket: &Tickets, from: TicketTransfer, files: &[(String, Vec<u8>)]) -> Result<TicketTransfer, Error> {
+    let mut db = read_ticket_db()?;
+    let settings = read_settings()?;
+    let mut copy = ticket.transferred_copy(db.next_free_id(), from);
+    //A priority the project does not know becomes its lowest one
+    copy.priority = settings.find_priority(&copy.priority).unwrap_or_else(|| settings.lowest_priority());
+    for (hash, data) in files {
+        let path = attachment_path(hash);
+        if !path.exists() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, data)?;
+        }
     }
+    let to = TicketTransfer{ project: None, id: copy.id, key: settings.ticket_key(copy.id), transferred_at: Utc::now() };
+    db.tickets.push(copy);
+    write_changes(&db.tickets)?;
+    Ok(to)
+}
+
+pub fn read_templates() -> Result<Vec<Template>, Error> {
+
+    //Built in templates until the user writes their own
+    let path = match templates_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Template::defaults()),
+    };
 
-    let db_content = fs::read_to_string(DB_PATH)?;
-    let parsed: Vec<Tickets> = serde_json::from_str(&db_content)?; 
+    let content = fs::read_to_string(path)?;
+    let parsed: Vec<Template> = serde_json::from_str(&content)?;
     Ok(parsed)
 }
 
+//Attachments are stored by hash next to the database, so the same file is only stored once
+pub fn attachment_path(hash: &str) -> PathBuf {
+    db_path()
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(ATTACHMENTS_DIR)
+        .join(hash)
+}
+
+//Weekly status reports written from the TUI are kept next to the database
+pub fn report_path(week_start: NaiveDate) -> PathBuf {
+    db_path().with_file_name(format!("status-report-{}.html", week_start.format("%Y-%m-%d")))
+}
+
+//Snapshots are kept next to the database
+pub fn backups_dir() -> PathBuf {
+    db_path().with_file_name(BACKUPS_DIR)
+}
+
+//Writes a snapshot of the ticket database and removes the ones the retention no longer keeps
+pub fn create_snapshot() -> Result<Snapshot, Error> {
+    let db = read_ticket_db()?;
+    let snapshot = Snapshot::new(Local::now().naive_local());
+    fs::create_dir_all(backups_dir())?;
+    //Never replace a snapshot that is already there
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).open(backups_dir().join(&snapshot.name))?;
+    file.write_all(&serde_json::to_vec(&db)?)?;
+    prune_snapshots(&read_settings()?.backup_retention)?;
+    Ok(snapshot)
+}
+
+//Newest first
+pub fn list_snapshots() -> Result<Vec<Snapshot>, Error> {
+    if !backups_dir().exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(backups_dir())? {
+        if let Some(snapshot) = entry?.file_name().to_str().and_then(Snapshot::from_name) {
+            snapshots.push(snapshot);
+        }
+    }
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.taken_at));
+    Ok(snapshots)
+}
+
+//Removes the snapshots the retention does not keep and returns them
+pub fn prune_snapshots(retention: &Retention) -> Result<Vec<Snapshot>, Error> {
+    let expired = expired_snapshots(&list_snapshots()?, retention);
+    for snapshot in expired.iter() {
+        fs::remove_file(backups_dir().join(&snapshot.name))?;
+    }
+    Ok(expired)
+}
+
+//Snapshots from older versions are upgraded in memory, the file stays as it was
+pub fn read_snapshot(snapshot: &Snapshot) -> Result<TicketDb, Error> {
+    let content = fs::read_to_string(backups_dir().join(&snapshot.name))?;
+    Ok(parse_ticket_db(&content)?.0)
+}
+
+//Replaces all tickets with the ones in the snapshot. The current state is snapshotted first,
+//so a restore can be undone, and that snapshot is returned
+pub fn restore_snapshot(snapshot: &Snapshot) -> Result<Snapshot, Error> {
+    let restored = read_snapshot(snapshot)?;
+    let current = create_snapshot()?;
+    write_changes(&restored.tickets)?;
+    Ok(current)
+}
+
+//Puts one ticket back the way it was in the snapshot. A ticket created after the snapshot is
+//removed, None is returned then
+pub fn restore_ticket(snapshot: &Snapshot, id: i32) -> Result<Option<Tickets>, Error> {
+    let restored = read_snapshot(snapshot)?.tickets.into_iter().find(|ticket| ticket.id == id);
+    let mut tickets = read_db()?;
+    match (tickets.iter().position(|ticket| ticket.id == id), restored.clone()) {
+        (Some(index), Some(ticket)) => tickets[index] = ticket,
+        (Some(index), None) => {
+            tickets.remove(index);
+        }
+        (None, Some(ticket)) => tickets.push(ticket),
+        (None, None) => return Err(Error::NotFound(id)),
+    }
+    sync_links(id, &mut tickets);
+    write_changes(&tickets)?;
+    Ok(restored)
+}
+
+//Copies the file into the attachments directory and returns its metadata
+pub fn store_attachment(source: &Path, added_by: &str) -> io::Result<Attachment> {
+    let data = fs::read(source)?;
+    let hash = attachment_hash(&data);
+    let path = attachment_path(&hash);
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, &data)?;
+    }
+    Ok(Attachment{
+        name: source.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| hash.clone()),
+        size: data.len() as u64,
+        hash,
+        added_by: added_by.to_string(),
+        added_at: Utc::now(),
+    })
+}
+
 pub fn get_open_tickets() -> Vec<Tickets> {
     let tickets = read_db().unwrap();
     let mut open_tickets = Vec::new();
@@ -53,4 +454,82 @@ pub fn get_closed_tickets() -> Vec<Tickets> {
         }
     }
     closed_tickets
-}
\ No newline at end of file
+}
+//Empty directory for a test, named after it
+#[cfg(test)]
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("scrum-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+//Makes a test use the files of an empty project. The project in use is shared, so tests
+//that read or write files run one at a time while they hold the returned guard
+#[cfg(test)]
+pub fn scratch_project(name: &str) -> std::sync::MutexGuard<'static, ()> {
+    static SCRATCH: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let guard = SCRATCH.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    set_project(Some(Project{ name: name.to_string(), path: scratch_dir(name) })).unwrap();
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(id: i32, title: &str) -> Tickets {
+        Tickets{ id, title: title.to_string(), priority: "Low".to_string(), ..Tickets::default() }
+    }
+
+    fn other_project(name: &str) -> Project {
+        Project{ name: name.to_string(), path: scratch_dir(name) }
+    }
+
+    //Transfers
+
+    #[test]
+    fn moved_ticket_leaves_a_tombstone_behind() {
+        let _guard = scratch_project("move-source");
+        let target = other_project("move-target");
+        write_changes(&[Tickets{ description: "Weekly".to_string(), ..ticket(4, "Backup") }]).unwrap();
+        in_project(Some(target.clone()), || write_changes(&[ticket(1, "Printer")])).unwrap();
+
+        let to = transfer_ticket(4, Some(target.clone()), false).unwrap();
+        assert_eq!((to.project.as_deref(), to.id), (Some("move-target"), 2));
+
+        let tombstone = read_db().unwrap().into_iter().find(|ticket| ticket.id == 4).unwrap();
+        assert_eq!(tombstone.status, TicketStatus::Closed);
+        assert_eq!(tombstone.moved_to, Some(to));
+        assert_eq!(tombstone.description, "");
+
+        let copy = in_project(Some(target), read_db).unwrap().into_iter().find(|ticket| ticket.id == 2).unwrap();
+        assert_eq!(copy.title, "Backup");
+        assert_eq!(copy.description, "Weekly");
+        assert_eq!(copy.copied_from.map(|from| (from.project, from.id)), Some((Some("move-source".to_string()), 4)));
+    }
+
+    #[test]
+    fn copied_ticket_stays_in_place() {
+        let _guard = scratch_project("copy-source");
+        let target = other_project("copy-target");
+        write_changes(&[ticket(4, "Backup")]).unwrap();
+
+        transfer_ticket(4, Some(target.clone()), true).unwrap();
+        let source = read_db().unwrap();
+        assert_eq!(source[0].status, TicketStatus::Open);
+        assert!(source[0].moved_to.is_none());
+        assert!(in_project(Some(target), read_db).unwrap().iter().any(|ticket| ticket.title == "Backup"));
+    }
+
+    #[test]
+    fn tombstones_and_the_same_project_are_refused() {
+        let _guard = scratch_project("refuse-source");
+        let target = other_project("refuse-target");
+        write_changes(&[ticket(4, "Backup")]).unwrap();
+
+        assert!(matches!(transfer_ticket(4, current_project(), false), Err(Error::InvalidProject(_))));
+        transfer_ticket(4, Some(target.clone()), false).unwrap();
+        assert!(matches!(transfer_ticket(4, Some(target), false), Err(Error::InvalidProject(_))));
+        assert!(matches!(transfer_ticket(9, None, false), Err(Error::NotFound(9))));
+    }
+}
@@ -0,0 +1,367 @@
+//For sharing tickets outside the app: Markdown and HTML documents and the weekly status report
+
+use std::{fs, path::Path};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DocumentFormat {
+    Markdown,
+    Html,
+}
+
+impl DocumentFormat {
+    //Chosen by the file extension, None for anything else
+    pub fn from_path(path: &Path) -> Option<DocumentFormat> {
+        match path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase()).as_deref() {
+            Some("md") | Some("markdown") => Some(DocumentFormat::Markdown),
+            Some("html") | Some("htm") => Some(DocumentFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+//Tickets closed in a week and everything still open, by status and then by assignee
+pub struct WeeklyReport {
+    pub week_start: NaiveDate,
+    pub opened: usize,
+    pub closed: usize,
+    pub overdue: usize,
+    pub sections: Vec<(String, Vec<AssigneeGroup>)>,
+}
+
+//Name of the assignee and their tickets
+pub type AssigneeGroup = (String, Vec<Tickets>);
+
+//Stylesheet of the HTML documents, kept inline so the file can be mailed on its own
+const STYLE: &str = "body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; max-width: 860px; margin: 2em auto; padding: 0 1em; color: #282828; line-height: 1.5; }
+h1 { border-bottom: 2px solid #d79921; padding-bottom: .3em; }
+h2 { margin-top: 2em; color: #076678; }
+h3 { color: #427b58; }
+article { border: 1px solid #d5c4a1; border-radius: 6px; padding: 0 1.2em 1em; margin: 1.5em 0; }
+dl { display: grid; grid-template-columns: max-content auto; gap: .2em 1em; }
+dt { font-weight: bold; color: #7c6f64; }
+dd { margin: 0; }
+.description { white-space: pre-wrap; }
+.timeline { list-style: none; padding-left: 1em; border-left: 3px solid #d5c4a1; }
+.timeline li { margin: .6em 0; white-space: pre-wrap; }
+.timeline time, .muted { color: #928374; font-size: .9em; }
+.status-open { color: #af3a03; }
+.status-closed { color: #79740e; }
+table { border-collapse: collapse; width: 100%; }
+td, th { text-align: left; padding: .3em .6em; border-bottom: 1px solid #ebdbb2; }";
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+//Label and value of every metadata row shown for a ticket
+fn metadata(ticket: &Tickets, settings: &DbSettings) -> Vec<(&'static str, String)> {
+    let mut rows = vec![
+        ("Status", ticket.status.to_string().to_string()),
+        ("Priority", ticket.priority.clone()),
+        ("Kind", ticket.kind.to_string().to_string()),
+        ("Assignee", ticket.assignee.clone().unwrap_or("Unassigned".to_string())),
+    ];
+    if let Some(estimate) = ticket.estimate {
+        rows.push(("Estimate", estimate.to_string()));
+    }
+    if let Some(due) = ticket.due {
+        rows.push(("Due", due.format("%Y-%m-%d").to_string()));
+    }
+    if let Some(parent) = ticket.parent {
+        rows.push(("Parent", settings.ticket_ref(parent)));
+    }
+    if !ticket.tags.is_empty() {
+        rows.push(("Tags", ticket.tags.iter().cloned().collect::<Vec<String>>().join(", ")));
+    }
+    if let Some((done, total)) = ticket.checklist_progress() {
+        rows.push(("Checklist", format!("{}/{}", done, total)));
+    }
+    rows.push(("Created", format_time(&ticket.created_at)));
+    rows.push(("Updated", format_time(&ticket.updated_at)));
+    if let Some(closed_at) = ticket.closed_at() {
+        rows.push(("Closed", format_time(&closed_at)));
+    }
+    rows
+}
+
+//Notes oldest first, the way they happened
+fn timeline(ticket: &Tickets) -> Vec<Note> {
+    let mut notes: Vec<Note> = ticket.notes.iter().flatten().cloned().collect();
+    notes.sort_by_key(|note| note.created_at);
+    notes
+}
+
+fn ticket_markdown(ticket: &Tickets, settings: &DbSettings) -> String {
+    let mut text = format!("## {} {}\n\n", settings.ticket_ref(ticket.id), ticket.title);
+    for (label, value) in metadata(ticket, settings) {
+        text.push_str(&format!("- **{}:** {}\n", label, value));
+    }
+    if !ticket.description.trim().is_empty() {
+        text.push_str(&format!("\n{}\n", ticket.description.trim()));
+    }
+    if !ticket.checklist.is_empty() {
+        text.push_str("\n### Checklist\n\n");
+        for item in ticket.checklist.iter() {
+            text.push_str(&format!("- [{}] {}\n", if item.done {"x"} else {" "}, item.text));
+        }
+    }
+    let notes = timeline(ticket);
+    if !notes.is_empty() {
+        text.push_str("\n### Notes\n\n");
+        for note in notes {
+            //Later lines of a note are indented so they stay in the same list item
+            text.push_str(&format!("- **{}** {}\n", format_time(&note.created_at), note.text.trim().replace('\n', "\n  ")));
+        }
+    }
+    text
+}
+
+fn ticket_html(ticket: &Tickets, settings: &DbSettings) -> String {
+    let mut html = format!("<article>\n<h2>{} {}</h2>\n<dl>\n", escape_html(&settings.ticket_ref(ticket.id)), escape_html(&ticket.title));
+    for (label, value) in metadata(ticket, settings) {
+        let class = if label == "Status" {format!(" class=\"status-{}\"", escape_html(&value.to_lowercase()))} else {String::new()};
+        html.push_str(&format!("<dt>{}</dt><dd{}>{}</dd>\n", label, class, escape_html(&value)));
+    }
+    html.push_str("</dl>\n");
+    if !ticket.description.trim().is_empty() {
+        html.push_str(&format!("<p class=\"description\">{}</p>\n", escape_html(ticket.description.trim())));
+    }
+    if !ticket.checklist.is_empty() {
+        html.push_str("<h3>Checklist</h3>\n<ul>\n");
+        for item in ticket.checklist.iter() {
+            let checked = if item.done {" checked"} else {""};
+            html.push_str(&format!("<li><input type=\"checkbox\" disabled{}> {}</li>\n", checked, escape_html(&item.text)));
+        }
+        html.push_str("</ul>\n");
+    }
+    let notes = timeline(ticket);
+    if !notes.is_empty() {
+        html.push_str("<h3>Notes</h3>\n<ol class=\"timeline\">\n");
+        for note in notes {
+            html.push_str(&format!("<li><time>{}</time><br>{}</li>\n", format_time(&note.created_at), escape_html(note.text.trim())));
+        }
+        html.push_str("</ol>\n");
+    }
+    html.push_str("</article>\n");
+    html
+}
+
+//Complete HTML page around the body
+fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{style}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p class=\"muted\">Generated {generated}</p>\n{body}</body>\n</html>\n",
+        title = escape_html(title),
+        style = STYLE,
+        generated = Local::now().format("%Y-%m-%d %H:%M"),
+        body = body,
+    )
+}
+
+pub fn tickets_document(title: &str, tickets: &[Tickets], format: DocumentFormat, settings: &DbSettings) -> String {
+    match format {
+        DocumentFormat::Markdown => {
+            let sections: Vec<String> = tickets.iter().map(|ticket| ticket_markdown(ticket, settings)).collect();
+            format!("# {}\n\n{}", title, sections.join("\n"))
+        }
+        DocumentFormat::Html => {
+            let body: String = tickets.iter().map(|ticket| ticket_html(ticket, settings)).collect();
+            html_page(title, &body)
+        }
+    }
+}
+
+//First day of the week the given number of weeks before this one, weeks start on Monday
+pub fn week_start(weeks_ago: i64) -> NaiveDate {
+    let today = Local::now().naive_local().date();
+    today - Duration::days(today.weekday().num_days_from_monday() as i64) - Duration::weeks(weeks_ago)
+}
+
+pub fn weekly_report(tickets: &[Tickets], week_start: NaiveDate, settings: &DbSettings) -> WeeklyReport {
+    let week_end = week_start + Duration::days(7);
+    let in_week = |time: DateTime<Utc>| {
+        let date = time.with_timezone(&Local).naive_local().date();
+        date >= week_start && date < week_end
+    };
+    //Open at the end of the week, so older reports do not list tickets created or closed later
+    let last_day = (week_end - Duration::days(1)).min(Local::now().naive_local().date());
+    let end = week_end.and_hms_opt(0, 0, 0).and_then(|end| Local.from_local_datetime(&end).earliest()).map(|end| end.with_timezone(&Utc)).unwrap_or_else(Utc::now);
+    let open: Vec<Tickets> = tickets
+        .iter()
+        .filter(|ticket| ticket.status_at(end) == Some(TicketStatus::Open))
+        .cloned()
+        .collect();
+    let closed: Vec<Tickets> = tickets.iter().filter(|ticket| ticket.closed_at().is_some_and(in_week)).cloned().collect();
+
+    WeeklyReport {
+        week_start,
+        opened: tickets.iter().filter(|ticket| in_week(ticket.created_at)).count(),
+        closed: closed.len(),
+        overdue: open.iter().filter(|ticket| ticket.due.is_some_and(|due| due < last_day)).count(),
+        sections: vec![
+            (format!("{} this week", settings.status_name(&TicketStatus::Closed)), by_assignee(closed, settings)),
+            (settings.status_name(&TicketStatus::Open), by_assignee(open, settings)),
+        ],
+    }
+}
+
+//Assignees in alphabetical order with unassigned tickets last, highest priority first within each
+fn by_assignee(tickets: Vec<Tickets>, settings: &DbSettings) -> Vec<AssigneeGroup> {
+    let mut groups: Vec<AssigneeGroup> = Vec::new();
+    for ticket in tickets {
+        let assignee = ticket.assignee.clone().unwrap_or_default();
+        match groups.iter_mut().find(|(name, _)| *name == assignee) {
+            Some((_, tickets)) => tickets.push(ticket),
+            None => groups.push((assignee, vec![ticket])),
+        }
+    }
+    groups.sort_by(|a, b| (a.0.is_empty(), &a.0).cmp(&(b.0.is_empty(), &b.0)));
+    for (name, tickets) in groups.iter_mut() {
+        if name.is_empty() {
+            *name = "Unassigned".to_string();
+        }
+        tickets.sort_by_key(|ticket| (settings.priority_rank(&ticket.priority), ticket.id));
+    }
+    groups
+}
+
+//Short remark after a ticket in the report, such as "new, due 2022-05-20"
+fn report_remark(report: &WeeklyReport, ticket: &Tickets) -> String {
+    let mut remarks = Vec::new();
+    let created = ticket.created_at.with_timezone(&Local).naive_local().date();
+    if created >= report.week_start && created < report.week_start + Duration::days(7) {
+        remarks.push("new".to_string());
+    }
+    if ticket.status == TicketStatus::Open {
+        if let Some(due) = ticket.due {
+            remarks.push(format!("due {}", due.format("%Y-%m-%d")));
+        }
+    }
+    if let Some((done, total)) = ticket.checklist_progress() {
+        remarks.push(format!("checklist {}/{}", done, total));
+    }
+    remarks.join(", ")
+}
+
+pub fn report_title(report: &WeeklyReport) -> String {
+    format!("Status report {} to {}", report.week_start.format("%Y-%m-%d"), (report.week_start + Duration::days(6)).format("%Y-%m-%d"))
+}
+
+fn report_summary(report: &WeeklyReport) -> String {
+    let open: usize = report.sections.get(1).map(|(_, groups)| groups.iter().map(|(_, tickets)| tickets.len()).sum()).unwrap_or(0);
+    format!("{} opened, {} closed, {} still open ({} overdue)", report.opened, report.closed, open, report.overdue)
+}
+
+pub fn report_document(report: &WeeklyReport, format: DocumentFormat, settings: &DbSettings) -> String {
+    match format {
+        DocumentFormat::Markdown => {
+            let mut text = format!("# {}\n\n{}\n", report_title(report), report_summary(report));
+            for (status, groups) in report.sections.iter() {
+                text.push_str(&format!("\n## {}\n", status));
+                if groups.is_empty() {
+                    text.push_str("\nNothing\n");
+                }
+                for (assignee, tickets) in groups {
+                    text.push_str(&format!("\n### {} ({})\n\n", assignee, tickets.len()));
+                    for ticket in tickets {
+                        let remark = report_remark(report, ticket);
+                        let remark = if remark.is_empty() {String::new()} else {format!(" _({})_", remark)};
+                        text.push_str(&format!("- {} **{}** [{}]{}\n", settings.ticket_ref(ticket.id), ticket.title, ticket.priority, remark));
+                    }
+                }
+            }
+            text
+        }
+        DocumentFormat::Html => {
+            let mut body = format!("<p><strong>{}</strong></p>\n", report_summary(report));
+            for (status, groups) in report.sections.iter() {
+                body.push_str(&format!("<h2>{}</h2>\n", escape_html(status)));
+                if groups.is_empty() {
+                    body.push_str("<p class=\"muted\">Nothing</p>\n");
+                }
+                for (assignee, tickets) in groups {
+                    body.push_str(&format!("<h3>{} ({})</h3>\n<table>\n<tr><th>ID</th><th>Title</th><th>Priority</th><th></th></tr>\n", escape_html(assignee), tickets.len()));
+                    for ticket in tickets {
+                        body.push_str(&format!(
+                            "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"muted\">{}</td></tr>\n",
+                            escape_html(&settings.ticket_ref(ticket.id)), escape_html(&ticket.title), escape_html(&ticket.priority), escape_html(&report_remark(report, ticket)),
+                        ));
+                    }
+                    body.push_str("</table>\n");
+                }
+            }
+            html_page(&report_title(report), &body)
+        }
+    }
+}
+
+//Writes the report for the week chosen for the timesheet next to the database
+pub fn write_weekly_report(app: &mut AppState) -> Result<(), Error> {
+    let report = weekly_report(&all_tickets(app), week_start(app.timesheet_week), &app.settings);
+    let path = report_path(report.week_start);
+    fs::write(&path, report_document(&report, DocumentFormat::Html, &app.settings))?;
+    app.notice = format!("Wrote {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_html_escapes_configured_names() {
+        let settings = DbSettings{
+            priorities: vec!["<b>Now</b>".to_string()],
+            workflow: vec!["To <do>".to_string(), "Done & dusted".to_string()],
+            ..DbSettings::default()
+        };
+        let ticket = Tickets{ id: 1, title: "Backup".to_string(), priority: "<b>Now</b>".to_string(), assignee: Some("ann".to_string()), ..Tickets::default() };
+        let report = weekly_report(&[ticket], week_start(0), &settings);
+        let html = report_document(&report, DocumentFormat::Html, &settings);
+
+        assert!(html.contains("<h2>To &lt;do&gt;</h2>"));
+        assert!(html.contains("<h2>Done &amp; dusted this week</h2>"));
+        assert!(html.contains("<td>&lt;b&gt;Now&lt;/b&gt;</td>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn html_escapes_hand_edited_key_prefixes() {
+        let settings = DbSettings{ key_prefix: "<i>".to_string(), ..DbSettings::default() };
+        let ticket = Tickets{ id: 7, title: "Backup".to_string(), ..Tickets::default() };
+        let html = tickets_document("Tickets", std::slice::from_ref(&ticket), DocumentFormat::Html, &settings);
+        assert!(html.contains("<h2>&lt;i&gt;-7 Backup</h2>"));
+
+        let report = weekly_report(&[ticket], week_start(0), &settings);
+        let html = report_document(&report, DocumentFormat::Html, &settings);
+        assert!(html.contains("<td>&lt;i&gt;-7</td>"));
+        assert!(!html.contains("<i>"));
+    }
+
+    #[test]
+    fn report_groups_by_status_then_assignee() {
+        let settings = DbSettings::default();
+        let ticket = |id: i32, priority: &str, assignee: Option<&str>| Tickets{
+            id,
+            priority: priority.to_string(),
+            assignee: assignee.map(|assignee| assignee.to_string()),
+            ..Tickets::default()
+        };
+        let tickets = vec![ticket(1, "Low", None), ticket(2, "Low", Some("bob")), ticket(3, "High", Some("bob")), ticket(4, "Low", Some("ann"))];
+        let report = weekly_report(&tickets, week_start(0), &settings);
+
+        assert_eq!(report.opened, 4);
+        let (status, groups) = &report.sections[1];
+        assert_eq!(status, "Open");
+        let groups: Vec<(&str, Vec<i32>)> = groups.iter().map(|(name, tickets)| (name.as_str(), tickets.iter().map(|ticket| ticket.id).collect())).collect();
+        assert_eq!(groups, vec![("ann", vec![4]), ("bob", vec![3, 2]), ("Unassigned", vec![1])]);
+    }
+}
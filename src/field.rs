@@ -0,0 +1,183 @@
+//For custom fields: defining them per database and the values on tickets
+
+use std::cmp::Ordering;
+use chrono::Local;
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+
+//Order of the kinds when picking one in the form
+pub const FIELD_KINDS: [&str; 5] = ["Text", "Number", "Enum", "Date", "User"];
+
+pub fn init_add_field(app: &mut AppState) -> Result<(), Error> {
+    app.field_name_input = String::new();
+    app.field_options_input = String::new();
+    app.field_kind = 0;
+    app.field_focus = FieldItem::Name;
+    app.field_form = true;
+    app.prompt = String::new();
+    Ok(())
+}
+
+pub fn add_field(app: &mut AppState) -> Result<(), Error> {
+    let name = app.field_name_input.trim().to_string();
+    let options: Vec<String> = app.field_options_input
+        .split(',')
+        .map(|option| option.trim().to_string())
+        .filter(|option| !option.is_empty())
+        .collect();
+
+    //Show what is wrong in the form instead of saving
+    if name.is_empty() {
+        app.prompt = "Field needs a name".to_string();
+        return Ok(());
+    }
+    if app.settings.fields.iter().any(|field| field.name.eq_ignore_ascii_case(&name)) {
+        app.prompt = format!("There already is a field called {}", name);
+        return Ok(());
+    }
+    let kind = match FIELD_KINDS[app.field_kind] {
+        "Number" => FieldKind::Number,
+        "Enum" if options.is_empty() => {
+            app.prompt = "Enum fields need comma separated options".to_string();
+            return Ok(());
+        }
+        "Enum" => FieldKind::Enum(options),
+        "Date" => FieldKind::Date,
+        "User" => FieldKind::User,
+        _ => FieldKind::Text,
+    };
+
+    let mut settings = app.settings.clone();
+    settings.fields.push(FieldDef{ name, kind });
+    if let Err(error) = write_settings(&settings) {
+        app.prompt = format!("Field was not saved: {}", error);
+        return Ok(());
+    }
+    app.settings = settings;
+
+    app.field_list_state.select(Some(app.settings.fields.len() - 1));
+    app.field_form = false;
+    app.prompt = String::new();
+    Ok(())
+}
+
+//Removes the selected field and its values from every ticket
+pub fn remove_field(app: &mut AppState) -> Result<(), Error> {
+    if let Some(selected) = app.field_list_state.selected() {
+        if selected < app.settings.fields.len() {
+            let mut settings = app.settings.clone();
+            let field = settings.fields.remove(selected);
+            if let Err(error) = write_settings(&settings) {
+                app.prompt = format!("Field was not removed: {}", error);
+                return Ok(());
+            }
+            app.settings = settings;
+
+            for ticket in app.open_tickets.iter_mut().chain(app.closed_tickets.iter_mut()) {
+                ticket.fields.remove(&field.name);
+            }
+            update_db(app);
+            update_ticket_count(app);
+
+            let amount_fields = app.settings.fields.len();
+            clamp_list_state(&mut app.field_list_state, amount_fields);
+        }
+    }
+    Ok(())
+}
+
+//Fills the edit form inputs from the ticket being edited
+pub fn init_field_inputs(app: &mut AppState) {
+    app.field_inputs = app.settings.fields
+        .iter()
+        .map(|field| app.edit_ticket.fields.get(&field.name).cloned().unwrap_or_default())
+        .collect();
+}
+
+//Validates the typed values and stores them on the ticket being edited, returns the first problem found
+pub fn apply_field_inputs(app: &mut AppState) -> Result<(), String> {
+    let today = Local::now().naive_local().date();
+    let mut values = Vec::new();
+    for (field, input) in app.settings.fields.iter().zip(app.field_inputs.iter()) {
+        values.push((field.name.clone(), field.validate(input, today)?));
+    }
+    for (name, value) in values {
+        if value.is_empty() {
+            app.edit_ticket.fields.remove(&name);
+        } else {
+            app.edit_ticket.fields.insert(name, value);
+        }
+    }
+    Ok(())
+}
+
+//Steps an enum field through its options
+pub fn cycle_field_option(app: &mut AppState, index: usize, forward: bool) {
+    if let Some(FieldKind::Enum(options)) = app.settings.fields.get(index).map(|field| field.kind.clone()) {
+        let position = options.iter().position(|option| option.eq_ignore_ascii_case(app.field_inputs[index].trim()));
+        let next = match position {
+            Some(position) if forward => (position + 1) % options.len(),
+            Some(position) => (position + options.len() - 1) % options.len(),
+            None => 0,
+        };
+        app.field_inputs[index] = options[next].clone();
+    }
+}
+
+//Parses "name=value" from the filter prompt, an empty filter clears it and a ticket key jumps to the ticket
+pub fn set_field_filter(app: &mut AppState) {
+    let input = app.input.trim().to_string();
+    app.input = String::new();
+    app.active_menu_item = MenuItem::Tickets;
+    //set index to 0 to prevent crash
+    app.ticket_list_state.select(Some(0));
+
+    if input.is_empty() {
+        app.filter.field = None;
+        return;
+    }
+    let (name, value) = match input.split_once('=') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => {
+            match app.settings.find_ticket_id(&input) {
+                Some(id) if select_ticket(app, id) => {}
+                Some(_) => app.notice = format!("Ticket {} does not exist", input),
+                None => app.notice = "Filters are written as field=value, tickets are found by key such as OPS-42".to_string(),
+            }
+            return;
+        }
+    };
+    match app.settings.fields.iter().find(|field| field.name.eq_ignore_ascii_case(name)) {
+        Some(field) => app.filter.field = Some((field.name.clone(), value.to_string())),
+        None => app.notice = format!("There is no field called {}", name),
+    }
+}
+
+//Whether the ticket passes the custom field filter, values match case insensitively on any part
+pub fn matches_field_filter(app: &AppState, ticket: &Tickets) -> bool {
+    match &app.filter.field {
+        Some((name, value)) => ticket.fields
+            .get(name)
+            .is_some_and(|field| field.to_lowercase().contains(&value.to_lowercase())),
+        None => true,
+    }
+}
+
+//Numbers compare as numbers, everything else as text. Tickets without a value go last
+pub fn compare_field(field: &FieldDef, a: &Tickets, b: &Tickets) -> Ordering {
+    match (a.fields.get(&field.name), b.fields.get(&field.name)) {
+        (Some(a), Some(b)) => match field.kind {
+            FieldKind::Number => {
+                let a: f64 = a.parse().unwrap_or(0.0);
+                let b: f64 = b.parse().unwrap_or(0.0);
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            }
+            _ => a.cmp(b),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
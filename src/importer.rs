@@ -0,0 +1,591 @@
+//For moving over from other trackers: GitHub and GitLab issue exports and Jira CSV exports
+
+use std::{collections::BTreeMap, fs, path::Path};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use clap::ValueEnum;
+use serde_json::Value;
+use scrum_lib::*;
+use crate::db::*;
+use crate::storage::*;
+use crate::csvio::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, ValueEnum)]
+pub enum ImportSource {
+    //Columns picked by header, see csvio
+    Csv,
+    //`gh issue list --json ...` or the issues REST API
+    Github,
+    //issues.ndjson of a project export or the issues API
+    Gitlab,
+    //"Export Excel CSV (all fields)"
+    Jira,
+}
+
+//Ids of the tickets an import created and updated
+pub struct ImportResult {
+    pub created: Vec<i32>,
+    pub updated: Vec<i32>,
+    //Imported before but moved to another project since, the tombstone is left as it is
+    pub skipped: Vec<i32>,
+}
+
+//Guesses the source from the extension and the start of the file
+pub fn detect_source(path: &Path) -> ImportSource {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+    let content = fs::read_to_string(path).unwrap_or_default();
+    if extension == "json" || extension == "ndjson" {
+        if content.contains("\"iid\"") {
+            ImportSource::Gitlab
+        } else {
+            ImportSource::Github
+        }
+    } else if content.lines().next().unwrap_or("").to_lowercase().contains("issue key") {
+        ImportSource::Jira
+    } else {
+        ImportSource::Csv
+    }
+}
+
+//Priorities and statuses become the ones of the project's settings
+pub fn read_import(path: &Path, source: ImportSource, mapping: &BTreeMap<String, String>, settings: &DbSettings) -> Result<ImportReport, Error> {
+    match source {
+        ImportSource::Csv => read_csv(path, mapping, settings),
+        ImportSource::Github => read_issues(path, settings, github_issue),
+        ImportSource::Gitlab => read_issues(path, settings, gitlab_issue),
+        ImportSource::Jira => read_jira(path, settings),
+    }
+}
+
+//What importing a ticket does to the stored tickets
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImportTarget {
+    New,
+    Update(i32),
+    //Imported before but moved to another project since, the tombstone is left as it is
+    Skip(i32),
+}
+
+//Issues of other trackers are matched by their external id, rows of a CSV export by the id of the ticket
+fn same_ticket(stored: &Tickets, ticket: &Tickets) -> bool {
+    match &ticket.external_id {
+        Some(_) => stored.external_id == ticket.external_id,
+        None => ticket.id != 0 && stored.id == ticket.id,
+    }
+}
+
+//What every imported ticket would do, used for the previews
+pub fn import_targets(stored: &[Tickets], tickets: &[Tickets]) -> Vec<ImportTarget> {
+    tickets
+        .iter()
+        .map(|ticket| {
+            match stored.iter().find(|stored| same_ticket(stored, ticket)) {
+                Some(existing) if existing.moved_to.is_some() => ImportTarget::Skip(existing.id),
+                Some(existing) => ImportTarget::Update(existing.id),
+                None => ImportTarget::New,
+            }
+        })
+        .collect()
+}
+
+//Such as "add 3, update 1 and skip 0 tickets"
+pub fn describe_targets(targets: &[ImportTarget]) -> String {
+    let count = |wanted: fn(&ImportTarget) -> bool| targets.iter().filter(|target| wanted(target)).count();
+    format!(
+        "add {}, update {} and skip {} tickets",
+        count(|target| *target == ImportTarget::New),
+        count(|target| matches!(target, ImportTarget::Update(_))),
+        count(|target| matches!(target, ImportTarget::Skip(_))),
+    )
+}
+
+//Creates new tickets and updates the ones imported before, see same_ticket
+pub fn store_imported(storage: &dyn Storage, tickets: Vec<Tickets>) -> Result<ImportResult, Error> {
+    let mut stored = storage.tickets()?;
+    let mut result = ImportResult{ created: Vec::new(), updated: Vec::new(), skipped: Vec::new() };
+    for ticket in tickets {
+        match stored.iter_mut().find(|stored| same_ticket(stored, &ticket)) {
+            Some(existing) if existing.moved_to.is_some() => {
+                result.skipped.push(existing.id);
+            }
+            Some(existing) => {
+                merge_imported(existing, ticket);
+                storage.update(existing.clone())?;
+                result.updated.push(existing.id);
+            }
+            None => {
+                //Kept so a second entry for the same issue in the file updates this one
+                let created = storage.create(ticket)?;
+                result.created.push(created.id);
+                stored.push(created);
+            }
+        }
+    }
+    Ok(result)
+}
+
+//Takes over what the other tracker knows, local planning such as sprint, estimate and worklog is kept.
+//Tags added here are kept next to the labels of the tracker
+fn merge_imported(existing: &mut Tickets, ticket: Tickets) {
+    existing.title = ticket.title;
+    existing.description = ticket.description;
+    existing.priority = ticket.priority;
+    existing.assignee = ticket.assignee;
+    existing.tags.extend(ticket.tags);
+    if ticket.due.is_some() {
+        existing.due = ticket.due;
+    }
+    if existing.status != ticket.status {
+        let changed_at = ticket.history.last().map(|change| change.changed_at).unwrap_or_else(Utc::now);
+        existing.history.push(StatusChange{ status: ticket.status.clone(), changed_at });
+        existing.status = ticket.status;
+    }
+    //Comments are matched by when they were written, so edited comments replace the old text.
+    //CSV files have no times for the notes, a note with the same text is already there
+    let from_csv = ticket.external_id.is_none();
+    let notes = existing.notes.get_or_insert_with(Vec::new);
+    let mut matched = vec![false; notes.len()];
+    for note in ticket.notes.unwrap_or_default() {
+        match (0..matched.len()).find(|index| !matched[*index] && notes[*index].created_at == note.created_at) {
+            Some(index) => {
+                matched[index] = true;
+                notes[index] = note;
+            }
+            None if from_csv && notes.iter().any(|existing| existing.text.trim() == note.text.trim()) => {}
+            None => notes.push(note),
+        }
+    }
+}
+
+//First of the keys that is present and not null, exports differ in naming
+fn field<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().filter_map(|key| value.get(*key)).find(|value| !value.is_null())
+}
+
+fn text(value: &Value, keys: &[&str]) -> String {
+    field(value, keys).and_then(|value| value.as_str()).unwrap_or("").trim().to_string()
+}
+
+fn time(value: &Value, keys: &[&str]) -> Option<DateTime<Utc>> {
+    field(value, keys)
+        .and_then(|value| value.as_str())
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+//Label names, given as strings or as objects with a name or title
+fn labels(value: &Value, keys: &[&str]) -> Vec<String> {
+    field(value, keys)
+        .and_then(|labels| labels.as_array())
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|label| match label {
+                    Value::String(name) => Some(name.clone()),
+                    label => field(label, &["name", "title"]).or(label.get("label").and_then(|label| label.get("title"))).and_then(|name| name.as_str()).map(|name| name.to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+//High and medium priorities of other trackers are the highest and the middle priority of the project,
+//unless the project has a priority of that name
+fn tracker_priority(name: &str, settings: &DbSettings) -> String {
+    if let Some(priority) = settings.find_priority(name) {
+        return priority;
+    }
+    let level = match name {
+        "high" => 0,
+        "medium" => settings.priorities.len() / 2,
+        _ => settings.priorities.len(),
+    };
+    settings.priorities.get(level).cloned().unwrap_or_else(|| settings.lowest_priority())
+}
+
+//Trackers without a priority field often use labels such as "priority: high" or "P1"
+fn priority_from_labels(labels: &[String], settings: &DbSettings) -> String {
+    for label in labels {
+        let label = label.to_lowercase();
+        let is_priority = label.contains("priority") || label.contains("prio") || (label.len() == 2 && label.starts_with('p'));
+        if !is_priority {
+            continue;
+        }
+        //"priority: blocker" when the project has a Blocker priority
+        let words: Vec<&str> = label.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+        if let Some(priority) = words.iter().find_map(|word| settings.find_priority(word)) {
+            return priority;
+        }
+        if ["critical", "urgent", "high", "p0", "p1"].iter().any(|word| label.contains(word)) {
+            return tracker_priority("high", settings);
+        }
+        if ["medium", "normal", "p2"].iter().any(|word| label.contains(word)) {
+            return tracker_priority("medium", settings);
+        }
+    }
+    settings.lowest_priority()
+}
+
+//Builds the ticket every importer ends up with, closed tickets remember when they were closed
+#[allow(clippy::too_many_arguments)]
+fn imported_ticket(external_id: String, title: String, description: String, closed: bool, priority: String, labels: &[String], created_at: DateTime<Utc>, updated_at: DateTime<Utc>, closed_at: Option<DateTime<Utc>>) -> Tickets {
+    let status = if closed {TicketStatus::Closed} else {TicketStatus::Open};
+    let mut ticket = Tickets::new(0, title, description, status, priority, created_at, updated_at);
+    ticket.external_id = Some(external_id);
+    ticket.tags = labels.iter().map(|label| normalize_tag(label)).filter(|tag| !tag.is_empty()).collect();
+    if closed {
+        ticket.history.push(StatusChange{ status: TicketStatus::Closed, changed_at: closed_at.unwrap_or(updated_at) });
+    }
+    ticket
+}
+
+//A JSON array, a single issue or one issue per line, each read by the given function
+fn read_issues(path: &Path, settings: &DbSettings, read_issue: fn(&Value, &DbSettings) -> Result<Option<Tickets>, String>) -> Result<ImportReport, Error> {
+    let content = fs::read_to_string(path)?;
+    let mut report = ImportReport{ tickets: Vec::new(), errors: Vec::new() };
+    let issues: Vec<(String, Value)> = match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Array(issues)) => issues.into_iter().enumerate().map(|(index, issue)| (format!("issue {}", index + 1), issue)).collect(),
+        Ok(issue) => vec![("issue 1".to_string(), issue)],
+        Err(_) => {
+            let mut issues = Vec::new();
+            for (index, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                match serde_json::from_str::<Value>(line) {
+                    Ok(issue) => issues.push((format!("line {}", index + 1), issue)),
+                    Err(error) => report.errors.push((format!("line {}", index + 1), error.to_string())),
+                }
+            }
+            issues
+        }
+    };
+    for (place, issue) in issues {
+        match read_issue(&issue, settings) {
+            Ok(Some(ticket)) => report.tickets.push(ticket),
+            Ok(None) => {}
+            Err(error) => report.errors.push((place, error)),
+        }
+    }
+    Ok(report)
+}
+
+//Comments as notes, "author: text"
+fn comment_notes(comments: &[Value], text_keys: &[&str], author_keys: &[&str]) -> Vec<Note> {
+    comments
+        .iter()
+        //GitLab also exports notes written by the system, such as "changed the description"
+        .filter(|comment| !comment.get("system").and_then(|system| system.as_bool()).unwrap_or(false))
+        .filter_map(|comment| {
+            let body = text(comment, text_keys);
+            let created_at = time(comment, &["createdAt", "created_at"])?;
+            let updated_at = time(comment, &["updatedAt", "updated_at"]).unwrap_or(created_at);
+            let author = field(comment, &["author", "user"]).map(|author| text(author, author_keys)).unwrap_or_default();
+            let body = if author.is_empty() {body} else {format!("{}: {}", author, body)};
+            Some(Note::new(body, created_at, updated_at))
+        })
+        .collect()
+}
+
+fn github_issue(issue: &Value, settings: &DbSettings) -> Result<Option<Tickets>, String> {
+    //The REST API lists pull requests as issues too
+    if field(issue, &["pull_request"]).is_some() {
+        return Ok(None);
+    }
+    let number = field(issue, &["number"]).and_then(|number| number.as_i64()).ok_or("no issue number")?;
+    let title = text(issue, &["title"]);
+    if title.is_empty() {
+        return Err(format!("issue {} has no title", number));
+    }
+    //https://github.com/owner/repo/issues/12 or https://api.github.com/repos/owner/repo/issues/12
+    let url = text(issue, &["html_url", "url"]);
+    let repository = url
+        .split_once("github.com/")
+        .map(|(_, path)| path.trim_start_matches("repos/").split('/').take(2).collect::<Vec<&str>>().join("/"))
+        .unwrap_or_default();
+    let labels = labels(issue, &["labels"]);
+    let created_at = time(issue, &["createdAt", "created_at"]).ok_or(format!("issue {} has no creation date", number))?;
+    let updated_at = time(issue, &["updatedAt", "updated_at"]).unwrap_or(created_at);
+    let closed = text(issue, &["state"]).eq_ignore_ascii_case("closed");
+
+    let mut ticket = imported_ticket(
+        format!("github:{}#{}", repository, number),
+        title,
+        text(issue, &["body"]),
+        closed,
+        priority_from_labels(&labels, settings),
+        &labels,
+        created_at,
+        updated_at,
+        time(issue, &["closedAt", "closed_at"]),
+    );
+    let assignees = field(issue, &["assignees"]).and_then(|assignees| assignees.as_array()).cloned().unwrap_or_default();
+    ticket.assignee = assignees
+        .first()
+        .or(field(issue, &["assignee"]))
+        .map(|assignee| text(assignee, &["login", "name"]))
+        .filter(|login| !login.is_empty());
+    //The REST API only gives the number of comments, `gh issue list --json comments` gives them all
+    if let Some(comments) = field(issue, &["comments"]).and_then(|comments| comments.as_array()) {
+        let notes = comment_notes(comments, &["body"], &["login", "name"]);
+        if !notes.is_empty() {
+            ticket.notes = Some(notes);
+        }
+    }
+    Ok(Some(ticket))
+}
+
+fn gitlab_issue(issue: &Value, settings: &DbSettings) -> Result<Option<Tickets>, String> {
+    let iid = field(issue, &["iid"]).and_then(|iid| iid.as_i64()).ok_or("no issue iid")?;
+    let title = text(issue, &["title"]);
+    if title.is_empty() {
+        return Err(format!("issue {} has no title", iid));
+    }
+    //https://gitlab.com/group/project/-/issues/5, project exports only have the project id
+    let url = text(issue, &["web_url"]);
+    let project = match url.split_once("://").and_then(|(_, rest)| rest.split_once('/')).and_then(|(_, path)| path.split_once("/-/")) {
+        Some((project, _)) => project.to_string(),
+        None => field(issue, &["project_id"]).map(|id| id.to_string()).unwrap_or_default(),
+    };
+    let mut labels = labels(issue, &["labels"]);
+    labels.extend(self::labels(issue, &["label_links"]));
+    let created_at = time(issue, &["created_at"]).ok_or(format!("issue {} has no creation date", iid))?;
+    let updated_at = time(issue, &["updated_at"]).unwrap_or(created_at);
+    let closed = text(issue, &["state"]).eq_ignore_ascii_case("closed");
+
+    let mut ticket = imported_ticket(
+        format!("gitlab:{}#{}", project, iid),
+        title,
+        text(issue, &["description"]),
+        closed,
+        priority_from_labels(&labels, settings),
+        &labels,
+        created_at,
+        updated_at,
+        time(issue, &["closed_at"]),
+    );
+    let assignees = field(issue, &["assignees"]).and_then(|assignees| assignees.as_array()).cloned().unwrap_or_default();
+    ticket.assignee = assignees
+        .first()
+        .or(field(issue, &["assignee"]))
+        .map(|assignee| text(assignee, &["username", "name"]))
+        .filter(|name| !name.is_empty());
+    ticket.due = text(issue, &["due_date"]).parse::<NaiveDate>().ok();
+    if let Some(notes) = field(issue, &["notes"]).and_then(|notes| notes.as_array()) {
+        let notes = comment_notes(notes, &["note", "body"], &["username", "name"]);
+        if !notes.is_empty() {
+            ticket.notes = Some(notes);
+        }
+    }
+    Ok(Some(ticket))
+}
+
+//Jira writes dates like "19/Oct/22 4:31 PM", depending on the instance settings
+fn parse_jira_time(input: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let formats = ["%d/%b/%y %I:%M %p", "%d/%b/%Y %I:%M %p", "%d/%b/%y %H:%M", "%d.%m.%Y %H:%M"];
+    match formats.iter().find_map(|format| NaiveDateTime::parse_from_str(input, format).ok()) {
+        Some(time) => Ok(Local.from_local_datetime(&time).earliest().map(|time| time.with_timezone(&Utc))),
+        None => parse_time(input),
+    }
+}
+
+fn read_jira(path: &Path, settings: &DbSettings) -> Result<ImportReport, Error> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path).map_err(csv_error)?;
+    //Labels, comments and other multi-valued fields come as several columns with the same header
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(csv_error)?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+    let mut report = ImportReport{ tickets: Vec::new(), errors: Vec::new() };
+    if !headers.iter().any(|header| header == "issue key") || !headers.iter().any(|header| header == "summary") {
+        report.errors.push(("line 1".to_string(), "not a Jira export, the Issue key and Summary columns are missing".to_string()));
+        return Ok(report);
+    }
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                let line = error.position().map(|position| position.line()).unwrap_or(0);
+                report.errors.push((format!("line {}", line), error.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map(|position| position.line()).unwrap_or(0);
+        let cells = |name: &str| -> Vec<String> {
+            headers
+                .iter()
+                .zip(record.iter())
+                .filter(|(header, value)| *header == name && !value.trim().is_empty())
+                .map(|(_, value)| value.trim().to_string())
+                .collect()
+        };
+        let cell = |name: &str| cells(name).into_iter().next().unwrap_or_default();
+        match jira_row(&cell, &cells, settings) {
+            Ok(ticket) => report.tickets.push(ticket),
+            Err(error) => report.errors.push((format!("line {}", line), error)),
+        }
+    }
+    Ok(report)
+}
+
+fn jira_row(cell: &dyn Fn(&str) -> String, cells: &dyn Fn(&str) -> Vec<String>, settings: &DbSettings) -> Result<Tickets, String> {
+    let key = cell("issue key");
+    if key.is_empty() {
+        return Err("the issue key is empty".to_string());
+    }
+    let title = cell("summary");
+    if title.is_empty() {
+        return Err(format!("{} has no summary", key));
+    }
+    let priority = match settings.find_priority(&cell("priority")) {
+        Some(priority) => priority,
+        None => match cell("priority").to_lowercase().as_str() {
+            "highest" | "high" | "critical" | "blocker" => tracker_priority("high", settings),
+            "medium" | "major" => tracker_priority("medium", settings),
+            _ => settings.lowest_priority(),
+        },
+    };
+    let status = cell("status").to_lowercase();
+    let closed = cell("status category").eq_ignore_ascii_case("done")
+        || settings.find_status(&status) == Some(TicketStatus::Closed)
+        || ["done", "closed", "resolved", "won't do", "cancelled", "canceled"].contains(&status.as_str());
+    let created_at = parse_jira_time(&cell("created"))?.ok_or(format!("{} has no creation date", key))?;
+    let updated_at = parse_jira_time(&cell("updated"))?.unwrap_or(created_at);
+    let closed_at = parse_jira_time(&cell("resolved"))?;
+
+    let mut ticket = imported_ticket(
+        format!("jira:{}", key),
+        title,
+        cell("description"),
+        closed,
+        priority,
+        &cells("labels"),
+        created_at,
+        updated_at,
+        closed_at,
+    );
+    let assignee = cell("assignee");
+    ticket.assignee = if assignee.is_empty() {None} else {Some(assignee)};
+    let due = cell("due date");
+    if !due.is_empty() {
+        ticket.due = Some(parse_jira_time(&due)?.map(|due| due.with_timezone(&Local).naive_local().date()).ok_or(format!("\"{}\" is not a due date", due))?);
+    }
+    //Comments are "date;author;text"
+    let notes: Vec<Note> = cells("comment")
+        .into_iter()
+        .map(|comment| {
+            let parts: Vec<&str> = comment.splitn(3, ';').collect();
+            match parts.as_slice() {
+                [date, author, text] => match parse_jira_time(date.trim()) {
+                    Ok(Some(written)) => Note::new(format!("{}: {}", author.trim(), text.trim()), written, written),
+                    _ => Note::new(comment.clone(), updated_at, updated_at),
+                },
+                _ => Note::new(comment.clone(), updated_at, updated_at),
+            }
+        })
+        .collect();
+    if !notes.is_empty() {
+        ticket.notes = Some(notes);
+    }
+    Ok(ticket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imported(external_id: &str, title: &str) -> Tickets {
+        Tickets{ title: title.to_string(), priority: "Low".to_string(), external_id: Some(external_id.to_string()), ..Tickets::default() }
+    }
+
+    //Writes the export to a file of its own and reads it the way its extension and content suggest
+    fn read_export(name: &str, file: &str, content: &str) -> ImportReport {
+        let dir = scratch_dir(name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(file);
+        fs::write(&path, content).unwrap();
+        read_import(&path, detect_source(&path), &BTreeMap::new(), &DbSettings::default()).unwrap()
+    }
+
+    fn tags(ticket: &Tickets) -> Vec<&str> {
+        ticket.tags.iter().map(|tag| tag.as_str()).collect()
+    }
+
+    #[test]
+    fn github_issues_with_comments() {
+        let report = read_export("import-github", "issues.json", r#"[
+            {"number": 12, "title": "Printer jam", "body": "Floor 2", "state": "CLOSED", "url": "https://github.com/o/r/issues/12",
+             "labels": [{"name": "bug"}, {"name": "priority: high"}], "assignees": [{"login": "ann"}],
+             "createdAt": "2022-05-01T10:00:00Z", "updatedAt": "2022-05-03T10:00:00Z", "closedAt": "2022-05-02T10:00:00Z",
+             "comments": [{"author": {"login": "bob"}, "body": "Fixed", "createdAt": "2022-05-02T09:00:00Z"}]},
+            {"number": 13, "title": "A pull request", "pull_request": {}, "created_at": "2022-05-01T10:00:00Z"}
+        ]"#);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.tickets.len(), 1);
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.external_id.as_deref(), Some("github:o/r#12"));
+        assert_eq!((ticket.status.clone(), ticket.priority.as_str(), ticket.assignee.as_deref()), (TicketStatus::Closed, "High", Some("ann")));
+        assert_eq!(ticket.history.last().map(|change| change.changed_at.to_rfc3339()), Some("2022-05-02T10:00:00+00:00".to_string()));
+        assert!(tags(ticket).contains(&"bug"));
+        assert_eq!(ticket.notes.as_ref().unwrap()[0].text, "bob: Fixed");
+    }
+
+    #[test]
+    fn gitlab_issues_one_per_line() {
+        let report = read_export("import-gitlab", "issues.ndjson", concat!(
+            r#"{"iid": 5, "title": "Backup", "description": "Nightly", "state": "opened", "web_url": "https://gitlab.com/g/p/-/issues/5", "labels": ["ops"], "due_date": "2022-06-01", "created_at": "2022-05-01T10:00:00Z", "notes": [{"note": "changed the description", "system": true, "created_at": "2022-05-01T11:00:00Z"}, {"note": "On it", "author": {"username": "ann"}, "created_at": "2022-05-01T12:00:00Z"}]}"#, "\n",
+            "not json\n",
+        ));
+        assert_eq!(report.errors.len(), 1);
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.external_id.as_deref(), Some("gitlab:g/p#5"));
+        assert_eq!((ticket.status.clone(), ticket.priority.as_str()), (TicketStatus::Open, "Low"));
+        assert_eq!(ticket.due, NaiveDate::from_ymd_opt(2022, 6, 1));
+        let notes: Vec<&str> = ticket.notes.iter().flatten().map(|note| note.text.as_str()).collect();
+        assert_eq!(notes, vec!["ann: On it"]);
+    }
+
+    #[test]
+    fn jira_csv_with_repeated_columns() {
+        let report = read_export("import-jira", "jira.csv", concat!(
+            "Summary,Issue key,Status,Priority,Assignee,Created,Labels,Labels,Comment\n",
+            "Printer jam,OPS-7,Done,Highest,ann,01/May/22 10:00 AM,hardware,office,02/May/22 9:00 AM;bob;Fixed\n",
+            "No key,,To Do,Low,,01/May/22 10:00 AM,,,\n",
+        ));
+        assert_eq!(report.errors.len(), 1);
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.external_id.as_deref(), Some("jira:OPS-7"));
+        assert_eq!((ticket.status.clone(), ticket.priority.as_str(), ticket.assignee.as_deref()), (TicketStatus::Closed, "High", Some("ann")));
+        assert_eq!(tags(ticket), vec!["hardware", "office"]);
+        assert_eq!(ticket.notes.as_ref().unwrap()[0].text, "bob: Fixed");
+    }
+
+    #[test]
+    fn reimport_keeps_local_tags_and_planning() {
+        let _guard = scratch_project("import-merge");
+        let mut stored = Tickets{ id: 1, ..imported("github:o/r#1", "Printer") };
+        stored.tags = ["urgent".to_string(), "bug".to_string()].into_iter().collect();
+        stored.estimate = Some(3.0);
+        write_changes(&[stored]).unwrap();
+
+        let mut ticket = imported("github:o/r#1", "Printer jam");
+        ticket.tags = ["bug".to_string(), "hardware".to_string()].into_iter().collect();
+        let result = store_imported(&LocalStorage, vec![ticket]).unwrap();
+        assert_eq!(result.updated, vec![1]);
+        let stored = &read_db().unwrap()[0];
+        assert_eq!(stored.title, "Printer jam");
+        assert_eq!(tags(stored), vec!["bug", "hardware", "urgent"]);
+        assert_eq!(stored.estimate, Some(3.0));
+    }
+
+    #[test]
+    fn reimport_skips_tickets_moved_to_another_project() {
+        let _guard = scratch_project("import-moved");
+        let to = TicketTransfer{ project: Some("ops".to_string()), id: 7, key: "OPS-7".to_string(), transferred_at: Utc::now() };
+        write_changes(&[Tickets{ id: 1, ..imported("github:o/r#1", "Printer") }.tombstone(to)]).unwrap();
+
+        let tickets = vec![imported("github:o/r#1", "Printer jam")];
+        assert_eq!(import_targets(&read_db().unwrap(), &tickets), vec![ImportTarget::Skip(1)]);
+        let result = store_imported(&LocalStorage, tickets).unwrap();
+        assert_eq!((result.created, result.updated, result.skipped), (vec![], vec![], vec![1]));
+        assert_eq!(read_db().unwrap()[0].title, "Printer");
+    }
+}
@@ -3,10 +3,27 @@ mod db;
 mod app;
 mod ui;
 mod ticket;
+mod sprint;
+mod report;
 mod theme;
+mod worklog;
+mod field;
+mod checklist;
+mod attachment;
+mod storage;
+mod cli;
+mod csvio;
+mod document;
+mod importer;
+mod backup;
+mod project;
 
 use app::*;
 use ticket::*;
+use clap::Parser;
+use cli::*;
+use db::*;
+use scrum_lib::{SCHEMA_VERSION, valid_project_name};
 
 enum Event<I> {
     Input(I),
@@ -14,13 +31,56 @@ enum Event<I> {
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+
+    //Commands for scripts run without the TUI
+    let cli = Cli::parse();
+
+    //A registered project is used instead of the working directory, the server looks the name up itself
+    if let Some(name) = &cli.project {
+        let opened = if cli.remote {
+            if valid_project_name(name) {Ok(())} else {Err(Error::InvalidProject(format!("\"{}\" is not a project name", name)))}
+        } else {
+            find_project(name).and_then(|project| set_project(Some(project)))
+        };
+        if let Err(error) = opened {
+            eprintln!("scrum: {}", error);
+            std::process::exit(CliError::from(error).exit_code());
+        }
+    }
+
+    //Older database files are upgraded before anything reads them, newer ones are not touched
+    let upgraded = if cli.remote {None} else {
+        match upgrade_db() {
+            Ok(backup) => backup,
+            Err(error) => {
+                eprintln!("scrum: {}", error);
+                std::process::exit(EXIT_STORAGE);
+            }
+        }
+    };
+    if let Some(command) = cli.command {
+        if let Some(backup) = &upgraded {
+            eprintln!("Upgraded the database to schema version {}, the old file is {}", SCHEMA_VERSION, backup.display());
+        }
+        let remote = if cli.remote {Some(cli.server)} else {None};
+        std::process::exit(run_command(command, remote, cli.project));
+    }
     
     //Initialize AppState
     let mut app = AppState::default();
+    if let Some(backup) = &upgraded {
+        add_notice(&mut app, format!("Upgraded the database to schema version {}, the old file is {}", SCHEMA_VERSION, backup.display()));
+    }
     //Initialize DB
     update_ticket_count(&mut app);
-    //Initialize theme
-    let theme = theme::Theme::default();
+    //Keep a snapshot of how the tickets were before this session
+    if let Err(error) = create_snapshot() {
+        add_notice(&mut app, format!("No snapshot was taken: {}", error));
+    }
+    //Create the recurring tickets that are due since the last start
+    if let Err(error) = create_recurring_tickets(&mut app) {
+        add_notice(&mut app, format!("Recurring tickets were not created: {}", error));
+    }
     //Run the app
     run(&mut app).unwrap();
 
@@ -0,0 +1,203 @@
+//For keeping customers apart: the registry of projects and switching between them in the TUI
+
+use std::path::Path;
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+use crate::attachment::*;
+
+//Lists the registered projects, the one in use is selected
+pub fn init_projects(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    app.projects = read_projects()?;
+    //Index 0 is the database in the working directory
+    let current = current_project().and_then(|current| app.projects.iter().position(|project| project.name == current.name));
+    app.project_list_state.select(Some(current.map(|index| index + 1).unwrap_or(0)));
+    app.active_menu_item = MenuItem::Projects;
+    Ok(())
+}
+
+fn selected_project(app: &AppState) -> Option<Project> {
+    app.project_list_state.selected().and_then(|selected| selected.checked_sub(1)).and_then(|index| app.projects.get(index)).cloned()
+}
+
+pub fn switch_project(app: &mut AppState) -> Result<(), Error> {
+    open_project(app, selected_project(app))?;
+    Ok(())
+}
+
+//Reloads everything from the files of the project, a database the app cannot read is not opened
+pub fn open_project(app: &mut AppState, project: Option<Project>) -> Result<bool, Error> {
+    let previous = current_project();
+    set_project(project.clone())?;
+    let upgraded = match upgrade_db() {
+        Ok(upgraded) => upgraded,
+        Err(error) => {
+            set_project(previous)?;
+            app.notice = format!("Cannot open {}: {}", project_name(project.as_ref()), error);
+            return Ok(false);
+        }
+    };
+
+    *app = AppState::default();
+    update_ticket_count(app);
+    app.ticket_list_state.select(Some(0));
+    let opened = match upgraded {
+        Some(backup) => format!("Opened {}, the database was upgraded and the old file is {}", project_name(project.as_ref()), backup.display()),
+        None => format!("Opened {}", project_name(project.as_ref())),
+    };
+    add_notice(app, opened);
+    if let Err(error) = create_snapshot() {
+        add_notice(app, format!("No snapshot was taken: {}", error));
+    }
+    if let Err(error) = create_recurring_tickets(app) {
+        add_notice(app, format!("Recurring tickets were not created: {}", error));
+    }
+    app.due_banner = due_banner(app);
+    Ok(true)
+}
+
+pub fn project_name(project: Option<&Project>) -> String {
+    match project {
+        Some(project) => project.name.clone(),
+        None => "the working directory".to_string(),
+    }
+}
+
+pub fn init_project_form(app: &mut AppState) -> Result<(), Error> {
+    app.input = String::new();
+    app.messages = Vec::new();
+    app.prompt = "Name and directory of the new project, such as \"ops ~/tickets/ops\" (Enter to add)".to_string();
+    app.active_menu_item = MenuItem::ProjectForm;
+    Ok(())
+}
+
+pub fn register_project(app: &mut AppState) -> Result<(), Error> {
+    let (name, path) = match app.input.trim().split_once(' ') {
+        Some((name, path)) => (name.to_string(), expand_path(path.trim())),
+        None => {
+            app.messages.push("Type a name and a directory separated by a space".to_string());
+            return Ok(());
+        }
+    };
+    match add_project(&name, Path::new(&path)) {
+        Ok(project) => {
+            init_projects(app)?;
+            app.project_list_state.select(Some(app.projects.len()));
+            app.notice = format!("Added {} at {}", project.name, project.path.display());
+            app.input = String::new();
+        }
+        Err(error) => app.messages.push(error.to_string()),
+    }
+    Ok(())
+}
+
+pub fn init_prefix_form(app: &mut AppState) -> Result<(), Error> {
+    app.input = app.settings.key_prefix.clone();
+    app.messages = Vec::new();
+    app.prompt = format!("Key prefix of {}, such as OPS for OPS-42, empty for plain numbers (Enter to save)", project_name(current_project().as_ref()));
+    app.active_menu_item = MenuItem::PrefixForm;
+    Ok(())
+}
+
+//Tickets keep their ids, only the keys they are shown with change
+pub fn save_prefix(app: &mut AppState) -> Result<(), Error> {
+    let mut settings = app.settings.clone();
+    if let Err(error) = settings.set_key_prefix(&app.input) {
+        app.messages.push(error);
+        return Ok(());
+    }
+    if let Err(error) = write_settings(&settings) {
+        app.messages.push(error.to_string());
+        return Ok(());
+    }
+    app.settings = settings;
+    app.notice = if app.settings.key_prefix.is_empty() {
+        "Tickets are shown by number".to_string()
+    } else {
+        format!("Tickets are shown as {}", app.settings.ticket_key(1))
+    };
+    app.input = String::new();
+    app.active_menu_item = MenuItem::Projects;
+    Ok(())
+}
+
+//Lists the other projects the selected ticket can be moved or copied to
+pub fn init_transfer(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    let ticket = match selected_ticket(app) {
+        Some(ticket) => ticket,
+        None => return Ok(()),
+    };
+    if moved_notice(app, &ticket) {
+        return Ok(());
+    }
+    let current = current_project();
+    app.transfer_targets = std::iter::once(None)
+        .chain(read_projects()?.into_iter().map(Some))
+        .filter(|target| *target != current)
+        .collect();
+    if app.transfer_targets.is_empty() {
+        app.notice = "There is no other project yet, add one with p and then n".to_string();
+        return Ok(());
+    }
+    app.transfer_list_state.select(Some(0));
+    app.active_menu_item = MenuItem::TransferPicker;
+    Ok(())
+}
+
+//Moving leaves a closed tombstone behind, copying leaves the ticket as it is
+pub fn transfer_selected_ticket(app: &mut AppState, keep: bool) -> Result<(), Error> {
+    let target = app.transfer_list_state.selected().and_then(|selected| app.transfer_targets.get(selected)).cloned();
+    if let (Some(ticket), Some(target)) = (selected_ticket(app), target) {
+        let key = app.settings.ticket_key(ticket.id);
+        match transfer_ticket(ticket.id, target, keep) {
+            Ok(to) => {
+                update_ticket_count(app);
+                //The tombstone is closed, so a moved ticket leaves the open tickets
+                if let (false, Some(selected)) = (keep, app.ticket_list_state.selected()) {
+                    update_selected_ticket(app, selected);
+                }
+                app.notice = format!("{} {} to {}", if keep {"Copied"} else {"Moved"}, key, to.describe());
+            }
+            Err(error) => app.notice = format!("Cannot {} {}: {}", if keep {"copy"} else {"move"}, key, error),
+        }
+    }
+    app.active_menu_item = MenuItem::Tickets;
+    Ok(())
+}
+
+//Opens the project a moved ticket went to and selects it there
+pub fn follow_moved_ticket(app: &mut AppState) -> Result<(), Error> {
+    let moved = match selected_ticket(app).and_then(|ticket| ticket.moved_to) {
+        Some(moved) => moved,
+        None => return Ok(()),
+    };
+    let project = match transfer_project(&moved.project) {
+        Ok(project) => project,
+        Err(error) => {
+            app.notice = format!("Cannot open {}: {}", moved.describe(), error);
+            return Ok(());
+        }
+    };
+    if open_project(app, project)? && !select_ticket(app, moved.id) {
+        app.notice = format!("{} no longer exists", moved.describe());
+    }
+    Ok(())
+}
+
+//Only the registry entry is removed, the project in use cannot be removed
+pub fn unregister_project(app: &mut AppState) -> Result<(), Error> {
+    if let Some(project) = selected_project(app) {
+        if current_project().as_ref() == Some(&project) {
+            app.notice = format!("{} is in use, switch to another project first", project.name);
+            return Ok(());
+        }
+        remove_project(&project.name)?;
+        app.projects = read_projects()?;
+        clamp_list_state(&mut app.project_list_state, app.projects.len() + 1);
+        app.notice = format!("Removed {}, the files in {} were kept", project.name, project.path.display());
+    }
+    Ok(())
+}
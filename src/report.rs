@@ -0,0 +1,148 @@
+//For computing the charts on the Reports screen, all from the local ticket store
+
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use scrum_lib::*;
+use crate::app::*;
+use crate::ticket::*;
+
+pub const THROUGHPUT_WEEKS: i64 = 12;
+
+pub struct Burndown {
+    pub sprint: Option<Sprint>,
+    pub remaining: Vec<(f64, f64)>,
+    pub ideal: Vec<(f64, f64)>,
+    pub days: f64,
+    pub total: f64,
+    //Ticket counts are used when nothing in the sprint is estimated
+    pub estimated: bool,
+}
+
+pub struct Throughput {
+    pub opened: Vec<u64>,
+    pub closed: Vec<u64>,
+}
+
+//Minutes logged per user on each day of one week, Monday first
+pub struct Timesheet {
+    pub week_start: NaiveDate,
+    pub rows: Vec<(String, [i64; 7])>,
+}
+
+//Sprint shown by default: the current one, otherwise the latest one
+pub fn default_report_sprint(app: &AppState) -> usize {
+    let today = Local::now().naive_local().date();
+    let current = Sprint::current(&app.sprints, today).map(|sprint| sprint.id);
+    app.sprints
+        .iter()
+        .position(|sprint| Some(sprint.id) == current)
+        .unwrap_or_else(|| app.sprints.len().saturating_sub(1))
+}
+
+pub fn burndown(app: &AppState) -> Burndown {
+    let sprint = match app.sprints.get(app.report_sprint) {
+        Some(sprint) => sprint.clone(),
+        None => return Burndown { sprint: None, remaining: Vec::new(), ideal: Vec::new(), days: 1.0, total: 1.0, estimated: false },
+    };
+
+    let tickets: Vec<Tickets> = all_tickets(app)
+        .into_iter()
+        .filter(|ticket| ticket.sprint == Some(sprint.id))
+        .collect();
+    let estimated = tickets.iter().any(|ticket| ticket.estimate.is_some());
+    let weight = |ticket: &Tickets| if estimated {ticket.estimate.unwrap_or(0.0)} else {1.0};
+    let total: f64 = tickets.iter().map(weight).fold(0.0, |total, weight| total + weight);
+
+    let days = (sprint.end_date - sprint.start_date).num_days();
+    let today = Local::now().naive_local().date();
+    let mut remaining = Vec::new();
+    for day in 0..=days {
+        let date = sprint.start_date + Duration::days(day);
+        if date > today {
+            break;
+        }
+        //Work left at the end of the day
+        let end_of_day = end_of_day(date);
+        let left = tickets
+            .iter()
+            .filter(|ticket| ticket.status_at(end_of_day) != Some(TicketStatus::Closed))
+            .map(weight)
+            .fold(0.0, |total, weight| total + weight);
+        remaining.push((day as f64, left));
+    }
+
+    Burndown {
+        sprint: Some(sprint),
+        remaining,
+        ideal: vec![(0.0, total), (days as f64, 0.0)],
+        days: (days as f64).max(1.0),
+        total: total.max(1.0),
+        estimated,
+    }
+}
+
+//Tickets opened and closed per week, oldest week first
+pub fn throughput(app: &AppState) -> Throughput {
+    let today = Local::now().naive_local().date();
+    let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let tickets = all_tickets(app);
+
+    let mut opened = Vec::new();
+    let mut closed = Vec::new();
+    for week in (0..THROUGHPUT_WEEKS).rev() {
+        let start = this_week - Duration::weeks(week);
+        let end = start + Duration::weeks(1);
+        let in_week = |date: NaiveDate| date >= start && date < end;
+
+        opened.push(tickets.iter().filter(|ticket| in_week(ticket.created_at.with_timezone(&Local).naive_local().date())).count() as u64);
+        closed.push(tickets.iter().filter_map(|ticket| ticket.closed_at()).filter(|closed_at| in_week(closed_at.with_timezone(&Local).naive_local().date())).count() as u64);
+    }
+
+    Throughput { opened, closed }
+}
+
+//Logged work per user for the week chosen with app.timesheet_week (0 is this week)
+pub fn timesheet(app: &AppState) -> Timesheet {
+    let today = Local::now().naive_local().date();
+    let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64) - Duration::weeks(app.timesheet_week);
+
+    let mut rows: Vec<(String, [i64; 7])> = Vec::new();
+    for ticket in all_tickets(app) {
+        for entry in ticket.worklog {
+            //Work counts on the day it was started
+            let day = (entry.started_at.with_timezone(&Local).naive_local().date() - week_start).num_days();
+            if !(0..7).contains(&day) {
+                continue;
+            }
+            match rows.iter_mut().find(|(user, _)| *user == entry.user) {
+                Some((_, days)) => days[day as usize] += entry.minutes,
+                None => {
+                    let mut days = [0; 7];
+                    days[day as usize] = entry.minutes;
+                    rows.push((entry.user.clone(), days));
+                }
+            }
+        }
+    }
+    rows.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Timesheet { week_start, rows }
+}
+
+//Open tickets per priority
+pub fn priority_breakdown(app: &AppState) -> Vec<(&str, u64)> {
+    app.settings.priorities
+        .iter()
+        .map(|priority| {
+            let count = app.open_tickets.iter().filter(|ticket| ticket.priority == *priority).count();
+            (priority.as_str(), count as u64)
+        })
+        .collect()
+}
+
+fn end_of_day(date: NaiveDate) -> chrono::DateTime<Utc> {
+    let end = date.and_hms_opt(23, 59, 59).unwrap();
+    match Local.from_local_datetime(&end).earliest() {
+        Some(local) => local.with_timezone(&Utc),
+        None => Utc.from_utc_datetime(&end),
+    }
+}
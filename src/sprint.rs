@@ -0,0 +1,169 @@
+//For functions related to handling sprints
+
+use chrono::{Duration, Local, NaiveDate, Utc};
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+pub fn init_add_sprint(app: &mut AppState) -> Result<(), Error> {
+
+    //New sprints start the day after the last planned one ends
+    let start = app.sprints
+        .iter()
+        .map(|sprint| sprint.end_date + Duration::days(1))
+        .max()
+        .unwrap_or_else(|| Local::now().naive_local().date());
+    let end = start + Duration::days(13);
+
+    //To indicate that it is a new sprint (referenced in add_sprint)
+    app.edit_sprint = Sprint::new(-7, String::new(), String::new(), start, end);
+    app.sprint_start_input = start.format(DATE_FORMAT).to_string();
+    app.sprint_end_input = end.format(DATE_FORMAT).to_string();
+    app.sprint_edit_focus = SprintItem::Name;
+
+    app.prompt = String::new();
+    app.active_menu_item = MenuItem::SprintForm;
+    Ok(())
+}
+
+pub fn add_sprint(app: &mut AppState) -> Result<(), Error> {
+    let start = NaiveDate::parse_from_str(app.sprint_start_input.trim(), DATE_FORMAT);
+    let end = NaiveDate::parse_from_str(app.sprint_end_input.trim(), DATE_FORMAT);
+
+    //Show what is wrong in the form instead of saving
+    let (start, end) = match (start, end) {
+        _ if app.edit_sprint.name.trim().is_empty() => {
+            app.prompt = "Sprint needs a name".to_string();
+            return Ok(());
+        }
+        (Ok(start), Ok(end)) if start <= end => (start, end),
+        (Ok(_), Ok(_)) => {
+            app.prompt = "End date must not be before start date".to_string();
+            return Ok(());
+        }
+        _ => {
+            app.prompt = "Dates must be in YYYY-MM-DD format".to_string();
+            return Ok(());
+        }
+    };
+
+    let max_id = app.sprints.iter().map(|sprint| sprint.id).max().unwrap_or(0);
+    app.edit_sprint.id = max_id + 1;
+    app.edit_sprint.start_date = start;
+    app.edit_sprint.end_date = end;
+    let mut sprints = app.sprints.clone();
+    sprints.push(app.edit_sprint.clone());
+    //The form stays open with the reason when the sprints cannot be saved
+    if let Err(error) = write_sprints(&sprints) {
+        app.prompt = format!("Sprint was not saved: {}", error);
+        return Ok(());
+    }
+    app.sprints = sprints;
+
+    app.sprint_list_state.select(Some(app.sprints.len() - 1));
+    app.sprint_ticket_state.select(None);
+    app.prompt = String::new();
+    app.active_menu_item = MenuItem::Sprints;
+    Ok(())
+}
+
+pub fn selected_sprint(app: &AppState) -> Option<Sprint> {
+    let selected = app.sprint_list_state.selected()?;
+    app.sprints.get(selected).cloned()
+}
+
+//Open tickets that have not been planned into a sprint
+pub fn backlog_tickets(app: &AppState) -> Vec<Tickets> {
+    app.open_tickets
+        .iter()
+        .filter(|ticket| ticket.sprint.is_none())
+        .cloned()
+        .collect()
+}
+
+//Open and closed tickets planned into the selected sprint
+pub fn sprint_tickets(app: &AppState) -> Vec<Tickets> {
+    match selected_sprint(app) {
+        Some(sprint) => app.open_tickets
+            .iter()
+            .chain(app.closed_tickets.iter())
+            .filter(|ticket| ticket.sprint == Some(sprint.id))
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn move_to_sprint(app: &mut AppState) -> Result<(), Error> {
+    if let Some(sprint) = selected_sprint(app) {
+        if sprint.closed {
+            return Ok(());
+        }
+        if let Some(selected) = app.backlog_state.selected() {
+            if let Some(ticket) = backlog_tickets(app).get(selected) {
+                set_ticket_sprint(app, ticket.id, Some(sprint.id))?;
+                let amount_tickets = backlog_tickets(app).len();
+                clamp_list_state(&mut app.backlog_state, amount_tickets);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn move_to_backlog(app: &mut AppState) -> Result<(), Error> {
+    if let Some(selected) = app.sprint_ticket_state.selected() {
+        if let Some(ticket) = sprint_tickets(app).get(selected) {
+            set_ticket_sprint(app, ticket.id, None)?;
+            let amount_tickets = sprint_tickets(app).len();
+            clamp_list_state(&mut app.sprint_ticket_state, amount_tickets);
+        }
+    }
+    Ok(())
+}
+
+fn set_ticket_sprint(app: &mut AppState, id: i32, sprint: Option<i32>) -> Result<(), Error> {
+    for ticket in app.open_tickets.iter_mut().chain(app.closed_tickets.iter_mut()) {
+        if ticket.id == id {
+            ticket.sprint = sprint;
+            ticket.updated_at = Utc::now();
+        }
+    }
+
+    update_db(app);
+    update_ticket_count(app);
+    Ok(())
+}
+
+//Closes the selected sprint and rolls its unfinished tickets into the next one (or the backlog)
+pub fn close_sprint(app: &mut AppState) -> Result<(), Error> {
+    if let Some(sprint) = selected_sprint(app) {
+        if sprint.closed {
+            return Ok(());
+        }
+        let next = sprint.next(&app.sprints).map(|next| next.id);
+
+        //The sprint is saved as closed first, the tickets only roll over once that worked
+        let mut sprints = app.sprints.clone();
+        for existing in sprints.iter_mut() {
+            if existing.id == sprint.id {
+                existing.closed = true;
+            }
+        }
+        write_sprints(&sprints)?;
+        app.sprints = sprints;
+
+        for ticket in app.open_tickets.iter_mut() {
+            if ticket.sprint == Some(sprint.id) {
+                ticket.sprint = next;
+                ticket.updated_at = Utc::now();
+            }
+        }
+        update_db(app);
+        update_ticket_count(app);
+        app.sprint_ticket_state.select(None);
+    }
+    Ok(())
+}
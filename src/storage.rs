@@ -0,0 +1,241 @@
+//Where tickets are kept: the local database file or scrum-server
+
+use std::{fs, path::Path};
+use chrono::Utc;
+use scrum_lib::*;
+use crate::db::*;
+use crate::client::*;
+use crate::worklog::current_user;
+
+pub trait Storage {
+    fn tickets(&self) -> Result<Vec<Tickets>, Error>;
+    //Gives the ticket a new id and returns it as stored
+    fn create(&self, ticket: Tickets) -> Result<Tickets, Error>;
+    fn update(&self, ticket: Tickets) -> Result<(), Error>;
+    fn delete(&self, id: i32) -> Result<(), Error>;
+    //Stores the file and adds it to the ticket, attaching the same file again only updates its metadata
+    fn attach(&self, id: i32, file: &Path) -> Result<Attachment, Error>;
+    //Content of the attachment with the hash
+    fn attachment(&self, hash: &str) -> Result<Vec<u8>, Error>;
+    //Key prefix, priorities and workflow of the project the tickets belong to
+    fn settings(&self) -> Result<DbSettings, Error>;
+
+    fn find(&self, id: i32) -> Result<Tickets, Error> {
+        self.tickets()?
+            .into_iter()
+            .find(|ticket| ticket.id == id)
+            .ok_or(Error::NotFound(id))
+    }
+}
+
+//The database file the TUI uses
+pub struct LocalStorage;
+
+//scrum-server at the given address, with the project to use there
+pub struct RemoteStorage {
+    pub address: String,
+    pub project: Option<String>,
+}
+
+//Remote when a server address is given, locally the project has already been opened with set_project
+pub fn open_storage(remote: Option<String>, project: Option<String>) -> Box<dyn Storage> {
+    match remote {
+        Some(address) => Box::new(RemoteStorage{ address, project }),
+        None => Box::new(LocalStorage),
+    }
+}
+
+impl Storage for LocalStorage {
+    fn tickets(&self) -> Result<Vec<Tickets>, Error> {
+        read_db()
+    }
+    fn create(&self, mut ticket: Tickets) -> Result<Tickets, Error> {
+        let mut db = read_ticket_db()?;
+        let mut settings = read_settings()?;
+        //Tags are registered so they get a color and show up in the tag filter
+        if db.create_ticket(&mut ticket, &mut settings) {
+            write_settings(&settings)?;
+        }
+        write_changes(&db.tickets)?;
+        Ok(ticket)
+    }
+    fn update(&self, mut ticket: Tickets) -> Result<(), Error> {
+        let mut tickets = read_db()?;
+        let id = ticket.id;
+        ticket.updated_at = Utc::now();
+        match tickets.iter_mut().find(|stored| stored.id == id) {
+            Some(Tickets{ moved_to: Some(moved), .. }) => return Err(Error::Moved(id, moved.clone())),
+            Some(stored) => *stored = ticket,
+            None => return Err(Error::NotFound(id)),
+        }
+        sync_links(id, &mut tickets);
+        write_changes(&tickets)?;
+        Ok(())
+    }
+    fn delete(&self, id: i32) -> Result<(), Error> {
+        let mut tickets = read_db()?;
+        match tickets.iter().find(|ticket| ticket.id == id) {
+            Some(Tickets{ moved_to: Some(moved), .. }) => return Err(Error::Moved(id, moved.clone())),
+            Some(_) => {}
+            None => return Err(Error::NotFound(id)),
+        }
+        tickets.retain(|ticket| ticket.id != id);
+        sync_links(id, &mut tickets);
+        write_changes(&tickets)?;
+        Ok(())
+    }
+    fn attach(&self, id: i32, file: &Path) -> Result<Attachment, Error> {
+        let mut ticket = self.find(id)?;
+        let attachment = store_attachment(file, &current_user())?;
+        ticket.attachments.retain(|existing| existing.hash != attachment.hash || existing.name != attachment.name);
+        ticket.attachments.push(attachment.clone());
+        self.update(ticket)?;
+        Ok(attachment)
+    }
+    fn attachment(&self, hash: &str) -> Result<Vec<u8>, Error> {
+        Ok(fs::read(attachment_path(hash))?)
+    }
+    fn settings(&self) -> Result<DbSettings, Error> {
+        read_settings()
+    }
+}
+
+impl RemoteStorage {
+    fn send(&self, action: TicketAction, ticket: Tickets) -> Result<serde_json::Value, Error> {
+        exchange(&self.address, Request{ action, ticket, chunk: None, project: self.project.clone() }).map_err(Error::RemoteError)
+    }
+}
+
+impl Storage for RemoteStorage {
+    fn tickets(&self) -> Result<Vec<Tickets>, Error> {
+        let answer = self.send(TicketAction::UpdateDb, Tickets::default())?;
+        Ok(serde_json::from_value(answer)?)
+    }
+    fn create(&self, ticket: Tickets) -> Result<Tickets, Error> {
+        let answer = self.send(TicketAction::Create, ticket)?;
+        Ok(serde_json::from_value(answer)?)
+    }
+    fn update(&self, mut ticket: Tickets) -> Result<(), Error> {
+        ticket.updated_at = Utc::now();
+        self.send(TicketAction::Update, ticket)?;
+        Ok(())
+    }
+    fn delete(&self, id: i32) -> Result<(), Error> {
+        let ticket = Tickets{ id, ..Tickets::default() };
+        self.send(TicketAction::Delete, ticket)?;
+        Ok(())
+    }
+    //The server adds the metadata to the ticket once the last chunk has arrived
+    fn attach(&self, id: i32, file: &Path) -> Result<Attachment, Error> {
+        let mut ticket = self.find(id)?;
+        let data = fs::read(file)?;
+        let hash = attachment_hash(&data);
+        let attachment = Attachment{
+            name: file.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| hash.clone()),
+            size: data.len() as u64,
+            hash,
+            added_by: current_user(),
+            added_at: Utc::now(),
+        };
+        ticket.attachments.push(attachment.clone());
+        upload_attachment(&self.address, self.project.clone(), ticket, data).map_err(Error::RemoteError)?;
+        Ok(attachment)
+    }
+    fn attachment(&self, hash: &str) -> Result<Vec<u8>, Error> {
+        download_attachment(&self.address, self.project.clone(), hash.to_string()).map_err(Error::RemoteError)
+    }
+    fn settings(&self) -> Result<DbSettings, Error> {
+        let answer = self.send(TicketAction::Settings, Tickets::default())?;
+        Ok(serde_json::from_value(answer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(id: i32, title: &str) -> Tickets {
+        Tickets{ id, title: title.to_string(), priority: "Low".to_string(), ..Tickets::default() }
+    }
+
+    fn tombstone(id: i32) -> Tickets {
+        let to = TicketTransfer{ project: Some("ops".to_string()), id: 7, key: "OPS-7".to_string(), transferred_at: Utc::now() };
+        ticket(id, "Backup").tombstone(to)
+    }
+
+    //Answers one connection per answer the way scrum-server does, frames are a 4 byte length and the JSON.
+    //Returns the address and the requests that were received
+    fn fake_server(answers: Vec<serde_json::Value>) -> (String, std::thread::JoinHandle<Vec<Request>>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for answer in answers {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut length = [0; 4];
+                stream.read_exact(&mut length).unwrap();
+                let mut request = vec![0; u32::from_be_bytes(length) as usize];
+                stream.read_exact(&mut request).unwrap();
+                requests.push(serde_json::from_slice(&request).unwrap());
+                let answer = answer.to_string();
+                stream.write_all(&(answer.len() as u32).to_be_bytes()).unwrap();
+                stream.write_all(answer.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (address, server)
+    }
+
+    #[test]
+    fn created_tickets_get_the_next_id_registered_tags_and_mirrored_links() {
+        let _guard = scratch_project("storage-create");
+        write_changes(&[ticket(1, "Backup"), ticket(4, "Printer")]).unwrap();
+
+        let mut new = ticket(0, "Zabbix Setup");
+        new.tags.insert("ops".to_string());
+        new.links.push(TicketLink{ kind: LinkKind::Blocks, target: 1 });
+        let created = LocalStorage.create(new).unwrap();
+        assert_eq!(created.id, 5);
+        assert!(read_settings().unwrap().tags.iter().any(|tag| tag.name == "ops"));
+        let backup = LocalStorage.find(1).unwrap();
+        assert_eq!(backup.links, vec![TicketLink{ kind: LinkKind::BlockedBy, target: 5 }]);
+
+        LocalStorage.update(Tickets{ links: Vec::new(), ..created }).unwrap();
+        assert!(LocalStorage.find(1).unwrap().links.is_empty());
+        assert!(matches!(LocalStorage.delete(9), Err(Error::NotFound(9))));
+        assert!(matches!(LocalStorage.update(ticket(9, "Standup")), Err(Error::NotFound(9))));
+    }
+
+    #[test]
+    fn remote_storage_sends_the_project_and_reads_the_answers() {
+        let settings = DbSettings{ key_prefix: "OPS".to_string(), ..DbSettings::default() };
+        let (address, server) = fake_server(vec![
+            serde_json::to_value(vec![ticket(1, "Backup")]).unwrap(),
+            serde_json::to_value(&settings).unwrap(),
+            serde_json::json!({ "error": "ticket 3 does not exist" }),
+        ]);
+        let storage = RemoteStorage{ address, project: Some("ops".to_string()) };
+
+        assert_eq!(storage.tickets().unwrap()[0].title, "Backup");
+        assert_eq!(storage.settings().unwrap().key_prefix, "OPS");
+        assert!(matches!(storage.delete(3), Err(Error::RemoteError(message)) if message == "ticket 3 does not exist"));
+
+        let requests = server.join().unwrap();
+        let actions: Vec<&str> = requests.iter().map(|request| request.action.to_string()).collect();
+        assert_eq!(actions, vec!["UpdateDb", "Settings", "Delete"]);
+        assert!(requests.iter().all(|request| request.project.as_deref() == Some("ops")));
+        assert_eq!(requests[2].ticket.id, 3);
+    }
+
+    #[test]
+    fn tombstones_cannot_be_updated_or_deleted() {
+        let _guard = scratch_project("storage-tombstone");
+        write_changes(&[tombstone(1)]).unwrap();
+
+        let changed = Tickets{ description: "Changed".to_string(), ..tombstone(1) };
+        assert!(matches!(LocalStorage.update(changed), Err(Error::Moved(1, _))));
+        assert!(matches!(LocalStorage.delete(1), Err(Error::Moved(1, _))));
+        assert_eq!(read_db().unwrap()[0].description, "");
+    }
+}
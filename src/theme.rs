@@ -3,19 +3,37 @@
 use tui::style::Color;
 
 pub struct Theme {
+    #[allow(dead_code)]
     pub background: Color,
     pub text: Color,
     pub selection: Color,
     pub selection_text: Color,
+    //Colors for tag chips, tags store an index into this list
+    pub tags: Vec<Color>,
 }
 
 impl Theme {
+    pub fn tag_color(&self, index: usize) -> Color {
+        self.tags[index % self.tags.len()]
+    }
+}
+
+impl Theme {
+    #[allow(dead_code)]
     pub fn default() -> Self {
         Theme {
             background: Color::Rgb(0x0B, 0x0E, 0x14),
             text: Color::Rgb(0xBF, 0xBD, 0xB6),
             selection: Color::Rgb(0xE6, 0xB4, 0x50),
             selection_text: Color::Rgb(0x0B, 0x0E, 0x14),
+            tags: vec![
+                Color::Rgb(0xF0, 0x71, 0x78),
+                Color::Rgb(0xAA, 0xD9, 0x4C),
+                Color::Rgb(0x59, 0xC2, 0xFF),
+                Color::Rgb(0xD2, 0xA6, 0xFF),
+                Color::Rgb(0x95, 0xE6, 0xCB),
+                Color::Rgb(0xFF, 0x8F, 0x40),
+            ],
         }
     }
     pub fn gruvbox() -> Self {
@@ -24,6 +42,14 @@ impl Theme {
             text: Color::Rgb(0xF8, 0xF2, 0xD9),
             selection: Color::Rgb(0xE6, 0xB4, 0x50),
             selection_text: Color::Rgb(0x28, 0x28, 0x28),
+            tags: vec![
+                Color::Rgb(0xFB, 0x49, 0x34),
+                Color::Rgb(0xB8, 0xBB, 0x26),
+                Color::Rgb(0x83, 0xA5, 0x98),
+                Color::Rgb(0xD3, 0x86, 0x9B),
+                Color::Rgb(0x8E, 0xC0, 0x7C),
+                Color::Rgb(0xFE, 0x80, 0x19),
+            ],
         }
     }
 
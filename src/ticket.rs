@@ -1,88 +1,157 @@
 //For functions related to handling tickets
 
-use chrono::Utc;
+use chrono::{Local, Utc};
 use scrum_lib::*;
-use crate::{db::*, Event};
+use crate::db::*;
 use crate::ui::*;
 use crate::app::*;
+use crate::field::*;
+use crate::worklog::*;
+use tui::widgets::ListState;
 
 pub fn init_add_ticket(app: &mut AppState) -> Result<(), Error> {
 
     //To indicate that it is a new ticket (referenced in add_ticket)
+    app.edit_ticket = Tickets::default();
     app.edit_ticket.id = -7;
     app.edit_ticket.status = TicketStatus::Open;
     app.edit_ticket.title = String::new();
     app.edit_ticket.description = String::new();
-    app.edit_ticket.priority = "Low".to_string();
-
-    app.edit_priority_state.select(Some(0)); //Can be fixed to match set priority above
+    app.edit_ticket.priority = app.settings.lowest_priority();
+    app.estimate_input = String::new();
+    app.parent_input = String::new();
+    app.tag_input = String::new();
+    app.due_input = String::new();
+    app.notice = String::new();
+
+    app.edit_priority_state.select(Some(app.settings.priorities.len().saturating_sub(1)));
     app.edit_status_state.select(Some(0));
+    app.edit_kind_state.select(Some(0));
+    init_field_inputs(app);
+    app.checklist_input = String::new();
+    app.checklist_state.select(None);
 
     app.prompt = "Enter Title".to_string();
+
+    //Offer the templates first, the first entry of the picker is a blank ticket
+    app.templates = read_templates().unwrap_or_else(|_| {
+        app.notice = "Templates file could not be read, using a blank ticket".to_string();
+        Vec::new()
+    });
+    if app.templates.is_empty() {
+        app.active_menu_item = MenuItem::EditForm;
+    } else {
+        app.template_list_state.select(Some(0));
+        app.active_menu_item = MenuItem::TemplatePicker;
+    }
+    Ok(())
+}
+
+//Prefills the new ticket from the picked template and opens the edit form
+pub fn apply_template(app: &mut AppState) -> Result<(), Error> {
+    let selected = app.template_list_state.selected().unwrap_or(0);
+    //Index 0 is the blank ticket
+    if let Some(template) = selected.checked_sub(1).and_then(|index| app.templates.get(index)).cloned() {
+        let today = Local::now().naive_local().date();
+        app.edit_ticket.title = template.title_for(today, &current_user());
+        app.edit_ticket.description = template.description.clone();
+        app.edit_ticket.checklist = template.checklist.iter().map(|item| ChecklistItem::new(item.clone())).collect();
+        app.checklist_state.select(if app.edit_ticket.checklist.is_empty() {None} else {Some(0)});
+        app.edit_ticket.tags = template.tags.iter().map(|tag| normalize_tag(tag)).collect();
+        //Priorities the project does not have fall back to the lowest one, as when the template is used elsewhere
+        let priority = app.settings.find_priority(&template.priority).unwrap_or_else(|| app.settings.lowest_priority());
+        app.edit_priority_state.select(app.settings.priorities.iter().position(|name| *name == priority));
+    }
+    app.edit_focus = EditItem::Title;
     app.active_menu_item = MenuItem::EditForm;
     Ok(())
 }
 
 pub fn add_ticket (app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
     if let Some(selected) = app.ticket_list_state.selected() {
     //if new
 
-    //This can be fixed by using ticket priority enum with impl fn
-        if app.edit_priority_state.selected() == Some(0) {
-            app.edit_ticket.priority = "High".to_string();
-        } else if app.edit_priority_state.selected() == Some(1) {
-            app.edit_ticket.priority = "Medium".to_string();
-        } else if app.edit_priority_state.selected() == Some(2) {
-            app.edit_ticket.priority = "Low".to_string();
+        if let Some(priority) = app.edit_priority_state.selected().and_then(|selected| app.settings.priorities.get(selected)) {
+            app.edit_ticket.priority = priority.clone();
         }
 
         if app.edit_status_state.selected() == Some(0) {
-            app.edit_ticket.status = TicketStatus::Open;
+            app.edit_ticket.set_status(TicketStatus::Open);
         } else if app.edit_status_state.selected() == Some(1) {
-            app.edit_ticket.status = TicketStatus::Closed;
+            //A parent can only be closed once all of its children are
+            let open_children = open_children(app, app.edit_ticket.id);
+            if open_children > 0 {
+                app.notice = format!("Ticket was not closed, {} child tickets are still open", open_children);
+            } else {
+                app.edit_ticket.set_status(TicketStatus::Closed);
+            }
         }
 
-    if app.edit_ticket.id == -7 {
-        //Generate unique ID
-        let parsed: Vec<Tickets> = read_db().unwrap();
-        let mut max_id = 0;
-        for ticket in parsed.iter() {
-            if ticket.id > max_id {
-                max_id = ticket.id;
+        app.edit_ticket.kind = if app.edit_kind_state.selected() == Some(1) {TicketKind::Epic} else {TicketKind::Task};
+        app.edit_ticket.estimate = app.estimate_input.trim().parse().ok();
+
+        let today = Local::now().naive_local().date();
+        app.edit_ticket.due = parse_due(&app.due_input, today);
+        if app.edit_ticket.due.is_none() && !app.due_input.trim().is_empty() {
+            app.notice = format!("Due date was not set, \"{}\" is not a date", app.due_input.trim());
+        }
+
+        //A tag that was typed but not added yet still counts
+        add_tag_input(app);
+        let mut settings = app.settings.clone();
+        let mut new_tags = false;
+        for tag in app.edit_ticket.tags.iter() {
+            new_tags |= settings.register_tag(tag);
+        }
+        //The ticket is still saved when the new tags cannot be
+        if new_tags {
+            save_settings(app, settings);
+        }
+
+        app.edit_ticket.parent = None;
+        let parent = app.settings.find_ticket_id(&app.parent_input);
+        if parent.is_none() && !app.parent_input.trim().is_empty() {
+            app.notice = format!("Parent was not set, {} is not a ticket key", app.parent_input.trim());
+        }
+        if let Some(parent) = parent {
+            let tickets = all_tickets(app);
+            if !tickets.iter().any(|ticket| ticket.id == parent) {
+                app.notice = format!("Parent was not set, ticket {} does not exist", app.settings.ticket_key(parent));
+            } else if creates_cycle(app.edit_ticket.id, parent, &tickets) {
+                app.notice = format!("Parent was not set, ticket {} is a child of this ticket", app.settings.ticket_key(parent));
+            } else {
+                app.edit_ticket.parent = Some(parent);
             }
         }
-        app.edit_ticket.id = max_id + 1;
+
+    if app.edit_ticket.id == -7 {
+        //Generate unique ID
+        app.edit_ticket.id = next_ticket_id()?;
         app.edit_ticket.created_at = Utc::now();
         app.edit_ticket.updated_at = Utc::now();
 
 
         app.open_tickets.push(app.edit_ticket.clone());
+        sync_ticket_links(app, app.edit_ticket.id);
     } else {
         app.edit_ticket.updated_at = Utc::now();
-
-        //Makes sure to update the right index by checking if on open or close page
-        //very inefficient and requires blocking changing in other menus
-        match app.ticket_view_mode {
-            TicketViewMode::Open => {
-                app.open_tickets[selected] = app.edit_ticket.clone();
-                //If the ticket was changed to closed, reset index just in case it was last in list
-                if app.edit_ticket.status == TicketStatus::Closed {
-                    app.ticket_list_state.select(Some(0));
-                }
-            },
-            TicketViewMode::Closed => {
-                app.closed_tickets[selected] = app.edit_ticket.clone();
-                //If the ticket was changed to open, reset index just in case it was last in list
-                if app.edit_ticket.status == TicketStatus::Open {
-                    app.ticket_list_state.select(Some(0));
-                }
-            },
-        }    
+        replace_ticket(app, app.edit_ticket.clone());
+        sync_ticket_links(app, app.edit_ticket.id);
+
+        //If the ticket was moved out of the current view, reset index just in case it was last in list
+        let moved = match app.ticket_view_mode {
+            TicketViewMode::Open => app.edit_ticket.status == TicketStatus::Closed,
+            TicketViewMode::Closed => app.edit_ticket.status == TicketStatus::Open,
+        };
+        if moved || selected >= visible_tickets(app).len() {
+            app.ticket_list_state.select(Some(0));
+        }
     }
 
 
 
-    update_db(&app);
+    update_db(app);
     update_ticket_count(app);
     render_tickets(app);
 
@@ -97,31 +166,32 @@ pub fn add_ticket (app: &mut AppState) -> Result<(), Error> {
 
 
 pub fn edit_ticket_at_index(app: &mut AppState) -> Result<(), Error> {
-     if let Some(selected) = app.ticket_list_state.selected() {
+     if selected_ticket(app).is_some_and(|ticket| moved_notice(app, &ticket)) {
+        return Ok(());
+     }
+     if app.ticket_list_state.selected().is_some() {
         app.prompt = "Enter Title".to_string();
-        match app.ticket_view_mode {
-            TicketViewMode::Open => {
-                if app.open_tickets.len() != 0 {
-                    app.edit_ticket = app.open_tickets[selected].clone();
-                    app.input = app.edit_ticket.title.to_string();
-                    app.active_menu_item = MenuItem::EditForm;
-                }
-            },
-            TicketViewMode::Closed => {
-                if app.closed_tickets.len() != 0 {
-                    app.edit_ticket = app.closed_tickets[selected].clone();
-                    app.input = app.edit_ticket.title.to_string();
-                    app.active_menu_item = MenuItem::EditForm;
-                }
-            },
+        if let Some(ticket) = selected_ticket(app) {
+            app.edit_ticket = ticket;
+            app.input = app.edit_ticket.title.to_string();
+            app.estimate_input = app.edit_ticket.estimate.map(|estimate| estimate.to_string()).unwrap_or_default();
+            app.parent_input = app.edit_ticket.parent.map(|parent| app.settings.ticket_key(parent)).unwrap_or_default();
+            app.tag_input = String::new();
+            app.due_input = app.edit_ticket.due.map(|due| due.format("%Y-%m-%d").to_string()).unwrap_or_default();
+            app.notice = String::new();
+            init_field_inputs(app);
+            app.checklist_input = String::new();
+            app.checklist_state.select(if app.edit_ticket.checklist.is_empty() {None} else {Some(0)});
+            app.active_menu_item = MenuItem::EditForm;
         }
 
-        app.edit_priority_state.select(
-            if app.edit_ticket.priority == "High" {Some(0)} else if app.edit_ticket.priority == "Medium" {Some(1)} else {Some(2)}  
-          );
+        app.edit_priority_state.select(Some(app.settings.priority_rank(&app.edit_ticket.priority).min(app.settings.priorities.len().saturating_sub(1))));
           app.edit_status_state.select(
             if app.edit_ticket.status.to_string() == "Open" {Some(0)} else {Some(1)}  
           );
+          app.edit_kind_state.select(
+            if app.edit_ticket.kind == TicketKind::Epic {Some(1)} else {Some(0)}
+          );
      }
     
     Ok(())
@@ -131,41 +201,20 @@ pub fn edit_ticket_at_index(app: &mut AppState) -> Result<(), Error> {
 pub fn init_add_note(app: &mut AppState) -> Result<(), Error> {
    //If menus exactly the same, I could set an AppState variable that sets the amount of expected messages to save from having to create different forms
     app.messages = Vec::new();
-    if let Some(selected) = app.ticket_list_state.selected() {
+    if let Some(ticket) = selected_ticket(app).filter(|ticket| !moved_notice(app, ticket)) {
         app.prompt = "Enter Note".to_string();
-        match app.ticket_view_mode {
-            TicketViewMode::Open => {
-                if app.open_tickets.len() != 0 {
-                    app.edit_ticket = app.open_tickets[selected].clone();
-                    app.input = "".to_string();
-                    app.active_menu_item = MenuItem::NoteForm;
-                }
-            },
-            TicketViewMode::Closed => {
-                if app.closed_tickets.len() != 0 {
-                    app.edit_ticket = app.closed_tickets[selected].clone();
-                    app.input = app.edit_ticket.title.to_string();
-                    app.active_menu_item = MenuItem::NoteForm;
-                }
-            },
-        }
-
+        app.edit_ticket = ticket;
+        app.input = "".to_string();
+        app.active_menu_item = MenuItem::NoteForm;
      }
     
     Ok(())
 }
 
 pub fn add_note(app: &mut AppState) -> Result<(), Error> {
-    if let Some(selected) = app.ticket_list_state.selected() {
-    match app.ticket_view_mode {
-        TicketViewMode::Open => {
-            app.open_tickets[selected] = app.edit_ticket.clone();
-        },
-        TicketViewMode::Closed => {
-            app.closed_tickets[selected] = app.edit_ticket.clone();
-        },
-    }  
-    update_db(&app);
+    if app.ticket_list_state.selected().is_some() {
+    replace_ticket(app, app.edit_ticket.clone());
+    update_db(app);
     update_ticket_count(app);
     render_tickets(app);
 
@@ -179,25 +228,21 @@ pub fn add_note(app: &mut AppState) -> Result<(), Error> {
 
 pub fn remove_ticket_at_index(app: &mut AppState) -> Result<(), Error> {
     if let Some(selected) = app.ticket_list_state.selected() {
-       
-        match app.ticket_view_mode {
-            TicketViewMode::Open => {
-                if app.open_tickets.len() != 0 {
-                    app.open_tickets.remove(selected);
-                    update_db(&app);
-                }
-            }
-            TicketViewMode::Closed => {
-                if app.closed_tickets.len() != 0 {
-                app.closed_tickets.remove(selected);
-                update_db(&app);
-                }
-            }
+        //The tombstone stays, it leads to where the ticket is now
+        if selected_ticket(app).is_some_and(|ticket| moved_notice(app, &ticket)) {
+            return Ok(());
+        }
+        if let Some(ticket) = selected_ticket(app) {
+            app.open_tickets.retain(|t| t.id != ticket.id);
+            app.closed_tickets.retain(|t| t.id != ticket.id);
+            //Drop links pointing at the removed ticket
+            sync_ticket_links(app, ticket.id);
+            update_db(app);
         }
         update_selected_ticket(app, selected);
     }
 
-    update_db(&app);
+    update_db(app);
     update_ticket_count(app);
 
     Ok(())
@@ -206,12 +251,7 @@ pub fn remove_ticket_at_index(app: &mut AppState) -> Result<(), Error> {
 
 pub fn update_selected_ticket(app: &mut AppState, selected: usize) {
     //Set new selected ticket
-    let mut amount_tickets = 0;
-    if app.ticket_view_mode == TicketViewMode::Open {
-        amount_tickets = app.open_tickets.len().try_into().unwrap();
-    } else if app.ticket_view_mode == TicketViewMode::Closed {
-        amount_tickets = app.closed_tickets.len().try_into().unwrap();
-    }
+    let amount_tickets = visible_tickets(app).len();
     if amount_tickets == 0 {
         app.ticket_list_state.select(None);
     }
@@ -219,7 +259,106 @@ pub fn update_selected_ticket(app: &mut AppState, selected: usize) {
     if selected > 0 {
         app.ticket_list_state.select(Some(selected - 1));
     } else {
-        app.ticket_list_state.select(Some((0).try_into().unwrap()));
+        app.ticket_list_state.select(Some(0));
+    }
+}
+
+//Tickets shown in the table, depending on the view mode and active filters
+pub fn visible_tickets(app: &AppState) -> Vec<Tickets> {
+    let tickets = match app.ticket_view_mode {
+        TicketViewMode::Open => &app.open_tickets,
+        TicketViewMode::Closed => &app.closed_tickets,
+    };
+    let current_sprint = Sprint::current(&app.sprints, Local::now().naive_local().date()).map(|sprint| sprint.id);
+
+    let tickets: Vec<Tickets> = tickets
+        .iter()
+        .filter(|ticket| !app.filter.current_sprint || (current_sprint.is_some() && ticket.sprint == current_sprint))
+        .filter(|ticket| app.filter.tag.as_ref().is_none_or(|tag| ticket.tags.contains(tag)))
+        .filter(|ticket| matches_field_filter(app, ticket))
+        .cloned()
+        .collect();
+
+    if app.tree_view {
+        tree_order(&tickets)
+    } else {
+        tickets
+    }
+}
+
+//Orders tickets so that children directly follow their parent
+fn tree_order(tickets: &[Tickets]) -> Vec<Tickets> {
+    let mut ordered = Vec::new();
+    let roots = tickets
+        .iter()
+        .filter(|ticket| !ticket.parent.is_some_and(|parent| tickets.iter().any(|other| other.id == parent)));
+    for root in roots {
+        push_subtree(root, tickets, &mut ordered);
+    }
+    ordered
+}
+
+fn push_subtree(ticket: &Tickets, tickets: &[Tickets], ordered: &mut Vec<Tickets>) {
+    ordered.push(ticket.clone());
+    for child in tickets.iter().filter(|child| child.parent == Some(ticket.id)) {
+        push_subtree(child, tickets, ordered);
+    }
+}
+
+//How many of the ticket's ancestors are in the given list, used to indent the tree view
+pub fn tree_depth(ticket: &Tickets, tickets: &[Tickets]) -> usize {
+    let mut depth = 0;
+    let mut current = ticket.parent;
+    while let Some(parent) = current {
+        match tickets.iter().find(|other| other.id == parent) {
+            Some(other) if depth < tickets.len() => {
+                depth += 1;
+                current = other.parent;
+            }
+            _ => break,
+        }
+    }
+    depth
+}
+
+pub fn open_children(app: &AppState, id: i32) -> usize {
+    app.open_tickets.iter().filter(|ticket| ticket.parent == Some(id)).count()
+}
+
+//Selects the ticket in the table, switching between open and closed tickets and clearing filters that hide it
+pub fn select_ticket(app: &mut AppState, id: i32) -> bool {
+    let status = match all_tickets(app).iter().find(|ticket| ticket.id == id) {
+        Some(ticket) => ticket.status.clone(),
+        None => return false,
+    };
+    app.ticket_view_mode = if status == TicketStatus::Open {TicketViewMode::Open} else {TicketViewMode::Closed};
+    if !visible_tickets(app).iter().any(|ticket| ticket.id == id) {
+        app.filter = TicketFilter::default();
+    }
+    let position = visible_tickets(app).iter().position(|ticket| ticket.id == id);
+    app.ticket_list_state.select(position);
+    true
+}
+
+//The tombstone of a moved ticket is not changed, the notice says where the ticket is now
+pub fn moved_notice(app: &mut AppState, ticket: &Tickets) -> bool {
+    if let Some(moved) = &ticket.moved_to {
+        app.notice = format!("Ticket {} was moved to {}, press Enter to open it", app.settings.ticket_key(ticket.id), moved.describe());
+    }
+    ticket.moved_to.is_some()
+}
+
+pub fn selected_ticket(app: &AppState) -> Option<Tickets> {
+    let selected = app.ticket_list_state.selected()?;
+    visible_tickets(app).get(selected).cloned()
+}
+
+//Replaces the ticket with the same id in the open or closed list
+pub fn replace_ticket(app: &mut AppState, ticket: Tickets) {
+    for existing in app.open_tickets.iter_mut().chain(app.closed_tickets.iter_mut()) {
+        if existing.id == ticket.id {
+            *existing = ticket.clone();
+        }
     }
 }
 
@@ -230,34 +369,154 @@ pub fn update_ticket_count(app: &mut AppState) {
 }
 
 pub fn update_db(app: &AppState) {
-    //Concatenate open and closed tickets
+    write_changes(&all_tickets(app)).unwrap();
+}
+
+//Concatenate open and closed tickets
+pub fn all_tickets(app: &AppState) -> Vec<Tickets> {
     let mut all_tickets = app.open_tickets.clone();
     all_tickets.append(&mut app.closed_tickets.clone());
-    write_changes(&all_tickets).unwrap();
+    all_tickets
 }
 
 pub fn toggle_ticket_status(app: &mut AppState) -> Result<(), Error> {
     if let Some(selected) = app.ticket_list_state.selected() {
-        match app.ticket_view_mode {
-            TicketViewMode::Open => {
-                if app.open_tickets.len() != 0 {
-                    app.open_tickets[selected].status = TicketStatus::Closed;
-                    app.open_tickets[selected].updated_at = Utc::now();
-                }
-            },
-            TicketViewMode::Closed => {
-                if app.closed_tickets.len() != 0 {
-                app.closed_tickets[selected].status = TicketStatus::Open;
-                app.closed_tickets[selected].updated_at = Utc::now();
-                }
-            },
+        if let Some(ticket) = selected_ticket(app).filter(|ticket| !moved_notice(app, ticket)) {
+            let id = ticket.id;
+            let status = match ticket.status {
+                TicketStatus::Open => TicketStatus::Closed,
+                TicketStatus::Closed => TicketStatus::Open,
+            };
+
+            set_ticket_status(app, id, status)?;
+            //Selection only moves if the ticket left the view
+            if app.notice.is_empty() {
+                update_selected_ticket(app, selected);
+            }
         }
+    }
+    Ok(())
+}
 
-        update_selected_ticket(app, selected);
+//Sets the status of the ticket with the given id and saves it, used by both the ticket list and the board
+pub fn set_ticket_status(app: &mut AppState, id: i32, status: TicketStatus) -> Result<(), Error> {
+    app.notice = String::new();
 
-        update_db(&app);
-        update_ticket_count(app);
-        render_tickets(app);
+    //Callers check the notice, so a moved ticket is left where it is
+    if let Some(ticket) = all_tickets(app).into_iter().find(|ticket| ticket.id == id) {
+        if moved_notice(app, &ticket) {
+            return Ok(());
+        }
+    }
+
+    //A parent can only be closed once all of its children are
+    let open_children = open_children(app, id);
+    if status == TicketStatus::Closed && open_children > 0 {
+        app.notice = format!("Cannot close ticket {}, {} child tickets are still open", app.settings.ticket_key(id), open_children);
+        return Ok(());
+    }
+
+    for ticket in app.open_tickets.iter_mut().chain(app.closed_tickets.iter_mut()) {
+        if ticket.id == id {
+            ticket.set_status(status.clone());
+        }
+    }
+
+    update_db(app);
+    update_ticket_count(app);
+    Ok(())
+}
+
+//One column per status, in workflow order
+pub fn board_columns(app: &AppState) -> Vec<(TicketStatus, Vec<Tickets>)> {
+    TicketStatus::all()
+        .into_iter()
+        .map(|status| {
+            let tickets = app.open_tickets.iter()
+                .chain(app.closed_tickets.iter())
+                .filter(|ticket| ticket.status == status)
+                .cloned()
+                .collect();
+            (status, tickets)
+        })
+        .collect()
+}
+
+pub fn select_board_column(app: &mut AppState, forward: bool) {
+    let columns = board_columns(app);
+    if forward && app.board_column + 1 < columns.len() {
+        app.board_column += 1;
+    } else if !forward && app.board_column > 0 {
+        app.board_column -= 1;
+    }
+    //Keep the card index within the new column
+    let amount_cards = columns[app.board_column].1.len();
+    match app.board_card_state.selected() {
+        _ if amount_cards == 0 => app.board_card_state.select(None),
+        Some(selected) if selected < amount_cards => {}
+        _ => app.board_card_state.select(Some(amount_cards - 1)),
+    }
+}
+
+pub fn select_board_card(app: &mut AppState, forward: bool) {
+    let amount_cards = board_columns(app)[app.board_column].1.len();
+    step_list_state(&mut app.board_card_state, amount_cards, forward);
+}
+
+//Moves a list selection up or down, wrapping around at either end
+pub fn step_list_state(state: &mut ListState, amount: usize, forward: bool) {
+    if amount == 0 {
+        state.select(None);
+        return;
+    }
+    let selected = match state.selected() {
+        Some(selected) if selected >= amount => amount - 1,
+        Some(selected) if forward => (selected + 1) % amount,
+        Some(selected) if selected > 0 => selected - 1,
+        Some(_) => amount - 1,
+        None => 0,
+    };
+    state.select(Some(selected));
+}
+
+//Keeps a list selection in range after items were removed from the list
+pub fn clamp_list_state(state: &mut ListState, amount: usize) {
+    match state.selected() {
+        _ if amount == 0 => state.select(None),
+        Some(selected) if selected >= amount => state.select(Some(amount - 1)),
+        Some(_) => {}
+        None => state.select(Some(0)),
+    }
+}
+
+//Moves the selected card to the neighbouring column, changing its status
+pub fn move_board_card(app: &mut AppState, forward: bool) -> Result<(), Error> {
+    let columns = board_columns(app);
+    let target = if forward {
+        app.board_column + 1
+    } else if app.board_column > 0 {
+        app.board_column - 1
+    } else {
+        return Ok(());
+    };
+    if target >= columns.len() {
+        return Ok(());
+    }
+
+    if let Some(selected) = app.board_card_state.selected() {
+        if let Some(ticket) = columns[app.board_column].1.get(selected) {
+            let id = ticket.id;
+            set_ticket_status(app, id, columns[target].0.clone())?;
+            //The card stays where it is if it could not be moved
+            if !app.notice.is_empty() {
+                return Ok(());
+            }
+
+            //Follow the card into its new column
+            app.board_column = target;
+            let position = board_columns(app)[target].1.iter().position(|ticket| ticket.id == id);
+            app.board_card_state.select(position);
+        }
     }
     Ok(())
 }
@@ -268,10 +527,10 @@ pub fn sort(app: &mut AppState) {
             //Sort by ID
             match app.ticket_view_mode {
                 TicketViewMode::Open => {
-                    app.open_tickets.sort_by(|a, b| a.id.cmp(&b.id));
+                    app.open_tickets.sort_by_key(|a| a.id);
                 },
                 TicketViewMode::Closed => {
-                    app.closed_tickets.sort_by(|a, b| a.id.cmp(&b.id));
+                    app.closed_tickets.sort_by_key(|a| a.id);
                 },
             }
         }
@@ -290,10 +549,10 @@ pub fn sort(app: &mut AppState) {
             //Sort by Priority
             match app.ticket_view_mode {
                 TicketViewMode::Open => {
-                    app.open_tickets.sort_by(|a, b| a.priority.cmp(&b.priority));
+                    app.open_tickets.sort_by_key(|a| app.settings.priority_rank(&a.priority));
                 },
                 TicketViewMode::Closed => {
-                    app.closed_tickets.sort_by(|a, b| a.priority.cmp(&b.priority));
+                    app.closed_tickets.sort_by_key(|a| app.settings.priority_rank(&a.priority));
                 },
             }
         },
@@ -301,12 +560,211 @@ pub fn sort(app: &mut AppState) {
             //Sort by Updated
             match app.ticket_view_mode {
                 TicketViewMode::Open => {
-                    app.open_tickets.sort_by(|a, b| a.updated_at.cmp(&b.updated_at));
+                    app.open_tickets.sort_by_key(|a| a.updated_at);
+                },
+                TicketViewMode::Closed => {
+                    app.closed_tickets.sort_by_key(|a| a.updated_at);
+                },
+            }
+        },
+        SortBy::Field(index) => {
+            //Sort by a custom field
+            if let Some(field) = app.settings.fields.get(index).cloned() {
+                match app.ticket_view_mode {
+                    TicketViewMode::Open => {
+                        app.open_tickets.sort_by(|a, b| compare_field(&field, a, b));
+                    },
+                    TicketViewMode::Closed => {
+                        app.closed_tickets.sort_by(|a, b| compare_field(&field, a, b));
+                    },
+                }
+            }
+        },
+        SortBy::Due => {
+            //Sort by Due, tickets without a due date go last
+            match app.ticket_view_mode {
+                TicketViewMode::Open => {
+                    app.open_tickets.sort_by_key(|a| (a.due.is_none(), a.due));
                 },
                 TicketViewMode::Closed => {
-                    app.closed_tickets.sort_by(|a, b| a.updated_at.cmp(&b.updated_at));
+                    app.closed_tickets.sort_by_key(|a| (a.due.is_none(), a.due));
                 },
             }
         },
     }
-}
\ No newline at end of file
+}
+//Applies sync_links to the stored tickets so the other side of a link is always there
+pub fn sync_ticket_links(app: &mut AppState, id: i32) {
+    let mut tickets = all_tickets(app);
+    sync_links(id, &mut tickets);
+    app.open_tickets = tickets.iter().filter(|ticket| ticket.status == TicketStatus::Open).cloned().collect();
+    app.closed_tickets = tickets.into_iter().filter(|ticket| ticket.status == TicketStatus::Closed).collect();
+}
+
+//Tickets that can be picked as the other end of a link
+pub fn link_candidates(app: &AppState) -> Vec<Tickets> {
+    let mut tickets: Vec<Tickets> = all_tickets(app)
+        .into_iter()
+        .filter(|ticket| ticket.id != app.edit_ticket.id)
+        .collect();
+    tickets.sort_by_key(|ticket| ticket.id);
+    tickets
+}
+
+pub fn selected_link_kind(app: &AppState) -> LinkKind {
+    LinkKind::all()[app.link_kind].clone()
+}
+
+//Opened from the edit form, the link is saved together with the ticket
+pub fn init_link_picker(app: &mut AppState) -> Result<(), Error> {
+    app.link_kind = 0;
+    app.close_as_duplicate = false;
+    app.link_list_state.select(if link_candidates(app).is_empty() {None} else {Some(0)});
+    app.active_menu_item = MenuItem::LinkPicker;
+    Ok(())
+}
+
+//Opened from the ticket list, picking a ticket closes the selected one as its duplicate
+pub fn init_close_as_duplicate(app: &mut AppState) -> Result<(), Error> {
+    if let Some(ticket) = selected_ticket(app) {
+        if ticket.status == TicketStatus::Closed {
+            return Ok(());
+        }
+        app.edit_ticket = ticket;
+        app.link_kind = LinkKind::all().iter().position(|kind| *kind == LinkKind::Duplicates).unwrap_or(0);
+        app.close_as_duplicate = true;
+        app.link_list_state.select(if link_candidates(app).is_empty() {None} else {Some(0)});
+        app.active_menu_item = MenuItem::LinkPicker;
+    }
+    Ok(())
+}
+
+pub fn pick_link(app: &mut AppState) -> Result<(), Error> {
+    let target = match app.link_list_state.selected().and_then(|selected| link_candidates(app).get(selected).cloned()) {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let kind = selected_link_kind(app);
+
+    if !app.close_as_duplicate {
+        toggle_link(&mut app.edit_ticket, kind, target.id);
+        app.active_menu_item = MenuItem::EditForm;
+        return Ok(());
+    }
+
+    //Record the duplicate, then close the ticket
+    let id = app.edit_ticket.id;
+    if !app.edit_ticket.links.contains(&TicketLink{ kind: kind.clone(), target: target.id }) {
+        toggle_link(&mut app.edit_ticket, kind, target.id);
+    }
+    app.edit_ticket.updated_at = Utc::now();
+    replace_ticket(app, app.edit_ticket.clone());
+    sync_ticket_links(app, id);
+    update_db(app);
+
+    let selected = app.ticket_list_state.selected().unwrap_or(0);
+    set_ticket_status(app, id, TicketStatus::Closed)?;
+    if app.notice.is_empty() {
+        update_selected_ticket(app, selected);
+    }
+
+    app.edit_ticket = Tickets::default();
+    app.close_as_duplicate = false;
+    app.active_menu_item = MenuItem::Tickets;
+    Ok(())
+}
+
+//Open tickets that look like the ticket being added
+pub fn duplicate_candidates(app: &AppState) -> Vec<(Tickets, f64)> {
+    similar_tickets(&app.edit_ticket, &app.open_tickets)
+        .into_iter()
+        .map(|(ticket, score)| (ticket.clone(), score))
+        .collect()
+}
+
+//Saves the ticket, but new tickets that look like existing ones are shown to the user first
+pub fn save_ticket(app: &mut AppState) -> Result<(), Error> {
+    //Custom field values have to be valid before anything is saved
+    if let Err(message) = apply_field_inputs(app) {
+        app.notice = message;
+        return Ok(());
+    }
+    if app.edit_ticket.id == -7 && !duplicate_candidates(app).is_empty() {
+        app.duplicate_state.select(Some(0));
+        app.active_menu_item = MenuItem::DuplicateWarning;
+        return Ok(());
+    }
+    add_ticket(app)
+}
+
+//Links the new ticket to the chosen existing one and saves it
+pub fn link_duplicate(app: &mut AppState) -> Result<(), Error> {
+    let candidates = duplicate_candidates(app);
+    if let Some((ticket, _)) = app.duplicate_state.selected().and_then(|selected| candidates.get(selected)) {
+        toggle_link(&mut app.edit_ticket, LinkKind::RelatesTo, ticket.id);
+    }
+    add_ticket(app)
+}
+
+//Adds the typed tag to the ticket being edited, or removes it if the ticket already has it
+pub fn add_tag_input(app: &mut AppState) {
+    let tag = normalize_tag(&app.tag_input);
+    if !tag.is_empty() && !app.edit_ticket.tags.remove(&tag) {
+        app.edit_ticket.tags.insert(tag);
+    }
+    app.tag_input = String::new();
+}
+
+//Completes the typed tag to the first known tag it matches
+pub fn complete_tag(app: &mut AppState) {
+    if let Some(tag) = app.settings.tag_suggestions(&app.tag_input).first() {
+        app.tag_input = tag.clone();
+    }
+}
+
+//Steps the tag filter through no filter and then every known tag
+pub fn cycle_tag_filter(app: &mut AppState) {
+    let position = app.filter.tag.as_ref().and_then(|tag| app.settings.tags.iter().position(|known| known.name == *tag));
+    let next = match position {
+        Some(position) => position + 1,
+        None => 0,
+    };
+    app.filter.tag = app.settings.tags.get(next).map(|tag| tag.name.clone());
+    //set index to 0 to prevent crash
+    app.ticket_list_state.select(Some(0));
+}
+
+//Creates the tickets of recurring templates that are due, run when the app starts
+pub fn create_recurring_tickets(app: &mut AppState) -> Result<(), Error> {
+    let templates = read_templates()?;
+    let mut db = read_ticket_db()?;
+    let created = create_recurring(&templates, &mut db, &app.settings, Local::now().naive_local().date(), &current_user());
+    if !created.is_empty() {
+        write_changes(&db.tickets)?;
+        update_ticket_count(app);
+        let ids: Vec<String> = created.iter().map(|id| app.settings.ticket_ref(*id)).collect();
+        add_notice(app, format!("Created recurring tickets {}", ids.join(", ")));
+    }
+    Ok(())
+}
+
+//Lines for the banner shown on launch: open tickets due today and how many are overdue
+pub fn due_banner(app: &AppState) -> Vec<String> {
+    let today = Local::now().naive_local().date();
+    let mut lines: Vec<String> = app.open_tickets
+        .iter()
+        .filter(|ticket| ticket.due_in(today) == Some(0))
+        .map(|ticket| format!("Due today: {} {}", app.settings.ticket_ref(ticket.id), ticket.title))
+        .collect();
+    //Keep the banner small, the list still has to fit
+    if lines.len() > 4 {
+        let more = lines.len() - 3;
+        lines.truncate(3);
+        lines.push(format!("...and {} more due today", more));
+    }
+    let overdue = app.open_tickets.iter().filter(|ticket| ticket.due_in(today).is_some_and(|days| days < 0)).count();
+    if overdue > 0 {
+        lines.push(format!("{} ticket(s) are overdue", overdue));
+    }
+    lines
+}
@@ -1,76 +1,69 @@
+use std::collections::BTreeSet;
 use std::vec;
 
-use chrono::{Utc, Local};
+use chrono::{Duration, Local, Utc};
 use scrum_lib::*;
 use tui::{
     style::{Color, Modifier, Style},
-    text::{Span, Spans, Text, self},
+    symbols,
+    text::{Span, Spans},
     widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Tabs, TableState, Wrap,
+        Axis, BarChart, Block, BorderType, Borders, Cell, Chart, Dataset, GraphType, List, ListItem, Paragraph, Row, Sparkline, Table, Wrap,
     }, layout::{Constraint, Alignment},
 };
 use crate::app::*;
-use crate::theme::*;
+use crate::ticket::*;
+use crate::sprint::*;
+use crate::report::*;
+use crate::field::*;
+use crate::db::*;
 
 
 pub fn render_tickets<'a>(app: &AppState) -> (Table<'a>, Paragraph<'a>) {
  
-    let mut tickets = Vec::new();
-    match app.ticket_view_mode {
-        TicketViewMode::Open => {
-            let ticket_list = app.open_tickets.clone();
-            for ticket in ticket_list {
-                if ticket.status.to_string() == "Open" { //FIX THIS!
-                    tickets.push(ticket);
-                }
-            }
-        }
-        TicketViewMode::Closed => {
-            let ticket_list = app.closed_tickets.clone();
-            for ticket in ticket_list {
-                if ticket.status.to_string() == "Closed" { //FIX THIS!
-                    tickets.push(ticket);
-                }
-            }
-        }
-    }
+    let tickets = visible_tickets(app);
 
     let mut selected_ticket = Tickets {
-        id: 0,
-        title: "".to_owned(),
         description: "No tickets".to_owned(),
-        notes: None,
-        status: TicketStatus::Open,
-        priority: "".to_owned(),
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
+        ..Tickets::default()
     };
 
     //If there is at least ticket
-    if tickets.len() > 0 {
-    //Gets selected ticket by index, but requires nifty alignment
-    selected_ticket = tickets
-        .get(
-            app.ticket_list_state
-                .selected()
-                .expect("ticket list state"),
-        )
-        .expect("selected ticket")
-        .clone();
+    if let Some(ticket) = app.ticket_list_state.selected().and_then(|selected| tickets.get(selected)) {
+        selected_ticket = ticket.clone();
     }
 
-    let rows = tickets.iter().enumerate().map(|(i, item)| {
+    let everything = all_tickets(app);
+    let today = Local::now().naive_local().date();
+    let rows = tickets.iter().map(|item| {
+        //Children are indented under their parent in the tree view
+        let indent = if app.tree_view {"  ".repeat(tree_depth(item, &tickets))} else {String::new()};
+        let mut title = format!("{}{}", indent, item.title);
+        if item.kind == TicketKind::Epic {
+            title = format!("{}[Epic] {}", indent, item.title);
+        }
+        if let Some((closed, total)) = progress(item.id, &everything) {
+            title = format!("{} ({}/{})", title, closed, total);
+        }
+        if item.timer.is_some() {
+            title = format!("{} [timer]", title);
+        }
+        let mut titlespans = vec![Span::raw(title)];
+        titlespans.extend(tag_chips(app, &item.tags));
         Row::new(vec![
-            Cell::from(item.id.to_string()),
-            Cell::from(item.title.clone()),
+            Cell::from(app.settings.ticket_key(item.id)),
+            Cell::from(Spans::from(titlespans)),
             Cell::from(item.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()),
             Cell::from(item.updated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()),
+            Cell::from(item.due.map(|due| due.format("%Y-%m-%d").to_string()).unwrap_or_default()),
             Cell::from(item.priority.to_string()),
+            Cell::from(item.checklist_progress().map(|(done, total)| format!("{}/{}", done, total)).unwrap_or_default()),
         ])
+        .style(due_style(app, item.due_in(today)))
     });
 
     let list = Table::new(rows)
-        .block(Block::default().borders(Borders::ALL).title(" Tickets"))
+        .block(Block::default().borders(Borders::ALL).title(tickets_title(app)))
         .style(Style::default().fg(app.theme.text))
         .highlight_style(Style::default().bg(app.theme.selection).fg(Color::Black))
         .header(Row::new(vec![
@@ -90,16 +83,26 @@ pub fn render_tickets<'a>(app: &AppState) -> (Table<'a>, Paragraph<'a>) {
                 "Last Updated",
                 Style::default().add_modifier(Modifier::BOLD),
             )),
+            Cell::from(Span::styled(
+                "Due",
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
             Cell::from(Span::styled(
                 "Priority",
                 Style::default().add_modifier(Modifier::BOLD),
             )),
+            Cell::from(Span::styled(
+                "Checklist",
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
         ]))
         .widths(&[
-            Constraint::Percentage(10),
-            Constraint::Percentage(38),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
+            Constraint::Percentage(7),
+            Constraint::Percentage(31),
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
+            Constraint::Percentage(11),
+            Constraint::Percentage(9),
             Constraint::Percentage(12),
         ]);
 
@@ -120,11 +123,20 @@ pub fn render_tickets<'a>(app: &AppState) -> (Table<'a>, Paragraph<'a>) {
     let mut text = vec![
         Spans::from(vec![
             Span::styled("ID: ", Style::default().fg(app.theme.selection)),
-            Span::raw(selected_ticket.id.to_string()),
+            Span::raw(app.settings.ticket_key(selected_ticket.id)),
             Span::styled(" | Status: ", Style::default().fg(app.theme.selection)),
-            Span::raw(selected_ticket.status.to_string().to_owned()),
+            Span::raw(app.settings.status_name(&selected_ticket.status)),
             Span::styled(" | Priority: ", Style::default().fg(app.theme.selection)),
             Span::raw(selected_ticket.priority.to_string().to_owned()),
+            Span::styled(" | Assignee: ", Style::default().fg(app.theme.selection)),
+            Span::raw(selected_ticket.assignee.clone().unwrap_or_else(|| "Unassigned".to_owned())),
+            Span::styled(" | Due: ", Style::default().fg(app.theme.selection)),
+            Span::styled(
+                selected_ticket.due.map(|due| due.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "None".to_owned()),
+                due_style(app, selected_ticket.due_in(today)),
+            ),
+            Span::styled(" | Estimate: ", Style::default().fg(app.theme.selection)),
+            Span::raw(selected_ticket.estimate.map(|estimate| app.settings.estimate_unit.format(estimate)).unwrap_or_else(|| "None".to_owned())),
             Span::styled(" | Created: ", Style::default().fg(app.theme.selection)),
             Span::raw(selected_ticket.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()),
             Span::styled(" | Updated: ", Style::default().fg(app.theme.selection)),
@@ -146,6 +158,124 @@ pub fn render_tickets<'a>(app: &AppState) -> (Table<'a>, Paragraph<'a>) {
     //add notespan to text
     text.extend(notespan);
 
+    //Checklist with the number key that ticks off each item
+    if let Some((done, total)) = selected_ticket.checklist_progress() {
+        text.push(Spans::from(vec![Span::raw("\n")]));
+        text.push(Spans::from(vec![
+            Span::styled("Checklist: ", Style::default().fg(app.theme.selection)),
+            Span::raw(format!("{}/{}", done, total)),
+        ]));
+        for (i, item) in selected_ticket.checklist.iter().enumerate() {
+            text.push(Spans::from(vec![Span::raw(checklist_text(i, item))]));
+        }
+    }
+
+    //Children of the ticket and how many of them are done
+    if let Some((closed, total)) = progress(selected_ticket.id, &everything) {
+        text.push(Spans::from(vec![Span::raw("\n")]));
+        text.push(Spans::from(vec![
+            Span::styled("Children: ", Style::default().fg(app.theme.selection)),
+            Span::raw(format!("{}/{} closed ({}%)", closed, total, closed * 100 / total)),
+        ]));
+        for child in children(selected_ticket.id, &everything) {
+            text.push(Spans::from(vec![
+                Span::raw(format!("  {} [{}] ", app.settings.ticket_ref(child.id), app.settings.status_name(&child.status))),
+                Span::raw(child.title.clone()),
+            ]));
+        }
+    }
+
+    //Attached files, opened from the list with o
+    if !selected_ticket.attachments.is_empty() {
+        text.push(Spans::from(vec![Span::raw("\n")]));
+        text.push(Spans::from(vec![Span::styled("Attachments:", Style::default().fg(app.theme.selection))]));
+        for attachment in selected_ticket.attachments.iter() {
+            text.push(Spans::from(vec![Span::raw(attachment_text(attachment))]));
+        }
+    }
+
+    //Links to other tickets
+    if !selected_ticket.links.is_empty() {
+        text.push(Spans::from(vec![Span::raw("\n")]));
+        text.push(Spans::from(vec![Span::styled("Links:", Style::default().fg(app.theme.selection))]));
+        for link in selected_ticket.links.iter() {
+            text.push(Spans::from(vec![Span::raw(link_text(app, link, &everything))]));
+        }
+    }
+
+    //Logged time and the running timer
+    let mut logged = vec![
+        Span::styled("Logged: ", Style::default().fg(app.theme.selection)),
+        Span::raw(format_minutes(selected_ticket.logged_minutes())),
+    ];
+    if let Some(timer) = &selected_ticket.timer {
+        let running = (Utc::now() - timer.started_at).num_minutes();
+        logged.push(Span::styled(" | Timer: ", Style::default().fg(app.theme.selection)),);
+        logged.push(Span::raw(format!(
+            "{} since {} ({})",
+            timer.user,
+            timer.started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            format_minutes(running),
+        )));
+    }
+    text.insert(1, Spans::from(logged));
+
+    //Custom fields of the ticket, in the order they are defined
+    let mut fields = Vec::new();
+    for field in app.settings.fields.iter() {
+        if let Some(value) = selected_ticket.fields.get(&field.name) {
+            if !fields.is_empty() {
+                fields.push(Span::raw(" | "));
+            }
+            fields.push(Span::styled(format!("{}: ", field.name), Style::default().fg(app.theme.selection)));
+            fields.push(Span::raw(value.clone()));
+        }
+    }
+    if !fields.is_empty() {
+        text.insert(1, Spans::from(fields));
+    }
+
+    //Tags of the ticket
+    if !selected_ticket.tags.is_empty() {
+        let mut tags = vec![Span::styled("Tags:", Style::default().fg(app.theme.selection))];
+        tags.extend(tag_chips(app, &selected_ticket.tags));
+        text.insert(1, Spans::from(tags));
+    }
+
+    //Kind and parent of the ticket
+    let mut kind = vec![
+        Span::styled("Kind: ", Style::default().fg(app.theme.selection)),
+        Span::raw(selected_ticket.kind.to_string().to_owned()),
+    ];
+    if let Some(parent) = selected_ticket.parent {
+        let parent_title = everything.iter().find(|ticket| ticket.id == parent).map(|ticket| ticket.title.clone()).unwrap_or_default();
+        kind.push(Span::styled(" | Parent: ", Style::default().fg(app.theme.selection)));
+        kind.push(Span::raw(format!("{} {}", app.settings.ticket_ref(parent), parent_title)));
+    }
+    text.insert(1, Spans::from(kind));
+
+    //Where the ticket came from and, for a tombstone, where it went
+    if let Some(from) = &selected_ticket.copied_from {
+        text.insert(1, Spans::from(vec![
+            Span::styled("From: ", Style::default().fg(app.theme.selection)),
+            Span::raw(from.describe()),
+        ]));
+    }
+    if let Some(moved) = &selected_ticket.moved_to {
+        text.insert(1, Spans::from(vec![
+            Span::styled("Moved to: ", Style::default().fg(app.theme.selection)),
+            Span::raw(format!("{} (Enter to open it)", moved.describe())),
+        ]));
+    }
+
+    //Sprint the ticket is planned into
+    if let Some(sprint) = app.sprints.iter().find(|sprint| Some(sprint.id) == selected_ticket.sprint) {
+        text.insert(1, Spans::from(vec![
+            Span::styled("Sprint: ", Style::default().fg(app.theme.selection)),
+            Span::raw(sprint.name.clone()),
+        ]));
+    }
+
     let ticket_detail = Paragraph::new(text)
         .alignment(Alignment::Left)
         .block(
@@ -162,38 +292,178 @@ pub fn render_tickets<'a>(app: &AppState) -> (Table<'a>, Paragraph<'a>) {
     (list, ticket_detail)
 }
 
-pub fn render_edit_form<'a>(app: &mut AppState) -> (Paragraph<'a>, Paragraph<'a>, List<'a>, List<'a>, List<'a>) {
+fn tickets_title(app: &AppState) -> String {
+    let mut title = " Tickets".to_string();
+    if let Some(project) = current_project() {
+        title.push_str(&format!(" - {}", project.name));
+    }
+    if app.tree_view {
+        title.push_str(" [Tree]");
+    }
+    if app.filter.current_sprint {
+        match Sprint::current(&app.sprints, Local::now().naive_local().date()) {
+            Some(sprint) => title.push_str(&format!(" (Sprint: {})", sprint.name)),
+            None => title.push_str(" (No current sprint)"),
+        }
+    }
+    if let Some(tag) = &app.filter.tag {
+        title.push_str(&format!(" (Tag: {})", tag));
+    }
+    if let Some((name, value)) = &app.filter.field {
+        title.push_str(&format!(" ({}={})", name, value));
+    }
+    title
+}
+
+//Tags as colored chips, each tag keeps the color it got when it was first used
+fn tag_chips<'a>(app: &AppState, tags: &BTreeSet<String>) -> Vec<Span<'a>> {
+    let mut chips = Vec::new();
+    for tag in tags {
+        chips.push(Span::raw(" "));
+        chips.push(Span::styled(
+            format!(" {} ", tag),
+            Style::default().bg(app.theme.tag_color(app.settings.tag_color(tag))).fg(Color::Black),
+        ));
+    }
+    chips
+}
+
+//One line description of a link, such as "Blocks OPS-3 Zabbix Setup"
+fn link_text(app: &AppState, link: &TicketLink, tickets: &[Tickets]) -> String {
+    let title = tickets.iter().find(|ticket| ticket.id == link.target).map(|ticket| ticket.title.clone()).unwrap_or_default();
+    format!("{} {} {}", link.kind.to_string(), app.settings.ticket_ref(link.target), title)
+}
+
+//One line of a checklist, such as "3. [x] Take snapshots"
+fn checklist_text(index: usize, item: &ChecklistItem) -> String {
+    format!("{}. [{}] {}", index + 1, if item.done {"x"} else {" "}, item.text)
+}
+
+//One line description of an attachment, such as "log.txt (1.2 KB) added by anna on 2022-05-11 04:07"
+fn attachment_text(attachment: &Attachment) -> String {
+    format!(
+        "{} ({}) added by {} on {}",
+        attachment.name,
+        format_size(attachment.size),
+        attachment.added_by,
+        attachment.added_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+    )
+}
+
+//Overdue tickets are red and tickets due soon are yellow
+fn due_style(app: &AppState, due_in: Option<i64>) -> Style {
+    match due_in {
+        Some(days) if days < 0 => Style::default().fg(Color::Red),
+        Some(days) if days <= DUE_SOON_DAYS => Style::default().fg(Color::Yellow),
+        _ => Style::default().fg(app.theme.text),
+    }
+}
+
+pub fn render_due_banner<'a>(app: &AppState) -> Paragraph<'a> {
+    let lines: Vec<Spans> = app.due_banner.iter().map(|line| Spans::from(Span::raw(line.clone()))).collect();
+    Paragraph::new(lines)
+        .style(Style::default().fg(Color::Yellow))
+        .block(Block::default().borders(Borders::ALL).title(" Due today (Esc to dismiss)"))
+}
+
+//Footer line for messages such as a ticket that could not be closed
+pub fn render_notice<'a>(app: &AppState) -> Paragraph<'a> {
+    Paragraph::new(app.notice.clone()).style(Style::default().fg(Color::Red))
+}
+
+//Widgets of the edit form, one per field
+pub struct EditForm<'a> {
+    pub title: Paragraph<'a>,
+    pub assignee: Paragraph<'a>,
+    pub tags: Paragraph<'a>,
+    pub description: Paragraph<'a>,
+    pub priority: List<'a>,
+    pub status: List<'a>,
+    pub estimate: Paragraph<'a>,
+    pub due: Paragraph<'a>,
+    pub kind: List<'a>,
+    pub parent: Paragraph<'a>,
+    pub notes: List<'a>,
+    pub links: List<'a>,
+    pub checklist: List<'a>,
+    pub fields: List<'a>,
+}
+
+pub fn render_edit_form<'a>(app: &mut AppState) -> EditForm<'a> {
     
     let input1 = Paragraph::new(app.edit_ticket.title.clone())
     .style(Style::default().fg(if app.edit_focus == EditItem::Title {app.theme.selection} else {app.theme.text},))
     .block(Block::default().borders(Borders::ALL).title("Title")).wrap(Wrap { trim: true });
 
+    let assigneeinput = Paragraph::new(app.edit_ticket.assignee.clone().unwrap_or_default())
+    .style(Style::default().fg(if app.edit_focus == EditItem::Assignee {app.theme.selection} else {app.theme.text},))
+    .block(Block::default().borders(Borders::ALL).title("Assignee")).wrap(Wrap { trim: true });
+
+    //Chips for the tags the ticket has, then what is being typed
+    let mut tagspans = tag_chips(app, &app.edit_ticket.tags);
+    tagspans.push(Span::raw(format!(" {}", app.tag_input)));
+    let suggestions = app.settings.tag_suggestions(&app.tag_input);
+    let tagstitle = match suggestions.first() {
+        Some(suggestion) => format!("Tags (Enter to add or remove, Right to complete: {})", suggestion),
+        None => "Tags (Enter to add or remove, Backspace removes the last)".to_string(),
+    };
+    let tagsinput = Paragraph::new(Spans::from(tagspans))
+    .style(Style::default().fg(if app.edit_focus == EditItem::Tags {app.theme.selection} else {app.theme.text},))
+    .block(Block::default().borders(Borders::ALL).title(tagstitle));
+
     let input2 = Paragraph::new(app.edit_ticket.description.clone())
     .style(Style::default().fg(if app.edit_focus == EditItem::Description {app.theme.selection} else {app.theme.text},))
     .block(Block::default().borders(Borders::ALL).title("Description")).wrap(Wrap { trim: true });
 
     //Create ListItem for each priority
-    let priorityrows = vec![
-        ListItem::new(Span::styled("High", Style::default().fg(app.theme.text))),
-        ListItem::new(Span::styled("Medium", Style::default().fg(app.theme.text))),
-        ListItem::new(Span::styled("Low", Style::default().fg(app.theme.text))),
-    ];
+    let priorityrows: Vec<ListItem> = app.settings.priorities
+        .iter()
+        .map(|priority| ListItem::new(Span::styled(priority.clone(), Style::default().fg(app.theme.text))))
+        .collect();
 
     let input3 = List::new(priorityrows)
     .block(Block::default().borders(Borders::ALL).title("Priority"))
     .style(Style::default().fg(if app.edit_focus == EditItem::Priority {app.theme.selection} else {app.theme.text},))
     .highlight_style(Style::default().bg(app.theme.selection).fg(Color::Black));
 
-    let statusrows = vec![
-        ListItem::new(Span::styled("Open", Style::default().fg(app.theme.text))),
-        ListItem::new(Span::styled("Closed", Style::default().fg(app.theme.text))),
-    ];
+    let statusrows: Vec<ListItem> = TicketStatus::all()
+        .iter()
+        .map(|status| ListItem::new(Span::styled(app.settings.status_name(status), Style::default().fg(app.theme.text))))
+        .collect();
 
     let input4 = List::new(statusrows)
     .block(Block::default().borders(Borders::ALL).title("Status"))
     .style(Style::default().fg(if app.edit_focus == EditItem::Status {app.theme.selection} else {app.theme.text},))
     .highlight_style(Style::default().bg(app.theme.selection).fg(Color::Black));
 
+    let estimateinput = Paragraph::new(app.estimate_input.clone())
+    .style(Style::default().fg(if app.edit_focus == EditItem::Estimate {app.theme.selection} else {app.theme.text},))
+    .block(Block::default().borders(Borders::ALL).title(format!("Estimate ({})", app.settings.estimate_unit.to_string())));
+
+    //Shows what the typed due date is understood as
+    let duetitle = match parse_due(&app.due_input, Local::now().naive_local().date()) {
+        Some(due) => format!("Due: {}", due.format("%a %Y-%m-%d")),
+        None if app.due_input.trim().is_empty() => "Due (today, fri, +3d, 2022-05-20)".to_string(),
+        None => "Due (not a date)".to_string(),
+    };
+    let dueinput = Paragraph::new(app.due_input.clone())
+    .style(Style::default().fg(if app.edit_focus == EditItem::Due {app.theme.selection} else {app.theme.text},))
+    .block(Block::default().borders(Borders::ALL).title(duetitle));
+
+    let kindrows = vec![
+        ListItem::new(Span::styled("Task", Style::default().fg(app.theme.text))),
+        ListItem::new(Span::styled("Epic", Style::default().fg(app.theme.text))),
+    ];
+
+    let kindinput = List::new(kindrows)
+    .block(Block::default().borders(Borders::ALL).title("Kind"))
+    .style(Style::default().fg(if app.edit_focus == EditItem::Kind {app.theme.selection} else {app.theme.text},))
+    .highlight_style(Style::default().bg(app.theme.selection).fg(Color::Black));
+
+    let parentinput = Paragraph::new(app.parent_input.clone())
+    .style(Style::default().fg(if app.edit_focus == EditItem::Parent {app.theme.selection} else {app.theme.text},))
+    .block(Block::default().borders(Borders::ALL).title("Parent ticket (key or ID)"));
+
     //Create new ListItem for each note in edit_ticket
     let mut notespan = Vec::new();
     if app.edit_ticket.notes.is_some() {
@@ -215,7 +485,66 @@ pub fn render_edit_form<'a>(app: &mut AppState) -> (Paragraph<'a>, Paragraph<'a>
         .highlight_style(Style::default().bg(app.theme.selection).fg(Color::Black));
 
 
-(input1, input2, input3, input4, noteinput)
+    let everything = all_tickets(app);
+    let linkrows: Vec<ListItem> = app.edit_ticket.links
+        .iter()
+        .map(|link| ListItem::new(Span::styled(link_text(app, link, &everything), Style::default().fg(app.theme.text))))
+        .collect();
+
+    let linksinput = List::new(linkrows)
+    .block(Block::default().borders(Borders::ALL).title("Links (F6 to add or remove)"))
+    .style(Style::default().fg(app.theme.text));
+
+    //Items are added by typing, the typed text is shown in the title
+    let checklistrows: Vec<ListItem> = app.edit_ticket.checklist
+        .iter()
+        .enumerate()
+        .map(|(i, item)| ListItem::new(Span::raw(checklist_text(i, item))))
+        .collect();
+    let checklisttitle = match app.edit_ticket.checklist_progress() {
+        Some((done, total)) => format!("Checklist {}/{} (Left/Right ticks off, Backspace removes): {}", done, total, app.checklist_input),
+        None => format!("Checklist (type an item, Enter to add): {}", app.checklist_input),
+    };
+
+    let checklistinput = List::new(checklistrows)
+    .block(Block::default().borders(Borders::ALL).title(checklisttitle))
+    .style(Style::default().fg(if app.edit_focus == EditItem::Checklist {app.theme.selection} else {app.theme.text},))
+    .highlight_style(Style::default().bg(app.theme.selection).fg(Color::Black));
+
+    //Custom fields defined for this database
+    let fieldrows: Vec<ListItem> = app.settings.fields
+        .iter()
+        .zip(app.field_inputs.iter())
+        .enumerate()
+        .map(|(i, (field, input))| {
+            let focused = app.edit_focus == EditItem::Field(i);
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{} ({}): ", field.name, field.kind.to_string()), Style::default().fg(if focused {app.theme.selection} else {app.theme.text})),
+                Span::raw(input.clone()),
+            ]))
+        })
+        .collect();
+
+    let fieldsinput = List::new(fieldrows)
+    .block(Block::default().borders(Borders::ALL).title("Custom fields (Up/Down picks enum options)"))
+    .style(Style::default().fg(app.theme.text));
+
+EditForm {
+    title: input1,
+    assignee: assigneeinput,
+    tags: tagsinput,
+    description: input2,
+    priority: input3,
+    status: input4,
+    estimate: estimateinput,
+    due: dueinput,
+    kind: kindinput,
+    parent: parentinput,
+    notes: noteinput,
+    links: linksinput,
+    checklist: checklistinput,
+    fields: fieldsinput,
+}
  
 }
 
@@ -225,10 +554,6 @@ pub fn render_notes_form<'a>(app: &'a mut AppState) -> (Paragraph<'a>, List<'a>)
     .style(Style::default().fg(app.theme.selection))
     .block(Block::default().borders(Borders::ALL).title(app.prompt.clone())).wrap(Wrap { trim: true });
 
-    let input2 = Paragraph::new(app.input.as_ref())
-    .style(Style::default().fg(app.theme.selection))
-    .block(Block::default().borders(Borders::ALL).title("Input")).wrap(Wrap { trim: true });
-
     let messages: Vec<ListItem> = app
     .messages
     .iter()
@@ -246,17 +571,87 @@ pub fn render_notes_form<'a>(app: &'a mut AppState) -> (Paragraph<'a>, List<'a>)
  
 }
 
+//The file to import and the mapping of CSV columns, Tab moves between them
+pub fn render_import_form<'a>(app: &'a mut AppState) -> (Paragraph<'a>, Paragraph<'a>, List<'a>) {
+    let style = |focused: bool| if focused {Style::default().fg(app.theme.selection)} else {Style::default()};
+    let path = Paragraph::new(app.input.as_ref())
+    .style(style(!app.import_focus_mapping))
+    .block(Block::default().borders(Borders::ALL).title(app.prompt.clone()));
+    let mapping = Paragraph::new(app.import_mapping.as_ref())
+    .style(style(app.import_focus_mapping))
+    .block(Block::default().borders(Borders::ALL).title("CSV columns when the headers are not recognised, such as title=Summary, priority=Prio"));
+
+    let messages: Vec<ListItem> = app
+    .messages
+    .iter()
+    .enumerate()
+    .map(|(i, m)| ListItem::new(vec![Spans::from(Span::raw(format!("{}: {}", i, m)))]))
+    .collect();
+    let messages = List::new(messages).block(Block::default().borders(Borders::ALL).title("Messages"));
+
+    (path, mapping, messages)
+}
+
 pub fn render_help_form<'a>(app: &'a mut AppState) -> Paragraph<'a> {
     
     let help = Paragraph::new(vec![
         Spans::from(vec![Span::raw("Commands")]),
         Spans::from(vec![Span::raw("Ctrl + k: Delete ticket (must be closed)")]),
+        Spans::from(vec![Span::raw("c: Close or reopen ticket (parents close once all children are closed)")]),
+        Spans::from(vec![Span::raw("t: Show tickets as a tree of epics and subtasks")]),
+        Spans::from(vec![Span::raw("g: Only show tickets with a tag, press again for the next tag")]),
+        Spans::from(vec![Span::raw("Esc: Dismiss the banner with tickets due today")]),
+        Spans::from(vec![Span::raw("w: Start a timer on the ticket, press again to stop it and log the time")]),
+        Spans::from(vec![Span::raw("d: Define custom fields, /: Filter by a custom field (field=value) or go to a ticket by key (OPS-42 or 42)")]),
+        Spans::from(vec![Span::raw("1-9: Tick off or untick a checklist item of the selected ticket")]),
+        Spans::from(vec![Span::raw("u: Attach a file to the ticket, o: Open an attachment (Delete removes it)")]),
+        Spans::from(vec![Span::raw("Shift + i: Import tickets from CSV or a GitHub, GitLab or Jira export (preview first, imported issues are updated)")]),
+        Spans::from(vec![Span::raw("Shift + e: Export the shown tickets to CSV, .md or .html")]),
+        Spans::from(vec![Span::raw("x: Close ticket as a duplicate of another ticket")]),
+        Spans::from(vec![Span::raw("F6 (while editing): Add or remove a link, Left/Right picks the link type")]),
+        Spans::from(vec![Span::raw("a: New ticket, pick a template first (templates.json in the config directory)")]),
+        Spans::from(vec![Span::raw("Templates with a recurrence are created as tickets when they are due, checked on every start")]),
+        Spans::from(vec![Span::raw("New tickets that look like open ones show the possible duplicates before saving")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Board")]),
+        Spans::from(vec![Span::raw("b: Open board")]),
+        Spans::from(vec![Span::raw("Arrow keys: Select card and column")]),
+        Spans::from(vec![Span::raw("Shift + Left/Right: Move card to neighbouring column")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Sprints")]),
+        Spans::from(vec![Span::raw("s: Open sprint planning")]),
+        Spans::from(vec![Span::raw("f: Only show tickets in the current sprint")]),
+        Spans::from(vec![Span::raw("Tab: Switch between sprints, backlog and sprint tickets")]),
+        Spans::from(vec![Span::raw("Right/Left: Move ticket into the sprint or back to the backlog")]),
+        Spans::from(vec![Span::raw("n: New sprint, x: Close sprint (unfinished tickets roll into the next sprint)")]),
+        Spans::from(vec![Span::raw("+/-: Change team capacity, u: Switch between story points and hours")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Reports")]),
+        Spans::from(vec![Span::raw("r: Open reports")]),
+        Spans::from(vec![Span::raw("Left/Right: Choose the sprint for the burndown chart")]),
+        Spans::from(vec![Span::raw("Up/Down: Choose the week for the timesheet")]),
+        Spans::from(vec![Span::raw("w: Write the status report of that week to an HTML file for mailing")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Projects")]),
+        Spans::from(vec![Span::raw("p: Open the projects, Enter switches to the selected one (scrum --project <name> starts in it)")]),
+        Spans::from(vec![Span::raw("n: Register a directory as a project, Delete: Remove it from the registry (the files are kept)")]),
+        Spans::from(vec![Span::raw("m: Move the selected ticket to another project (Enter), or copy it (c), Enter on a moved ticket opens it where it is now")]),
+        Spans::from(vec![Span::raw("k: Set the key prefix of the project in use, such as OPS for OPS-42 (old prefixes keep working)")]),
+        Spans::from(vec![Span::raw("Every project has its own dbsettings.json (priorities, workflow names) and can have its own templates.json")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Backups")]),
+        Spans::from(vec![Span::raw("Shift + b: Open the snapshots of the database (one is taken on every start)")]),
+        Spans::from(vec![Span::raw("Tab: Switch between the snapshots and the changes since the selected one")]),
+        Spans::from(vec![Span::raw("n: Take a snapshot now, Delete: Remove the selected snapshot")]),
+        Spans::from(vec![Span::raw("Shift + r: Restore the whole snapshot, Enter (on a change): Restore only that ticket")]),
         Spans::from(vec![Span::raw("")]),
         Spans::from(vec![Span::raw("Sorting")]),
         Spans::from(vec![Span::raw("F1: Sort by ID")]),
         Spans::from(vec![Span::raw("F2: Sort by Title")]),
         Spans::from(vec![Span::raw("F3: Sort by Priority")]),
         Spans::from(vec![Span::raw("F4: Sort by Last Updated")]),
+        Spans::from(vec![Span::raw("F5: Sort by Due date")]),
+        Spans::from(vec![Span::raw("F6: Sort by a custom field, press again for the next field")]),
     ])
     .alignment(Alignment::Center)
     .block(
@@ -268,4 +663,506 @@ pub fn render_help_form<'a>(app: &'a mut AppState) -> Paragraph<'a> {
     );
 
     help
-}
\ No newline at end of file
+}
+
+pub fn render_board<'a>(app: &AppState) -> Vec<List<'a>> {
+
+    board_columns(app)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (status, tickets))| {
+            //Two line card: id and title, then priority and assignee
+            let cards: Vec<ListItem> = tickets
+                .iter()
+                .map(|ticket| {
+                    ListItem::new(vec![
+                        Spans::from(vec![
+                            Span::styled(format!("{} ", app.settings.ticket_ref(ticket.id)), Style::default().fg(app.theme.selection)),
+                            Span::raw(ticket.title.clone()),
+                        ]),
+                        Spans::from(vec![
+                            Span::raw(format!("   {} | ", ticket.priority)),
+                            Span::raw(ticket.assignee.clone().unwrap_or_else(|| "Unassigned".to_owned())),
+                            Span::raw(ticket.estimate.map(|estimate| format!(" | {}", app.settings.estimate_unit.format(estimate))).unwrap_or_default()),
+                        ]),
+                    ])
+                })
+                .collect();
+
+            List::new(cards)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!(" {} ({})", app.settings.status_name(&status), tickets.len()))
+                        .border_style(Style::default().fg(if i == app.board_column {app.theme.selection} else {app.theme.text})),
+                )
+                .style(Style::default().fg(app.theme.text))
+                .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text))
+        })
+        .collect()
+}
+
+pub fn render_sprints<'a>(app: &AppState) -> (List<'a>, List<'a>, List<'a>) {
+
+    let today = Local::now().naive_local().date();
+    let current = Sprint::current(&app.sprints, today).map(|sprint| sprint.id);
+    let focus_style = |focus: SprintFocus| Style::default().fg(if app.sprint_focus == focus {app.theme.selection} else {app.theme.text});
+
+    let sprintrows: Vec<ListItem> = app.sprints
+        .iter()
+        .map(|sprint| {
+            let state = if sprint.closed {" (closed)"} else if Some(sprint.id) == current {" (current)"} else {""};
+            ListItem::new(vec![
+                Spans::from(vec![
+                    Span::raw(sprint.name.clone()),
+                    Span::styled(state, Style::default().fg(app.theme.selection)),
+                ]),
+                Spans::from(vec![Span::raw(format!("   {} - {}", sprint.start_date.format("%Y-%m-%d"), sprint.end_date.format("%Y-%m-%d")))]),
+            ])
+        })
+        .collect();
+
+    let sprintlist = List::new(sprintrows)
+        .block(Block::default().borders(Borders::ALL).title(" Sprints (n: new, x: close)").border_style(focus_style(SprintFocus::Sprints)))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    let ticket_row = |ticket: &Tickets| {
+        let done = if ticket.status == TicketStatus::Closed {" (closed)"} else {""};
+        ListItem::new(Spans::from(vec![
+            Span::styled(format!("{} ", app.settings.ticket_ref(ticket.id)), Style::default().fg(app.theme.selection)),
+            Span::raw(format!("{} [{}]{}", ticket.title, ticket.priority, done)),
+        ]))
+    };
+
+    let unit = &app.settings.estimate_unit;
+    let backlog_tickets = backlog_tickets(app);
+    let backlogrows: Vec<ListItem> = backlog_tickets.iter().map(ticket_row).collect();
+    let backlog = List::new(backlogrows)
+        .block(Block::default().borders(Borders::ALL).title(format!(" Backlog ({})", unit.format(total_estimate(&backlog_tickets)))).border_style(focus_style(SprintFocus::Backlog)))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    let sprint_tickets = sprint_tickets(app);
+    let sprint_title = match selected_sprint(app) {
+        Some(sprint) if sprint.goal.is_empty() => format!(" {}", sprint.name),
+        Some(sprint) => format!(" {}: {}", sprint.name, sprint.goal),
+        None => " No sprint selected".to_string(),
+    };
+
+    //Committed work against the team capacity (zero means no capacity is configured)
+    let committed = total_estimate(&sprint_tickets);
+    let capacity = app.settings.team_capacity;
+    let over_capacity = capacity > 0.0 && committed > capacity;
+    let title = Spans::from(vec![
+        Span::raw(sprint_title),
+        Span::raw(if capacity > 0.0 {
+            format!(" | Committed {} of {} ", unit.format(committed), unit.format(capacity))
+        } else {
+            format!(" | Committed {} ", unit.format(committed))
+        }),
+        Span::styled(if over_capacity {"OVER CAPACITY "} else {""}, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+    ]);
+
+    let sprintticketrows: Vec<ListItem> = sprint_tickets.iter().map(ticket_row).collect();
+    let sprinttickets = List::new(sprintticketrows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(if over_capacity {Style::default().fg(Color::Red)} else {focus_style(SprintFocus::Sprint)}),
+        )
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    (sprintlist, backlog, sprinttickets)
+}
+
+pub fn render_sprint_form<'a>(app: &AppState) -> (Paragraph<'a>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>) {
+
+    let input = |text: String, title: &'a str, item: SprintItem| {
+        Paragraph::new(text)
+            .style(Style::default().fg(if app.sprint_edit_focus == item {app.theme.selection} else {app.theme.text}))
+            .block(Block::default().borders(Borders::ALL).title(title))
+    };
+
+    let nameinput = input(app.edit_sprint.name.clone(), "Name", SprintItem::Name);
+    let goalinput = input(app.edit_sprint.goal.clone(), "Goal", SprintItem::Goal);
+    let startinput = input(app.sprint_start_input.clone(), "Start date (YYYY-MM-DD)", SprintItem::Start);
+    let endinput = input(app.sprint_end_input.clone(), "End date (YYYY-MM-DD)", SprintItem::End);
+
+    //Validation errors from add_sprint
+    let prompt = Paragraph::new(app.prompt.clone())
+        .style(Style::default().fg(app.theme.selection));
+
+    (nameinput, goalinput, startinput, endinput, prompt)
+}
+
+pub fn render_reports<'a>(app: &AppState, burndown: &'a Burndown, throughput: &'a Throughput, priorities: &'a [(&'a str, u64)]) -> (Chart<'a>, Sparkline<'a>, Sparkline<'a>, BarChart<'a>) {
+
+    let unit = if burndown.estimated {app.settings.estimate_unit.to_string()} else {"Tickets"}.to_string();
+    let (title, start, end) = match &burndown.sprint {
+        Some(sprint) => (
+            format!(" Burndown: {} (Left/Right to change sprint)", sprint.name),
+            sprint.start_date.format("%Y-%m-%d").to_string(),
+            sprint.end_date.format("%Y-%m-%d").to_string(),
+        ),
+        None => (" Burndown: no sprints planned".to_string(), String::new(), String::new()),
+    };
+
+    let datasets = vec![
+        Dataset::default()
+            .name("Remaining")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.selection))
+            .data(&burndown.remaining),
+        Dataset::default()
+            .name("Ideal")
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(app.theme.text))
+            .data(&burndown.ideal),
+    ];
+
+    let burndownchart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(Style::default().fg(app.theme.text))
+        .x_axis(
+            Axis::default()
+                .title("Day")
+                .bounds([0.0, burndown.days])
+                .labels(vec![Span::raw(start), Span::raw(end)]),
+        )
+        .y_axis(
+            Axis::default()
+                .title(unit)
+                .bounds([0.0, burndown.total])
+                .labels(vec![Span::raw("0"), Span::raw(format!("{}", burndown.total))]),
+        );
+
+    let opened_total: u64 = throughput.opened.iter().sum();
+    let closed_total: u64 = throughput.closed.iter().sum();
+
+    let openedline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(format!(" Opened per week, last {} weeks ({} total)", THROUGHPUT_WEEKS, opened_total)))
+        .style(Style::default().fg(app.theme.selection))
+        .data(&throughput.opened);
+
+    let closedline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(format!(" Closed per week, last {} weeks ({} total)", THROUGHPUT_WEEKS, closed_total)))
+        .style(Style::default().fg(app.theme.text))
+        .data(&throughput.closed);
+
+    let prioritychart = BarChart::default()
+        .block(Block::default().borders(Borders::ALL).title(" Open tickets by priority"))
+        .data(priorities)
+        .bar_width(8)
+        .bar_gap(2)
+        .bar_style(Style::default().fg(app.theme.selection))
+        .value_style(Style::default().fg(app.theme.selection_text).bg(app.theme.selection))
+        .label_style(Style::default().fg(app.theme.text));
+
+    (burndownchart, openedline, closedline, prioritychart)
+}
+
+pub fn render_link_picker<'a>(app: &AppState) -> (Paragraph<'a>, List<'a>) {
+
+    let kind = selected_link_kind(app);
+    let hint = if app.close_as_duplicate {""} else {"  (Left/Right to change)"};
+    let kindinput = Paragraph::new(Spans::from(vec![
+        Span::styled(format!("{} {} ", app.settings.ticket_ref(app.edit_ticket.id), kind.to_string()), Style::default().fg(app.theme.selection)),
+        Span::raw(hint),
+    ]))
+    .style(Style::default().fg(app.theme.text))
+    .block(Block::default().borders(Borders::ALL).title(if app.close_as_duplicate {"Close as duplicate of"} else {"Link type"}));
+
+    //Tickets already linked with the chosen kind are marked, picking them again removes the link
+    let rows: Vec<ListItem> = link_candidates(app)
+        .iter()
+        .map(|ticket| {
+            let linked = app.edit_ticket.links.contains(&TicketLink{ kind: kind.clone(), target: ticket.id });
+            ListItem::new(Spans::from(vec![
+                Span::raw(if linked {"[x] "} else {"[ ] "}),
+                Span::styled(format!("{} ", app.settings.ticket_ref(ticket.id)), Style::default().fg(app.theme.selection)),
+                Span::raw(format!("{} ({})", ticket.title, ticket.status.to_string())),
+            ]))
+        })
+        .collect();
+
+    let ticketlist = List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title("Pick ticket (Enter to select)"))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    (kindinput, ticketlist)
+}
+
+pub fn render_duplicate_warning<'a>(app: &AppState) -> (Paragraph<'a>, List<'a>) {
+
+    let candidates = duplicate_candidates(app);
+    let warning = Paragraph::new(vec![
+        Spans::from(vec![
+            Span::raw(format!("\"{}\" looks like {} open ticket(s).", app.edit_ticket.title, candidates.len())),
+        ]),
+        Spans::from(vec![
+            Span::styled("Enter/l: ", Style::default().fg(app.theme.selection)),
+            Span::raw("Link to the selected ticket and save | "),
+            Span::styled("p: ", Style::default().fg(app.theme.selection)),
+            Span::raw("Save anyway | "),
+            Span::styled("Esc: ", Style::default().fg(app.theme.selection)),
+            Span::raw("Back to the form"),
+        ]),
+    ])
+    .style(Style::default().fg(app.theme.text))
+    .block(Block::default().borders(Borders::ALL).title("Possible duplicates")).wrap(Wrap { trim: true });
+
+    let rows: Vec<ListItem> = candidates
+        .iter()
+        .map(|(ticket, score)| {
+            ListItem::new(vec![
+                Spans::from(vec![
+                    Span::styled(format!("{} ", app.settings.ticket_ref(ticket.id)), Style::default().fg(app.theme.selection)),
+                    Span::raw(ticket.title.clone()),
+                    Span::raw(format!(" ({:.0}% similar)", score * 100.0)),
+                ]),
+                Spans::from(vec![Span::raw(format!("   {}", ticket.description))]),
+            ])
+        })
+        .collect();
+
+    let list = List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title("Open tickets"))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    (warning, list)
+}
+
+pub fn render_timesheet<'a>(app: &AppState, timesheet: &Timesheet) -> Table<'a> {
+
+    let hours = |minutes: i64| if minutes == 0 {String::new()} else {format!("{}:{:02}", minutes / 60, minutes % 60)};
+
+    let mut header = vec![Cell::from("User")];
+    for day in 0..7 {
+        header.push(Cell::from((timesheet.week_start + Duration::days(day)).format("%a").to_string()));
+    }
+    header.push(Cell::from("Total"));
+
+    let rows: Vec<Row> = timesheet.rows
+        .iter()
+        .map(|(user, days)| {
+            let mut cells = vec![Cell::from(user.clone())];
+            cells.extend(days.iter().map(|minutes| Cell::from(hours(*minutes))));
+            cells.push(Cell::from(hours(days.iter().sum())));
+            Row::new(cells)
+        })
+        .collect();
+
+    Table::new(rows)
+        .header(Row::new(header).style(Style::default().fg(app.theme.selection).add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title(format!(" Timesheet (h:mm): week of {} (Up/Down)", timesheet.week_start.format("%Y-%m-%d"))))
+        .style(Style::default().fg(app.theme.text))
+        .widths(&[
+            Constraint::Min(8),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(6),
+        ])
+}
+
+pub fn render_fields<'a>(app: &AppState) -> (List<'a>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>) {
+
+    let rows: Vec<ListItem> = app.settings.fields
+        .iter()
+        .map(|field| {
+            let options = match &field.kind {
+                FieldKind::Enum(options) => format!(": {}", options.join(", ")),
+                _ => String::new(),
+            };
+            ListItem::new(Spans::from(vec![
+                Span::styled(field.name.clone(), Style::default().fg(app.theme.selection)),
+                Span::raw(format!(" ({}{})", field.kind.to_string(), options)),
+            ]))
+        })
+        .collect();
+
+    let fieldlist = List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(" Fields (n: new, Delete: remove)"))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    let focus_style = |focus: FieldItem| Style::default().fg(if app.field_focus == focus {app.theme.selection} else {app.theme.text});
+
+    let nameinput = Paragraph::new(app.field_name_input.clone())
+        .style(focus_style(FieldItem::Name))
+        .block(Block::default().borders(Borders::ALL).title("Name"));
+
+    let kindinput = Paragraph::new(format!("< {} >", FIELD_KINDS[app.field_kind]))
+        .style(focus_style(FieldItem::Kind))
+        .block(Block::default().borders(Borders::ALL).title("Kind (Left/Right to change)"));
+
+    let optionsinput = Paragraph::new(app.field_options_input.clone())
+        .style(focus_style(FieldItem::Options))
+        .block(Block::default().borders(Borders::ALL).title("Options, comma separated (Enum only)"));
+
+    //Errors while the form is open, otherwise what the fields are for
+    let text = if app.field_form {
+        app.prompt.clone()
+    } else {
+        "Custom fields are stored with this database and show up in the edit form of every ticket. Values are checked when the ticket is saved.".to_string()
+    };
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(if app.field_form {Color::Red} else {app.theme.text}))
+        .block(Block::default().borders(if app.field_form {Borders::NONE} else {Borders::ALL}))
+        .wrap(Wrap { trim: true });
+
+    (fieldlist, nameinput, kindinput, optionsinput, prompt)
+}
+
+pub fn render_template_picker<'a>(app: &AppState) -> (List<'a>, Paragraph<'a>) {
+
+    let mut rows = vec![ListItem::new(Span::raw("Blank ticket"))];
+    rows.extend(app.templates.iter().map(|template| ListItem::new(Span::raw(template.name.clone()))));
+
+    let path = templates_path().map(|path| path.display().to_string()).unwrap_or_default();
+    let templatelist = List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(" Templates (Enter to use)"))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    //What the picked template fills in
+    let label = |text: &str| Span::styled(text.to_string(), Style::default().fg(app.theme.selection));
+    let mut text = Vec::new();
+    match app.template_list_state.selected().unwrap_or(0).checked_sub(1).and_then(|index| app.templates.get(index)) {
+        Some(template) => {
+            let tags: Vec<String> = template.tags.iter().cloned().collect();
+            text.push(Spans::from(vec![label("Title: "), Span::raw(template.title.clone())]));
+            text.push(Spans::from(vec![label("Priority: "), Span::raw(template.priority.clone())]));
+            text.push(Spans::from(vec![label("Tags: "), Span::raw(tags.join(", "))]));
+            if let Some(recurrence) = &template.recurrence {
+                let next = match recurrence.validate() {
+                    Ok(()) => recurrence.next_occurrence(Local::now().naive_local().date())
+                        .map(|next| format!(", next on {}", next.format("%a %Y-%m-%d")))
+                        .unwrap_or_default(),
+                    Err(error) => format!(" ({})", error),
+                };
+                text.push(Spans::from(vec![label("Repeats: "), Span::raw(format!("{}{}", recurrence.describe(), next))]));
+            }
+            text.push(Spans::from(vec![label("Description:")]));
+            text.extend(template.description.lines().map(|line| Spans::from(Span::raw(line.to_string()))));
+            text.push(Spans::from(vec![label("Checklist:")]));
+            text.extend(template.checklist.iter().map(|item| Spans::from(Span::raw(format!("- {}", item)))));
+        }
+        None => text.push(Spans::from(Span::raw("Start from an empty form"))),
+    }
+    text.push(Spans::from(Span::raw("")));
+    text.push(Spans::from(vec![label("Templates file: "), Span::raw(path)]));
+
+    let preview = Paragraph::new(text)
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title(" Preview"))
+        .wrap(Wrap { trim: false });
+
+    (templatelist, preview)
+}
+
+pub fn render_attachments<'a>(app: &AppState) -> List<'a> {
+
+    let rows: Vec<ListItem> = app.edit_ticket.attachments
+        .iter()
+        .map(|attachment| ListItem::new(Span::raw(attachment_text(attachment))))
+        .collect();
+
+    List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(format!(" Attachments of {} {} (Enter to open, Delete to remove)", app.settings.ticket_ref(app.edit_ticket.id), app.edit_ticket.title)))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text))
+}
+
+//The prefix of the project in use and the old ones that still work, such as " OPS, was IT"
+fn prefix_hint(app: &AppState) -> String {
+    if app.settings.key_prefix.is_empty() {
+        return String::new();
+    }
+    let mut hint = format!(" {}", app.settings.key_prefix);
+    if !app.settings.key_aliases.is_empty() {
+        hint.push_str(&format!(", was {}", app.settings.key_aliases.join(", ")));
+    }
+    hint
+}
+
+pub fn render_projects<'a>(app: &AppState) -> List<'a> {
+
+    let current = current_project().map(|project| project.name);
+    let marker = |name: Option<&String>| if current.as_ref() == name {" (in use)"} else {""};
+
+    let mut rows = vec![ListItem::new(Spans::from(vec![
+        Span::raw("Working directory"),
+        Span::styled(marker(None), Style::default().fg(app.theme.selection)),
+    ]))];
+    rows.extend(app.projects.iter().map(|project| {
+        ListItem::new(Spans::from(vec![
+            Span::raw(format!("{}  {}", project.name, project.path.display())),
+            Span::styled(marker(Some(&project.name)), Style::default().fg(app.theme.selection)),
+        ]))
+    }));
+
+    List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(format!(" Projects (Enter to switch, n: new, Delete to remove, k: key prefix{})", prefix_hint(app))))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text))
+}
+
+pub fn render_transfer_targets<'a>(app: &AppState) -> List<'a> {
+
+    let key = selected_ticket(app).map(|ticket| app.settings.ticket_key(ticket.id)).unwrap_or_default();
+    let rows: Vec<ListItem> = app.transfer_targets
+        .iter()
+        .map(|target| ListItem::new(Span::raw(match target {
+            Some(project) => format!("{}  {}", project.name, project.path.display()),
+            None => "Working directory".to_string(),
+        })))
+        .collect();
+
+    List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(format!(" Move {} to (Enter to move, c to copy)", key)))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text))
+}
+
+pub fn render_backups<'a>(app: &AppState) -> (List<'a>, List<'a>) {
+
+    let focus_style = |focus: bool| Style::default().fg(if app.snapshot_focus_diff == focus {app.theme.selection} else {app.theme.text});
+
+    let snapshotrows: Vec<ListItem> = app.snapshots
+        .iter()
+        .map(|snapshot| ListItem::new(Span::raw(snapshot.taken_at.format("%Y-%m-%d %H:%M:%S").to_string())))
+        .collect();
+
+    let retention = &app.settings.backup_retention;
+    let snapshotlist = List::new(snapshotrows)
+        .block(Block::default().borders(Borders::ALL)
+            .title(format!(" Snapshots (keeping {} latest, {} daily, {} weekly)", retention.last, retention.daily, retention.weekly))
+            .border_style(focus_style(false)))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    let diffrows: Vec<ListItem> = if app.snapshot_diff.is_empty() && !app.snapshots.is_empty() {
+        vec![ListItem::new(Span::raw("No changes since this snapshot"))]
+    } else {
+        app.snapshot_diff.iter().map(|diff| ListItem::new(Span::raw(diff.describe(&app.settings)))).collect()
+    };
+
+    let difflist = List::new(diffrows)
+        .block(Block::default().borders(Borders::ALL).title(" Changes since then (Enter restores the ticket)").border_style(focus_style(true)))
+        .style(Style::default().fg(app.theme.text))
+        .highlight_style(Style::default().bg(app.theme.selection).fg(app.theme.selection_text));
+
+    (snapshotlist, difflist)
+}
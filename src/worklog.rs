@@ -0,0 +1,69 @@
+//For time tracking: starting and stopping timers on tickets
+
+use chrono::Utc;
+use scrum_lib::*;
+use crate::db::*;
+use crate::app::*;
+use crate::ticket::*;
+
+//Name work is logged under
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+//Ticket the current user has a timer running on
+pub fn running_timer(app: &AppState) -> Option<Tickets> {
+    let user = current_user();
+    all_tickets(app)
+        .into_iter()
+        .find(|ticket| ticket.timer.as_ref().is_some_and(|timer| timer.user == user))
+}
+
+//Starts a timer on the selected ticket, or asks for a comment to stop it if it is already running
+pub fn toggle_timer(app: &mut AppState) -> Result<(), Error> {
+    app.notice = String::new();
+    if let Some(mut ticket) = selected_ticket(app).filter(|ticket| !moved_notice(app, ticket)) {
+        let user = current_user();
+        match &ticket.timer {
+            Some(timer) if timer.user == user => {
+                app.edit_ticket = ticket;
+                app.messages = Vec::new();
+                app.input = String::new();
+                app.prompt = "Comment for the logged time (Enter to save)".to_string();
+                app.active_menu_item = MenuItem::WorkLogForm;
+                return Ok(());
+            }
+            Some(timer) => {
+                app.notice = format!("{} has a timer running on ticket {}", timer.user, app.settings.ticket_key(ticket.id));
+                return Ok(());
+            }
+            None => {}
+        }
+        //Only one timer per user
+        if let Some(running) = running_timer(app) {
+            app.notice = format!("A timer is already running on ticket {}", app.settings.ticket_key(running.id));
+            return Ok(());
+        }
+        ticket.timer = Some(RunningTimer{ user, started_at: Utc::now() });
+        replace_ticket(app, ticket);
+        update_db(app);
+        update_ticket_count(app);
+    }
+    Ok(())
+}
+
+//Logs the time of the running timer with the typed comment
+pub fn stop_timer(app: &mut AppState) -> Result<(), Error> {
+    let comment = app.input.trim().to_string();
+    app.edit_ticket.stop_timer(comment, Utc::now());
+    replace_ticket(app, app.edit_ticket.clone());
+    update_db(app);
+    update_ticket_count(app);
+
+    app.edit_ticket = Tickets::default();
+    app.input = String::new();
+    app.active_menu_item = MenuItem::Tickets;
+    Ok(())
+}